@@ -62,8 +62,8 @@ impl<S: ApplicationState + 'static> Application<S> {
                     let size = PhysicalSize { width: 800u32, height: 600u32 };
                     let pos = PhysicalPosition { x: (mon.width - size.width) / 2, y: (mon.height - size.height) / 2 };
 
-                    let _ = self.window.set_inner_size(size);
-                    let _ = self.window.set_outer_position(pos);
+                    self.window.set_inner_size(size);
+                    self.window.set_outer_position(pos);
                 },
                 Event::RedrawRequested(_) => {
                     self.state.update();
@@ -5,8 +5,16 @@ use winit::{
     }
 };
 
+use crate::{model::Plane, prelude::{Point2d, Point3d}};
+
 const MOVE_MULTIPLIER: f32 = 10.0;
 
+/// vertical field of view
+const FOV: f32 = std::f32::consts::PI / 2.0;
+
+/// scale applied to model coordinates by the vertex shader
+const MODEL_SCALE: f32 = 0.005;
+
 pub const UPDATE_DISTANCE: f32 = 0.01;
 const UP: (f32, f32, f32) = (0.0, 1.0, 0.0);
 
@@ -69,12 +77,112 @@ impl CameraState {
         self.rotation = dir;
     }
 
+    /// rotate the model so that the plane faces the camera, with its X axis pointing right
+    pub fn face_plane(&mut self, plane: &crate::model::Plane) {
+        // the model is rotated by Rx * Ry * Rz, which must map the plane axes onto the screen axes,
+        //  so its rows are the x axis, y axis and normal of the plane
+        let (x, y, n) = (plane.x_axis(), plane.y_axis(), plane.normal());
+
+        let b = x.z.clamp(-1.0, 1.0).asin();
+        let (a, c) = match b.cos() {
+            // gimbal lock, where only a + c or a - c matters, so the Z rotation is dropped
+            cos if cos < 1e-4 => ((x.z.signum() * y.x).atan2(y.y), 0.0),
+            _ => ((-y.z).atan2(n.z), (-x.y).atan2(x.x))
+        };
+
+        self.rotation = (a, b, c);
+        self.normalize();
+    }
+
+    /// world position under a window position, on the given plane
+    ///
+    /// Returns `None` if the cursor ray is parallel to the plane or the plane is behind the camera.
+    pub fn unproject(&self, screen: &Point2d, plane: &Plane) -> Option<Point3d> {
+        let (origin, direction) = self.ray(screen);
+
+        let along = direction.dot(&plane.normal());
+        if along.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let t = (plane.origin() - origin).dot(&plane.normal()) / along;
+        if t < 0.0 {
+            return None;
+        }
+
+        Some(origin + direction * t)
+    }
+
+    /// cursor ray in model coordinates, as its origin at the eye and its direction
+    pub fn ray(&self, screen: &Point2d) -> (Point3d, Point3d) {
+        // normalized device coordinates
+        let ndc_x = 2.0 * screen.x / self.width - 1.0;
+        let ndc_y = 1.0 - 2.0 * screen.y / self.height;
+
+        // undo the perspective divide, with the view direction at depth 1
+        let f = 1.0 / (FOV / 2.0).tan();
+        let (dx, dy) = (ndc_x * self.get_aspect_ratio() / f, ndc_y / f);
+
+        let (s, u, forward) = self.basis();
+        let position = Point3d::new(self.position.0, self.position.1, self.position.2);
+
+        let origin = self.unrotate(position) / MODEL_SCALE;
+        let direction = self.unrotate(s * dx + u * dy + forward).normalize();
+
+        (origin, direction)
+    }
+
+    /// window position of a point in model coordinates, or `None` if it is behind the camera
+    pub fn project(&self, point: &Point3d) -> Option<Point2d> {
+        let mul = |m: [[f32; 4]; 4], v: [f32; 4]| -> [f32; 4] {
+            std::array::from_fn(|row| (0..4).map(|col| m[col][row] * v[col]).sum())
+        };
+
+        let p = *point * MODEL_SCALE;
+        let v = [p.x, p.y, p.z, 1.0];
+        let v = mul(self.get_z_rotation(), v);
+        let v = mul(self.get_y_rotation(), v);
+        let v = mul(self.get_x_rotation(), v);
+        let v = mul(self.get_view(), v);
+        let clip = mul(self.get_perspective(), v);
+
+        if clip[3] <= 0.0 {
+            return None;
+        }
+
+        Some(Point2d::new(
+            (clip[0] / clip[3] + 1.0) / 2.0 * self.width,
+            (1.0 - clip[1] / clip[3]) / 2.0 * self.height,
+        ))
+    }
+
+    /// right, up and forward directions of the view
+    fn basis(&self) -> (Point3d, Point3d, Point3d) {
+        let f = Point3d::new(self.direction.0, self.direction.1, self.direction.2).normalize();
+        let s = f.cross(&Point3d::new(UP.0, UP.1, UP.2)).normalize();
+        let u = s.cross(&f);
+
+        (s, u, f)
+    }
+
+    /// inverse of the model rotation, `(Rx * Ry * Rz)^T`
+    fn unrotate(&self, v: Point3d) -> Point3d {
+        // multiplying by the transpose, using column-major matrices
+        let transposed = |m: [[f32; 4]; 4], v: Point3d| Point3d::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        );
+
+        transposed(self.get_z_rotation(), transposed(self.get_y_rotation(), transposed(self.get_x_rotation(), v)))
+    }
+
     pub fn get_aspect_ratio(&self) -> f32 {
         self.width / self.height
     }
 
     pub fn get_perspective(&self) -> [[f32; 4]; 4] {
-        let fov: f32 = 3.141592 / 2.0;
+        let fov = FOV;
         let zfar = 1.0;
         let znear = 0.00001;
 
@@ -272,7 +380,7 @@ impl CameraState {
                     // TODO: refactor to impl this better
                     // TODO: movement about axes, given current camera position
                     // x-movement about the x-axis
-                    self.moving.0 = (x / x.abs() * -1.0) as i8;
+                    self.moving.0 = -(x / x.abs()) as i8;
                     self.update(x.abs() * MOVE_MULTIPLIER / 5.0);
                     self.moving.0 = 0;
                     // y-movement about the z-axis
@@ -330,3 +438,80 @@ impl Default for CameraState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rx * Ry * Rz applied to a point, matching the vertex shader
+    fn rotate(camera: &CameraState, p: Point3d) -> Point3d {
+        let apply = |m: [[f32; 4]; 4], p: Point3d| Point3d::new(
+            m[0][0] * p.x + m[1][0] * p.y + m[2][0] * p.z,
+            m[0][1] * p.x + m[1][1] * p.y + m[2][1] * p.z,
+            m[0][2] * p.x + m[1][2] * p.y + m[2][2] * p.z,
+        );
+
+        apply(camera.get_x_rotation(), apply(camera.get_y_rotation(), apply(camera.get_z_rotation(), p)))
+    }
+
+    #[test]
+    fn faces_plane() {
+        let planes = [
+            Plane::xy(),
+            Plane::yz(),
+            Plane::zx(),
+            Plane::new(Point3d::default(), Point3d::new(0.0, -1.0, 0.0)),
+            Plane::new(Point3d::default(), Point3d::new(1.0, -2.0, 0.5)),
+        ];
+
+        for plane in planes {
+            let mut camera = CameraState::new();
+            camera.face_plane(&plane);
+
+            for (axis, screen) in [(plane.x_axis(), [1.0, 0.0, 0.0]), (plane.y_axis(), [0.0, 1.0, 0.0]), (plane.normal(), [0.0, 0.0, 1.0])] {
+                let rotated = rotate(&camera, axis);
+                assert!((rotated - Point3d::from(screen)).length() < 1e-5, "{:?}: {:?} -> {:?}", plane, axis, rotated);
+            }
+        }
+    }
+
+    #[test]
+    fn unprojects_onto_plane() {
+        let mut camera = CameraState::new();
+
+        // looking straight down at the XY plane, the center of the window is the origin
+        let center = Point2d::new(camera.width / 2.0, camera.height / 2.0);
+        let hit = camera.unproject(&center, &Plane::xy()).unwrap();
+        assert!(hit.length() < 1e-3, "{:?}", hit);
+
+        camera.set_position((0.1, -0.05, 0.3));
+        camera.set_rotation((0.4, 5.9, 1.2));
+
+        let planes = [
+            Plane::xy(),
+            Plane::yz().at(Point3d::new(2.0, 0.0, 0.0)),
+            Plane::new(Point3d::new(1.0, 1.0, -3.0), Point3d::new(1.0, 2.0, 3.0)),
+        ];
+        for plane in planes {
+            let mut hits = 0;
+            for screen in [Point2d::new(100.0, 650.0), Point2d::new(512.0, 384.0), Point2d::new(900.0, 20.0)] {
+                let Some(hit) = camera.unproject(&screen, &plane) else { continue };
+                assert!(plane.distance(&hit).abs() < 1e-2, "{:?} is off {:?}", hit, plane);
+
+                let back = camera.project(&hit).unwrap();
+                assert!((back.x - screen.x).abs() < 0.5 && (back.y - screen.y).abs() < 0.5, "{:?} -> {:?}", screen, back);
+                hits += 1;
+            }
+            assert!(hits > 0, "no ray hit {:?}", plane);
+        }
+    }
+
+    #[test]
+    fn parallel_plane_is_missed() {
+        let camera = CameraState::new();
+
+        // the camera looks along -Z, so every ray through the center lies in the YZ plane
+        let center = Point2d::new(camera.width / 2.0, camera.height / 2.0);
+        assert_eq!(camera.unproject(&center, &Plane::yz()), None);
+    }
+}
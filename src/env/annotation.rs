@@ -0,0 +1,127 @@
+
+use crate::{prelude::*, model::{Constraint, Shape, Sketch}};
+
+/// dimension as drawn in the sketcher, in plane coordinates
+#[derive(Debug)]
+pub struct Annotation {
+    /// leader and extension lines
+    pub lines: Vec<(Point2d, Point2d)>,
+    /// where the value is written
+    pub label: Point2d,
+    pub text: String,
+}
+
+/// annotation of a dimension, or `None` for geometric constraints
+///
+/// `size` is the gap between the geometry and the annotation.
+pub fn annotation(sketch: &Sketch, constraint: &Constraint, size: f32) -> Option<Annotation> {
+    // radii and diameters are drawn at 45°
+    let diagonal = Point2d::new(1.0, 1.0) / 2f32.sqrt();
+
+    match *constraint {
+        Constraint::Distance { a, b, value } => {
+            let (a, b) = (sketch.point(a), sketch.point(b));
+            let d = b - a;
+            let offset = match d.length() {
+                len if len <= f32::EPSILON => Point2d::new(0.0, size),
+                len => d.perp() / len * size,
+            };
+
+            Some(Annotation {
+                lines: vec![(a, a + offset), (b, b + offset), (a + offset, b + offset)],
+                label: (a + b) / 2.0 + offset * 2.0,
+                text: format!("{:.2}", value),
+            })
+        },
+        Constraint::Radius { element, value } | Constraint::Diameter { element, value } => {
+            let center = match sketch.elements()[element].shape {
+                Shape::Circle { center, .. } | Shape::Arc { center, .. } => sketch.point(center),
+                Shape::Line { .. } => return None,
+            };
+            let radius = sketch.radius(element)?;
+            let rim = center + diagonal * radius;
+
+            let (start, text) = match constraint {
+                Constraint::Radius { .. } => (center, format!("R{:.2}", value)),
+                _ => (center - diagonal * radius, format!("⌀{:.2}", value)),
+            };
+
+            Some(Annotation {
+                lines: vec![(start, rim), (rim, rim + diagonal * size)],
+                label: rim + diagonal * size * 2.0,
+                text,
+            })
+        },
+        Constraint::Angle { a, b, value } => {
+            let (da, db) = (sketch.direction(a)?, sketch.direction(b)?);
+            let start = match sketch.elements()[a].shape {
+                Shape::Line { start, .. } => sketch.point(start),
+                _ => return None,
+            };
+
+            // the arc is centered where the lines cross, or on the first line if they do not
+            let vertex = match da.cross(&db) {
+                cross if cross.abs() <= f32::EPSILON => start,
+                cross => {
+                    let other = match sketch.elements()[b].shape {
+                        Shape::Line { start, .. } => sketch.point(start),
+                        _ => return None,
+                    };
+
+                    start + da * ((other - start).cross(&db) / cross)
+                }
+            };
+
+            let radius = size * 3.0;
+            let from = da.angle();
+            let sweep = value.to_radians();
+            let at = |angle: f32| vertex + Point2d::new(angle.cos(), angle.sin()) * radius;
+
+            let steps = 16;
+            let lines = (0..steps)
+                .map(|i| (
+                    at(from + sweep * i as f32 / steps as f32),
+                    at(from + sweep * (i + 1) as f32 / steps as f32),
+                ))
+                .collect();
+
+            let middle = from + sweep / 2.0;
+            Some(Annotation {
+                lines,
+                label: vertex + Point2d::new(middle.cos(), middle.sin()) * (radius + size),
+                text: format!("{:.1}°", value),
+            })
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Plane;
+
+    #[test]
+    fn distance_is_offset() {
+        let sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(4.0, 0.0)]);
+        let annotation = annotation(&sketch, &Constraint::Distance { a: 0, b: 1, value: 4.0 }, 1.0).unwrap();
+
+        assert_eq!(annotation.lines[2], (Point2d::new(0.0, 1.0), Point2d::new(4.0, 1.0)));
+        assert_eq!(annotation.label, Point2d::new(2.0, 2.0));
+        assert_eq!(annotation.text, "4.00");
+    }
+
+    #[test]
+    fn angle_is_centered_on_crossing() {
+        let mut sketch = Sketch::new();
+        let p = [(1.0, 0.0), (3.0, 0.0), (0.0, 1.0), (0.0, 3.0)].map(|(x, y)| sketch.add_point(Point2d::new(x, y)));
+        let a = sketch.add(Shape::Line { start: p[0], end: p[1] }, false);
+        let b = sketch.add(Shape::Line { start: p[2], end: p[3] }, false);
+
+        let arc = annotation(&sketch, &Constraint::Angle { a, b, value: 90.0 }, 1.0).unwrap();
+
+        assert!(arc.lines.iter().all(|(p, _)| (p.length() - 3.0).abs() < 1e-5));
+        assert!(arc.label.distance(&(Point2d::new(1.0, 1.0) / 2f32.sqrt() * 4.0)) < 1e-5);
+        assert!(annotation(&sketch, &Constraint::Horizontal(a), 1.0).is_none());
+    }
+}
@@ -1,12 +1,17 @@
 
+mod annotation;
 mod modeling;
 mod sketcher;
+mod snap;
+mod tool;
 
 use std::ops::{Deref, DerefMut};
 use std::sync::{Arc, Mutex};
 
 pub use modeling::Modeler;
 pub use sketcher::Sketcher;
+pub use snap::{SnapKind, SnapSettings};
+pub use tool::Tool;
 
 use crate::camera::CameraState;
 use crate::ui::UiDrawResult;
@@ -14,7 +19,7 @@ type Camera = Arc<Mutex<CameraState>>;
 
 pub enum ApplicationEnvironmentType {
     Modeling(Modeler),
-    Sketching(Sketcher),
+    Sketching(Box<Sketcher>),
 }
 
 impl Deref for ApplicationEnvironmentType {
@@ -23,7 +28,7 @@ impl Deref for ApplicationEnvironmentType {
     fn deref(&self) -> &Self::Target {
         match self {
             ApplicationEnvironmentType::Modeling(modeler) => modeler,
-            ApplicationEnvironmentType::Sketching(sketcher) => sketcher,
+            ApplicationEnvironmentType::Sketching(sketcher) => sketcher.as_ref(),
         }
     }
 }
@@ -32,7 +37,7 @@ impl DerefMut for ApplicationEnvironmentType {
     fn deref_mut(&mut self) -> &mut Self::Target {
         match self {
             ApplicationEnvironmentType::Modeling(modeler) => modeler,
-            ApplicationEnvironmentType::Sketching(sketcher) => sketcher,
+            ApplicationEnvironmentType::Sketching(sketcher) => sketcher.as_mut(),
         }
     }
 }
@@ -43,6 +48,12 @@ pub struct ApplicationEnvironment {
     env: ApplicationEnvironmentType
 }
 
+impl Default for ApplicationEnvironment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ApplicationEnvironment {
     pub fn new() -> Self {
         let camera = Arc::new(Mutex::new(CameraState::new()));
@@ -62,7 +73,7 @@ impl ApplicationEnvironment {
     }
 
     pub fn process_input(&mut self, event: &winit::event::WindowEvent) {
-        self.env.handle_window_event(&event);
+        self.env.handle_window_event(event);
     }
 }
 
@@ -85,4 +96,14 @@ pub trait ApplicationEnvironmentOps
 {
     fn draw_toolbar(&mut self, ui: &mut egui::Ui) -> Option<UiDrawResult>;
     fn handle_window_event(&mut self, event: &winit::event::WindowEvent);
+
+    /// line list of geometry that is drawn on top of the model while editing
+    fn preview(&self) -> Vec<crate::prelude::Vertex> {
+        Vec::new()
+    }
+
+    /// line list of selected geometry, drawn over the preview
+    fn highlight(&self) -> Vec<crate::prelude::Vertex> {
+        Vec::new()
+    }
 }
@@ -19,8 +19,8 @@ impl ApplicationEnvironmentOps for Modeler {
     fn draw_toolbar(&mut self, ui: &mut egui::Ui) -> Option<UiDrawResult> {
         if ui.button("+ Sketch").clicked() {
             log::trace!("Add sketch selected");
-            self.camera.lock().unwrap().set_rotation((0.0, 0.0, 0.0));
-            
+
+            // camera is turned to the sketch plane once it is chosen
             return Some(UiDrawResult::EnterSketcher);
         }
 
@@ -31,12 +31,60 @@ impl ApplicationEnvironmentOps for Modeler {
             return Some(UiDrawResult::ShowBlockDialog);
         }
 
+        if ui.button("+ Extrude").clicked() {
+            log::trace!("Add extrude selected");
+
+            return Some(UiDrawResult::ShowExtrudeDialog);
+        }
+
+        if ui.button("+ Revolve").clicked() {
+            log::trace!("Add revolve selected");
+
+            return Some(UiDrawResult::ShowRevolveDialog);
+        }
+
+        if ui.button("- Cut").clicked() {
+            log::trace!("Add cut selected");
+
+            return Some(UiDrawResult::ShowCutDialog);
+        }
+
+        if ui.button("- Hole").clicked() {
+            log::trace!("Add hole selected");
+
+            return Some(UiDrawResult::ShowHoleDialog);
+        }
+
+        if ui.button("Fillet").clicked() {
+            log::trace!("Add fillet selected");
+
+            return Some(UiDrawResult::ShowFilletDialog);
+        }
+
+        if ui.button("Chamfer").clicked() {
+            log::trace!("Add chamfer selected");
+
+            return Some(UiDrawResult::ShowChamferDialog);
+        }
+
+        if ui.button("Combine").clicked() {
+            log::trace!("Add combine selected");
+
+            return Some(UiDrawResult::ShowCombineDialog);
+        }
+
+        if ui.button("Pattern").clicked() {
+            log::trace!("Add pattern selected");
+
+            return Some(UiDrawResult::ShowPatternDialog);
+        }
+
         None
     }
 
     fn handle_window_event(&mut self, event: &winit::event::WindowEvent) {
         match self.camera.lock() {
-            Ok(mut camera) => camera.process_input(&event),
+            Ok(mut camera) => camera.process_input(event),
             Err(e) => log::error!("Failed to lock camera to handle WindowEvent<{:?}> because `{}`", event, e)
         }
     }
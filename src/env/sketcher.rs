@@ -1,15 +1,39 @@
 
 use winit::event::{WindowEvent, ElementState, MouseButton};
 
-use crate::{prelude::*, model::{ModelEntity, Sketch}, ui::UiDrawResult};
-use super::{ApplicationEnvironmentOps, ApplicationEnvironmentType};
+use crate::{prelude::*, model::{Constraint, ModelEntity, ModelEntityObject, Plane, Shape, Sketch, Status}, ui::UiDrawResult};
+use super::{ApplicationEnvironmentOps, ApplicationEnvironmentType, annotation::annotation, snap::{SnapCache, SnapSettings, Snapped}, tool::{Tool, ToolState}};
+
+/// distance in pixels within which a click picks sketch geometry
+const PICK_PIXELS: f32 = 8.0;
+
+/// sketch geometry picked with the select tool
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Selected {
+    Point(usize),
+    Element(usize),
+}
 
 #[derive(Debug, Default)]
 pub struct Sketcher {
     pub camera: super::Camera,
 
+    /// geometry drawn so far, in plane coordinates
+    sketch: Sketch,
+    tool: ToolState,
+    selection: Vec<Selected>,
+    status: Status,
+
+    pub snap: SnapSettings,
+    /// model edges projected onto the sketch plane, for snapping
+    edges: Vec<(Point2d, Point2d)>,
+    /// snap candidates, found once per sketch edit rather than for every snap
+    snap_cache: SnapCache,
+
+    /// cursor in window coordinates
     mouse_pos: Point2d,
-    points: Vec<Point2d>
+    /// finish was requested with a right click
+    finished: bool,
 }
 
 impl Sketcher {
@@ -17,19 +41,278 @@ impl Sketcher {
         Self::default()
     }
 
-    pub fn finalize(&self) {
-        log::error!("finalize not implemented")
+    /// sketch on `plane`, turning the camera to face it
+    pub fn on_plane(mut self, plane: Plane) -> Self {
+        match self.camera.lock() {
+            Ok(mut camera) => camera.face_plane(&plane),
+            Err(e) => log::error!("Failed to lock camera to face the sketch plane because `{}`", e)
+        }
+
+        self.sketch = Sketch::on_plane(plane);
+        self.status = self.sketch.solve();
+        self
+    }
+
+    /// snap to the given model edges, a line list, projected onto the sketch plane
+    pub fn with_edges(mut self, edges: &[Vertex]) -> Self {
+        self.edges = edges.chunks_exact(2)
+            .map(|pair| (
+                self.plane().local(&pair[0].position.into()),
+                self.plane().local(&pair[1].position.into()),
+            ))
+            .filter(|(a, b)| a != b)
+            .collect();
+        self.snap_cache = SnapCache::default();
+        self
+    }
+
+    pub fn plane(&self) -> &Plane {
+        self.sketch.plane()
+    }
+
+    /// cursor position projected onto the sketch plane, in plane coordinates
+    fn cursor_point(&self) -> Option<Point2d> {
+        let camera = match self.camera.lock() {
+            Ok(camera) => camera,
+            Err(e) => {
+                log::error!("Failed to lock camera to project the cursor because `{}`", e);
+                return None;
+            }
+        };
+
+        camera.unproject(&self.mouse_pos, self.plane())
+            .map(|p| self.plane().local(&p))
+    }
+
+    /// sketch distance covered by one window pixel, measured at the plane origin
+    fn pixel_size(&self) -> f32 {
+        let camera = match self.camera.lock() {
+            Ok(camera) => camera,
+            Err(e) => {
+                log::error!("Failed to lock camera to measure the pixel size because `{}`", e);
+                return 0.0;
+            }
+        };
+
+        camera.project(&self.plane().origin())
+            .and_then(|screen| camera.unproject(&(screen + Point2d::new(1.0, 0.0)), self.plane()))
+            .map(|p| self.plane().local(&p).length())
+            .unwrap_or_default()
+    }
+
+    fn pick_tolerance(&self) -> f32 {
+        PICK_PIXELS * self.pixel_size()
+    }
+
+    /// cursor point after snapping, or `None` if the cursor is off the sketch plane
+    fn snapped(&self) -> Option<Snapped> {
+        let cursor = self.cursor_point()?;
+        let tolerance = self.snap.tolerance * self.pixel_size();
+
+        let candidates = self.snap_cache.candidates(&self.snap, &self.sketch, &self.edges);
+
+        Some(self.snap.snap(cursor, tolerance, &self.sketch, &candidates, self.tool.last(&self.sketch)))
+    }
+
+    /// toggle the point or element under the cursor, or clear the selection if there is none
+    fn select(&mut self, cursor: Point2d) {
+        let tolerance = self.pick_tolerance();
+
+        // points win over the elements they belong to
+        let picked = match self.sketch.point_near(cursor, tolerance) {
+            Some(point) => Some(Selected::Point(point)),
+            None => self.sketch.element_near(cursor, tolerance).map(Selected::Element),
+        };
+
+        match picked {
+            Some(picked) => match self.selection.iter().position(|s| *s == picked) {
+                Some(index) => { self.selection.remove(index); },
+                None => self.selection.push(picked),
+            },
+            None => self.selection.clear(),
+        }
+    }
+
+    /// constraints that fit the selection, in toolbar order
+    ///
+    /// Dimensions start at the value they currently measure.
+    fn applicable(&self) -> Vec<Constraint> {
+        let shape = |e: usize| self.sketch.elements()[e].shape;
+        let is_line = |e: usize| matches!(shape(e), Shape::Line { .. });
+
+        let mut constraints = match self.selection.as_slice() {
+            [Selected::Point(point)] => vec![Constraint::Fixed { point: *point, at: self.sketch.point(*point) }],
+            [Selected::Point(a), Selected::Point(b)] => vec![
+                Constraint::Coincident(*a, *b),
+                Constraint::Distance { a: *a, b: *b, value: 0.0 },
+            ],
+            [Selected::Element(e)] => match shape(*e) {
+                Shape::Line { start, end } => vec![
+                    Constraint::Horizontal(*e),
+                    Constraint::Vertical(*e),
+                    Constraint::Distance { a: start, b: end, value: 0.0 },
+                ],
+                _ => vec![
+                    Constraint::Radius { element: *e, value: 0.0 },
+                    Constraint::Diameter { element: *e, value: 0.0 },
+                ],
+            },
+            [Selected::Element(a), Selected::Element(b)] => match (is_line(*a), is_line(*b)) {
+                (true, true) => vec![
+                    Constraint::Parallel(*a, *b),
+                    Constraint::Perpendicular(*a, *b),
+                    Constraint::EqualLength(*a, *b),
+                    Constraint::Angle { a: *a, b: *b, value: 0.0 },
+                ],
+                (false, false) => vec![
+                    Constraint::Tangent(*a, *b),
+                    Constraint::EqualLength(*a, *b),
+                    Constraint::Concentric(*a, *b),
+                ],
+                _ => vec![Constraint::Tangent(*a, *b)],
+            },
+            _ => Vec::new(),
+        };
+
+        for constraint in constraints.iter_mut() {
+            if let Some(value) = self.sketch.measure(constraint) {
+                constraint.set_value(value);
+            }
+        }
+
+        constraints
+    }
+
+    /// window listing every dimension, whose values drive the sketch
+    fn dimensions_window(&mut self, ctx: &egui::Context) {
+        if !self.sketch.constraints().iter().any(|c| c.value().is_some()) {
+            return;
+        }
+
+        let mut changed = None;
+        egui::Window::new("Dimensions")
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("dimension_table")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, constraint) in self.sketch.constraints().iter().enumerate() {
+                            let Some(mut value) = constraint.value() else { continue };
+
+                            ui.label(constraint.to_string());
+                            if ui.add(egui::DragValue::new(&mut value).speed(0.1).clamp_range(constraint.range())).changed() {
+                                changed = Some((i, value));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some((index, value)) = changed {
+            self.status = self.sketch.set_dimension(index, value);
+        }
+    }
+
+    /// write the value of every dimension next to its annotation
+    fn dimension_labels(&self, ctx: &egui::Context) {
+        let size = self.pick_tolerance() * 2.0;
+        let camera = match self.camera.lock() {
+            Ok(camera) => camera,
+            Err(e) => {
+                log::error!("Failed to lock camera to place dimension labels because `{}`", e);
+                return;
+            }
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let scale = ctx.pixels_per_point();
+        for constraint in self.sketch.constraints() {
+            let Some(annotation) = annotation(&self.sketch, constraint, size) else { continue };
+            let Some(screen) = camera.project(&self.plane().point(&annotation.label)) else { continue };
+
+            painter.text(
+                egui::pos2(screen.x / scale, screen.y / scale),
+                egui::Align2::CENTER_CENTER,
+                annotation.text,
+                egui::FontId::proportional(14.0),
+                egui::Color32::from_gray(30),
+            );
+        }
+    }
+
+
+    /// drop every constraint on the selected geometry
+    fn remove_constraints(&mut self) {
+        let selected = |c: &Constraint| {
+            c.points().iter().any(|p| self.selection.contains(&Selected::Point(*p)))
+                || c.elements().iter().any(|e| self.selection.contains(&Selected::Element(*e)))
+        };
+
+        let mut index = 0;
+        while index < self.sketch.constraints().len() {
+            match selected(&self.sketch.constraints()[index]) {
+                true => { self.sketch.remove_constraint(index); },
+                false => index += 1,
+            }
+        }
+
+        self.status = self.sketch.solve();
+    }
+
+    /// hand the drawn geometry over as a sketch entity, or `None` if nothing was drawn
+    pub fn finalize(&mut self) -> Option<ModelEntity> {
+        self.tool.cancel();
+        self.selection.clear();
+
+        let empty = Sketch::on_plane(self.plane().clone());
+        let sketch = std::mem::replace(&mut self.sketch, empty);
+        if sketch.is_empty() {
+            log::debug!("Discarding empty sketch");
+            return None;
+        }
+
+        Some(ModelEntity::Sketch(sketch))
     }
 }
 
 impl ApplicationEnvironmentOps for Sketcher {
     fn draw_toolbar(&mut self, ui: &mut egui::Ui) -> Option<UiDrawResult> {
-        if ui.button("Finish sketch (right-click)").clicked() {
-            self.finalize();
+        for tool in Tool::ALL {
+            if ui.selectable_label(self.tool.tool() == tool, tool.to_string()).clicked() {
+                self.tool.set_tool(tool);
+                self.selection.clear();
+            }
+        }
+
+        ui.checkbox(&mut self.tool.construction, "Construction");
+        ui.menu_button("Snap", |ui| self.snap.ui(ui));
+
+        ui.separator();
+
+        for constraint in self.applicable() {
+            if ui.button(constraint.to_string()).clicked() {
+                self.status = self.sketch.add_constraint(constraint);
+                self.selection.clear();
+            }
+        }
+
+        let ctx = ui.ctx().clone();
+        self.dimensions_window(&ctx);
+        self.dimension_labels(&ctx);
+
+        if !self.selection.is_empty() && ui.button("Remove constraints").clicked() {
+            self.remove_constraints();
+        }
+
+        ui.label(self.status.to_string());
+
+        ui.separator();
+
+        if ui.button("Finish sketch").on_hover_text("right-click when no tool is in use").clicked() || self.finished {
+            self.finished = false;
 
-            return Some(UiDrawResult::ExitSketcher(Some(
-                ModelEntity::Sketch(Sketch::new())
-            )));
+            return Some(UiDrawResult::ExitSketcher(self.finalize()));
         }
 
         None
@@ -37,24 +320,99 @@ impl ApplicationEnvironmentOps for Sketcher {
 
     fn handle_window_event(&mut self, event: &WindowEvent) {
         match event {
-            WindowEvent::CursorMoved { position, .. } => self.mouse_pos = (position.x as f32, position.y as f32).into(),
+            WindowEvent::CursorMoved { position, .. } => self.mouse_pos = (*position).into(),
             WindowEvent::MouseInput { state: ElementState::Released, button, .. } => {
                 match button {
-                    MouseButton::Left => {
-                        if self.points.contains(&self.mouse_pos) {
-                            log::warn!("Possible duplicate point in sketch since mouse did not move");
-                        }
-                        
-                        else { self.points.push(self.mouse_pos.clone()) }
+                    MouseButton::Left => match (self.cursor_point(), self.snapped(), self.tool.tool()) {
+                        (Some(point), _, Tool::Select) => self.select(point),
+                        (_, Some(snapped), _) => {
+                            if let Some(kind) = snapped.kind {
+                                log::trace!("Click snapped to {} at {:?}", kind, snapped.point);
+                            }
+
+                            self.tool.click(snapped.point, snapped.index, &mut self.sketch);
+                            self.status = self.sketch.solve();
+                        },
+                        _ => log::warn!("Cursor does not point at the sketch plane"),
+                    },
+                    MouseButton::Middle => {
+                        self.tool.cancel();
+                        self.selection.clear();
+                    },
+                    // with nothing in progress, the sketch is handed over on the next toolbar draw
+                    MouseButton::Right => match self.tool.is_idle() {
+                        true => self.finished = true,
+                        false => self.tool.finish(),
                     },
-                    MouseButton::Middle => (),
-                    MouseButton::Right => (),
                     _ => ()
                 }
             },
+            // unprojection needs the current window size
+            WindowEvent::Resized(_) => match self.camera.lock() {
+                Ok(mut camera) => camera.process_input(event),
+                Err(e) => log::error!("Failed to lock camera to handle WindowEvent<{:?}> because `{}`", event, e)
+            },
             _ => ()
         }
     }
+
+    fn preview(&self) -> Vec<Vertex> {
+        let mut data = self.sketch.edges();
+
+        if let Some(snapped) = self.snapped().filter(|_| self.tool.tool() != Tool::Select) {
+            data.extend(self.tool.preview(snapped.point, &self.sketch).edges());
+        }
+
+        let size = self.pick_tolerance() * 2.0;
+        for annotation in self.sketch.constraints().iter().filter_map(|c| annotation(&self.sketch, c, size)) {
+            for (a, b) in annotation.lines {
+                data.push(self.plane().point(&a).into());
+                data.push(self.plane().point(&b).into());
+            }
+        }
+
+        data
+    }
+
+    fn highlight(&self) -> Vec<Vertex> {
+        let plane = self.plane();
+        let size = self.pick_tolerance() / 2.0;
+
+        let mut segments = Vec::new();
+
+        // snap marker, a small square, and the inference lines leading to it
+        if let Some(snapped) = self.snapped().filter(|_| self.tool.tool() != Tool::Select) {
+            if snapped.kind.is_some() {
+                let corner = |x: f32, y: f32| snapped.point + Point2d::new(x * size, y * size);
+                let square = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+
+                segments.extend((0..4).map(|i| (square[i], square[(i + 1) % 4])));
+            }
+
+            segments.extend(snapped.guides);
+        }
+
+        for selected in self.selection.iter() {
+            segments.extend(match *selected {
+                // a small cross over the point
+                Selected::Point(point) => {
+                    let p = self.sketch.point(point);
+                    vec![
+                        (p + Point2d::new(-size, -size), p + Point2d::new(size, size)),
+                        (p + Point2d::new(-size, size), p + Point2d::new(size, -size)),
+                    ]
+                },
+                Selected::Element(element) => self.sketch.tessellate(&self.sketch.elements()[element])
+                    .windows(2)
+                    .map(|pair| (pair[0], pair[1]))
+                    .collect(),
+            });
+        }
+
+        segments.into_iter()
+            .flat_map(|(a, b)| [plane.point(&a).into(), plane.point(&b).into()])
+            .collect()
+    }
 }
 
 impl From<&ApplicationEnvironmentType> for Sketcher {
@@ -64,4 +422,4 @@ impl From<&ApplicationEnvironmentType> for Sketcher {
             _ => unimplemented!()
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,355 @@
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{prelude::*, model::{Shape, Sketch}};
+
+/// what the cursor snapped to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapKind {
+    Endpoint,
+    Midpoint,
+    Center,
+    Intersection,
+    /// end or middle of a model edge projected onto the sketch plane
+    Edge,
+    /// lined up with a point horizontally, vertically or both
+    Inference,
+    Grid,
+}
+
+impl std::fmt::Display for SnapKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapKind::Endpoint => write!(f, "Endpoint"),
+            SnapKind::Midpoint => write!(f, "Midpoint"),
+            SnapKind::Center => write!(f, "Center"),
+            SnapKind::Intersection => write!(f, "Intersection"),
+            SnapKind::Edge => write!(f, "Model edge"),
+            SnapKind::Inference => write!(f, "Inference"),
+            SnapKind::Grid => write!(f, "Grid"),
+        }
+    }
+}
+
+/// snapped cursor position, in plane coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapped {
+    pub point: Point2d,
+    pub kind: Option<SnapKind>,
+    /// sketch point snapped to, for endpoints and centers
+    pub index: Option<usize>,
+    /// inference lines from the points the cursor lined up with
+    pub guides: Vec<(Point2d, Point2d)>,
+}
+
+/// point that attracts the cursor, with the sketch point it is, if any
+pub type Candidate = (Point2d, SnapKind, Option<usize>);
+
+/// which snaps are in use, and how close the cursor has to be
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapSettings {
+    /// snap distance in pixels
+    pub tolerance: f32,
+    pub grid: bool,
+    /// grid spacing in sketch units
+    pub spacing: f32,
+    pub endpoints: bool,
+    pub midpoints: bool,
+    pub centers: bool,
+    pub intersections: bool,
+    pub edges: bool,
+    /// horizontal and vertical inference lines
+    pub inference: bool,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: 10.0,
+            grid: false,
+            spacing: 10.0,
+            endpoints: true,
+            midpoints: true,
+            centers: true,
+            intersections: true,
+            edges: true,
+            inference: true,
+        }
+    }
+}
+
+impl SnapSettings {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Tolerance (px)");
+            ui.add(egui::DragValue::new(&mut self.tolerance).speed(0.5).clamp_range(1.0..=50.0));
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.grid, "Grid");
+            ui.add_enabled(self.grid, egui::DragValue::new(&mut self.spacing).speed(0.1).clamp_range(0.01..=f32::MAX));
+        });
+
+        ui.checkbox(&mut self.endpoints, "Endpoints");
+        ui.checkbox(&mut self.midpoints, "Midpoints");
+        ui.checkbox(&mut self.centers, "Centers");
+        ui.checkbox(&mut self.intersections, "Intersections");
+        ui.checkbox(&mut self.edges, "Model edges");
+        ui.checkbox(&mut self.inference, "Inference lines");
+    }
+
+    /// snap `cursor` to the closest feature within `tolerance` sketch units
+    ///
+    /// Points in `candidates` win over inference, which wins over the grid.
+    /// `anchor` is the last click of the tool in use, which inference lines up with too.
+    pub fn snap(
+        &self,
+        cursor: Point2d,
+        tolerance: f32,
+        sketch: &Sketch,
+        candidates: &[Candidate],
+        anchor: Option<Point2d>,
+    ) -> Snapped {
+        let closest = candidates.iter()
+            .map(|&(point, kind, index)| (point, kind, index, point.distance(&cursor)))
+            .filter(|(_, _, _, d)| *d <= tolerance)
+            .min_by(|a, b| a.3.total_cmp(&b.3));
+
+        if let Some((point, kind, index, _)) = closest {
+            return Snapped { point, kind: Some(kind), index, guides: Vec::new() };
+        }
+
+        let grid = |v: f32| match self.grid {
+            true => (v / self.spacing).round() * self.spacing,
+            false => v,
+        };
+        let mut snapped = Snapped {
+            point: Point2d::new(grid(cursor.x), grid(cursor.y)),
+            kind: self.grid.then_some(SnapKind::Grid),
+            index: None,
+            guides: Vec::new(),
+        };
+
+        if self.inference {
+            let points: Vec<Point2d> = sketch.points().iter().copied().chain(anchor).collect();
+            let nearest = |along: fn(&Point2d) -> f32| points.iter()
+                .map(|p| (*p, (along(p) - along(&cursor)).abs()))
+                .filter(|(_, d)| *d <= tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(p, _)| p);
+
+            // lined up vertically with a point
+            if let Some(p) = nearest(|p| p.x) {
+                snapped.point.x = p.x;
+                snapped.kind = Some(SnapKind::Inference);
+            }
+
+            // lined up horizontally with a point
+            if let Some(p) = nearest(|p| p.y) {
+                snapped.point.y = p.y;
+                snapped.kind = Some(SnapKind::Inference);
+            }
+
+            for p in points.iter() {
+                if p.x == snapped.point.x || p.y == snapped.point.y {
+                    snapped.guides.push((*p, snapped.point));
+                }
+            }
+        }
+
+        snapped
+    }
+
+    /// points of the sketch and of the model `edges` that attract the cursor
+    ///
+    /// Intersections are found between every pair of elements, so this is best kept with
+    /// a [`SnapCache`] rather than run for every snap.
+    pub fn candidates(&self, sketch: &Sketch, edges: &[(Point2d, Point2d)]) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+
+        for element in sketch.elements() {
+            match element.shape {
+                Shape::Line { start, end } => {
+                    let (a, b) = (sketch.point(start), sketch.point(end));
+
+                    if self.endpoints {
+                        candidates.extend([(a, SnapKind::Endpoint, Some(start)), (b, SnapKind::Endpoint, Some(end))]);
+                    }
+                    if self.midpoints {
+                        candidates.push(((a + b) / 2.0, SnapKind::Midpoint, None));
+                    }
+                },
+                Shape::Circle { center, .. } => if self.centers {
+                    candidates.push((sketch.point(center), SnapKind::Center, Some(center)));
+                },
+                Shape::Arc { center, start, end } => {
+                    if self.endpoints {
+                        candidates.extend([start, end].map(|i| (sketch.point(i), SnapKind::Endpoint, Some(i))));
+                    }
+                    if self.centers {
+                        candidates.push((sketch.point(center), SnapKind::Center, Some(center)));
+                    }
+                },
+            }
+        }
+
+        if self.intersections {
+            let polylines: Vec<Vec<Point2d>> = sketch.elements().iter().map(|e| sketch.tessellate(e)).collect();
+
+            for (i, a) in polylines.iter().enumerate() {
+                for b in polylines[i + 1..].iter() {
+                    for pa in a.windows(2) {
+                        for pb in b.windows(2) {
+                            if let Some(p) = intersection(pa[0], pa[1], pb[0], pb[1]) {
+                                candidates.push((p, SnapKind::Intersection, None));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.edges {
+            for (a, b) in edges {
+                candidates.extend([*a, *b, (*a + *b) / 2.0].map(|p| (p, SnapKind::Edge, None)));
+            }
+        }
+
+        candidates
+    }
+}
+
+/// snap candidates of the last sketch and settings they were found for
+///
+/// The sketcher snaps several times a frame, while the sketch only changes on edits.
+#[derive(Debug, Default)]
+pub struct SnapCache {
+    found: RefCell<Option<Found>>,
+}
+
+#[derive(Debug)]
+struct Found {
+    sketch: Sketch,
+    settings: SnapSettings,
+    candidates: Rc<[Candidate]>,
+}
+
+impl SnapCache {
+    /// candidates of `sketch`, found again only if it or `settings` changed since the last call
+    ///
+    /// The model edges are taken to stay the same, a new cache is needed when they change.
+    pub fn candidates(&self, settings: &SnapSettings, sketch: &Sketch, edges: &[(Point2d, Point2d)]) -> Rc<[Candidate]> {
+        let mut found = self.found.borrow_mut();
+        match found.as_ref() {
+            Some(found) if found.sketch == *sketch && found.settings == *settings => found.candidates.clone(),
+            _ => {
+                let candidates: Rc<[Candidate]> = settings.candidates(sketch, edges).into();
+                *found = Some(Found { sketch: sketch.clone(), settings: settings.clone(), candidates: candidates.clone() });
+                candidates
+            },
+        }
+    }
+}
+
+/// crossing of two segments, excluding segments that only touch at their ends
+fn intersection(a: Point2d, b: Point2d, c: Point2d, d: Point2d) -> Option<Point2d> {
+    let (r, s) = (b - a, d - c);
+    let denom = r.cross(&s);
+    if denom.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let t = (c - a).cross(&s) / denom;
+    let u = (c - a).cross(&r) / denom;
+    let inside = |v: f32| v > 1e-4 && v < 1.0 - 1e-4;
+
+    (inside(t) && inside(u)).then(|| a + r * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Plane;
+
+    fn p(x: f32, y: f32) -> Point2d {
+        Point2d::new(x, y)
+    }
+
+    fn snap(settings: &SnapSettings, cursor: Point2d, sketch: &Sketch, edges: &[(Point2d, Point2d)], anchor: Option<Point2d>) -> Snapped {
+        settings.snap(cursor, 0.5, sketch, &settings.candidates(sketch, edges), anchor)
+    }
+
+    /// an X made of two lines, crossing at (2, 2)
+    fn cross() -> Sketch {
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        let [a, b, c, d] = [p(0.0, 0.0), p(4.0, 4.0), p(0.0, 4.0), p(4.0, 0.0)].map(|q| sketch.add_point(q));
+        sketch.add(Shape::Line { start: a, end: b }, false);
+        sketch.add(Shape::Line { start: c, end: d }, false);
+
+        sketch
+    }
+
+    #[test]
+    fn snaps_to_closest_feature() {
+        let settings = SnapSettings::default();
+        let sketch = cross();
+
+        let snapped = snap(&settings, p(3.8, 4.1), &sketch, &[], None);
+        assert_eq!((snapped.point, snapped.kind, snapped.index), (p(4.0, 4.0), Some(SnapKind::Endpoint), Some(1)));
+
+        // both midpoints sit on the crossing
+        let snapped = snap(&settings, p(2.1, 1.9), &sketch, &[], None);
+        assert_eq!(snapped.point, p(2.0, 2.0));
+
+        let midpoints_off = SnapSettings { midpoints: false, ..SnapSettings::default() };
+        let snapped = snap(&midpoints_off, p(2.1, 1.9), &sketch, &[], None);
+        assert_eq!((snapped.kind, snapped.index), (Some(SnapKind::Intersection), None));
+        assert!(snapped.point.distance(&p(2.0, 2.0)) < 1e-5);
+    }
+
+    #[test]
+    fn snaps_to_model_edges() {
+        let settings = SnapSettings::default();
+        let edges = [(p(10.0, 0.0), p(10.0, 6.0))];
+
+        let snapped = snap(&settings, p(10.2, 3.1), &Sketch::new(), &edges, None);
+        assert_eq!((snapped.point, snapped.kind), (p(10.0, 3.0), Some(SnapKind::Edge)));
+    }
+
+    #[test]
+    fn infers_alignment() {
+        let settings = SnapSettings::default();
+        let anchor = p(1.0, 1.0);
+
+        let snapped = snap(&settings, p(6.0, 1.3), &Sketch::new(), &[], Some(anchor));
+        assert_eq!((snapped.point, snapped.kind), (p(6.0, 1.0), Some(SnapKind::Inference)));
+        assert_eq!(snapped.guides, [(anchor, p(6.0, 1.0))]);
+    }
+
+    #[test]
+    fn falls_back_to_grid() {
+        let settings = SnapSettings { grid: true, spacing: 2.0, inference: false, ..SnapSettings::default() };
+
+        let snapped = snap(&settings, p(6.9, -3.2), &Sketch::new(), &[], None);
+        assert_eq!((snapped.point, snapped.kind), (p(6.0, -4.0), Some(SnapKind::Grid)));
+
+        let off = SnapSettings { grid: false, ..settings };
+        assert_eq!(snap(&off, p(6.9, -3.2), &Sketch::new(), &[], None).kind, None);
+    }
+
+    #[test]
+    fn cache_follows_sketch_and_settings() {
+        let cache = SnapCache::default();
+        let settings = SnapSettings::default();
+        let mut sketch = cross();
+
+        let first = cache.candidates(&settings, &sketch, &[]);
+        assert!(Rc::ptr_eq(&first, &cache.candidates(&settings, &sketch, &[])));
+
+        let off = SnapSettings { intersections: false, ..settings.clone() };
+        assert_eq!(cache.candidates(&off, &sketch, &[]).len(), first.len() - 1);
+
+        sketch.add_point(p(9.0, 9.0));
+        assert!(!Rc::ptr_eq(&first, &cache.candidates(&settings, &sketch, &[])));
+    }
+}
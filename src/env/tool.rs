@@ -0,0 +1,387 @@
+
+use crate::{prelude::*, model::{Shape, Sketch}};
+
+/// sketch drawing tools
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Tool {
+    /// pick points and elements to constrain
+    Select,
+    #[default]
+    Line,
+    /// connected lines, until the tool is finished
+    Polyline,
+    /// two opposite corners
+    CornerRectangle,
+    /// center and a corner
+    CenterRectangle,
+    /// center and a point on the circle
+    Circle,
+    /// start, end and a point along the arc
+    ThreePointArc,
+    /// center, start and end, counter-clockwise
+    CenterArc,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 8] = [
+        Tool::Select,
+        Tool::Line,
+        Tool::Polyline,
+        Tool::CornerRectangle,
+        Tool::CenterRectangle,
+        Tool::Circle,
+        Tool::ThreePointArc,
+        Tool::CenterArc,
+    ];
+
+    /// clicks that make up one use of the tool
+    fn clicks(&self) -> usize {
+        match self {
+            Tool::Select => 1,
+            Tool::ThreePointArc | Tool::CenterArc => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tool::Select => write!(f, "Select"),
+            Tool::Line => write!(f, "Line"),
+            Tool::Polyline => write!(f, "Polyline"),
+            Tool::CornerRectangle => write!(f, "Corner rectangle"),
+            Tool::CenterRectangle => write!(f, "Center rectangle"),
+            Tool::Circle => write!(f, "Circle"),
+            Tool::ThreePointArc => write!(f, "3 point arc"),
+            Tool::CenterArc => write!(f, "Center arc"),
+        }
+    }
+}
+
+/// active tool and the clicks given to it so far
+#[derive(Debug, Default)]
+pub struct ToolState {
+    tool: Tool,
+    pub construction: bool,
+
+    clicks: Vec<Point2d>,
+    /// clicks that snapped to an existing sketch point, which is used instead of a new one
+    joins: Vec<(Point2d, usize)>,
+    /// last point of the polyline being drawn
+    chain: Option<usize>,
+}
+
+impl ToolState {
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// switch tools, dropping any unfinished geometry
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.cancel();
+        self.tool = tool;
+    }
+
+    /// whether no geometry is in progress
+    pub fn is_idle(&self) -> bool {
+        self.clicks.is_empty() && self.chain.is_none()
+    }
+
+    /// drop the clicks of the geometry in progress
+    pub fn cancel(&mut self) {
+        self.clicks.clear();
+        self.joins.clear();
+        self.chain = None;
+    }
+
+    /// end a polyline; other tools drop their unfinished geometry
+    pub fn finish(&mut self) {
+        self.cancel();
+    }
+
+    /// add a click, adding geometry to the sketch once the tool has all of its clicks
+    pub fn click(&mut self, point: Point2d, existing: Option<usize>, sketch: &mut Sketch) {
+        // selection is handled by the sketcher
+        if self.tool == Tool::Select {
+            return;
+        }
+
+        if let Some(index) = existing {
+            self.joins.push((point, index));
+        }
+
+        if self.tool == Tool::Polyline {
+            let start = match (self.chain, self.clicks.pop()) {
+                (Some(index), _) => index,
+                (None, Some(first)) => join(sketch, &self.joins, first),
+                (None, None) => {
+                    self.clicks.push(point);
+                    return;
+                }
+            };
+
+            let end = join(sketch, &self.joins, point);
+            sketch.add(Shape::Line { start, end }, self.construction);
+            self.chain = Some(end);
+            self.joins.clear();
+            return;
+        }
+
+        self.clicks.push(point);
+        if self.clicks.len() == self.tool.clicks() {
+            if !build(self.tool, &self.clicks, &self.joins, sketch, self.construction) {
+                log::warn!("{} needs points that are not collinear or coincident", self.tool);
+            }
+
+            self.clicks.clear();
+            self.joins.clear();
+        }
+    }
+
+    /// last point given to the geometry in progress
+    pub fn last(&self, sketch: &Sketch) -> Option<Point2d> {
+        match self.chain {
+            Some(index) => Some(sketch.point(index)),
+            None => self.clicks.last().copied(),
+        }
+    }
+
+    /// geometry in progress, completed with the cursor position
+    pub fn preview(&self, cursor: Point2d, sketch: &Sketch) -> Sketch {
+        let mut preview = Sketch::on_plane(sketch.plane().clone());
+
+        let Some(last) = self.last(sketch) else { return preview };
+
+        let mut clicks = self.clicks.clone();
+        clicks.push(cursor);
+
+        let complete = self.tool != Tool::Polyline && clicks.len() == self.tool.clicks();
+        if !complete || !build(self.tool, &clicks, &[], &mut preview, self.construction) {
+            // rubber band from the last click
+            let start = preview.add_point(last);
+            let end = preview.add_point(cursor);
+            preview.add(Shape::Line { start, end }, self.construction);
+        }
+
+        preview
+    }
+}
+
+/// index of the joined sketch point at `point`, or of a new point there
+fn join(sketch: &mut Sketch, joins: &[(Point2d, usize)], point: Point2d) -> usize {
+    match joins.iter().find(|(p, _)| *p == point) {
+        Some((_, index)) => *index,
+        None => sketch.add_point(point),
+    }
+}
+
+/// add the geometry of a tool from all of its clicks, returning whether it was valid
+///
+/// Points that land on one of `joins` use that sketch point rather than a new one.
+fn build(tool: Tool, clicks: &[Point2d], joins: &[(Point2d, usize)], sketch: &mut Sketch, construction: bool) -> bool {
+    let mut add = |sketch: &mut Sketch, p: Point2d| join(sketch, joins, p);
+
+    match (tool, clicks) {
+        (Tool::Line | Tool::Polyline, [a, b]) => {
+            if a == b {
+                return false;
+            }
+
+            let (start, end) = (add(sketch, *a), add(sketch, *b));
+            sketch.add(Shape::Line { start, end }, construction);
+        },
+        (Tool::CornerRectangle, [a, b]) => {
+            rectangle(sketch, [*a, Point2d::new(b.x, a.y), *b, Point2d::new(a.x, b.y)], &mut add, construction);
+        },
+        (Tool::CenterRectangle, [c, p]) => {
+            let d = *p - *c;
+            let corner = |x: f32, y: f32| *c + Point2d::new(d.x * x, d.y * y);
+
+            rectangle(sketch, [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)], &mut add, construction);
+        },
+        (Tool::Circle, [c, p]) => {
+            let radius = c.distance(p);
+            if radius <= f32::EPSILON {
+                return false;
+            }
+
+            let center = add(sketch, *c);
+            sketch.add(Shape::Circle { center, radius }, construction);
+        },
+        (Tool::ThreePointArc, [s, e, m]) => {
+            let Some(c) = circumcenter(*s, *m, *e) else { return false };
+
+            // the arc runs counter-clockwise, so the ends are swapped for clockwise clicks
+            let (s, e) = match (*m - *s).cross(&(*e - *m)) > 0.0 {
+                true => (*s, *e),
+                false => (*e, *s),
+            };
+
+            let (center, start, end) = (add(sketch, c), add(sketch, s), add(sketch, e));
+            sketch.add(Shape::Arc { center, start, end }, construction);
+        },
+        (Tool::CenterArc, [c, s, e]) => {
+            let (radius, toward) = (c.distance(s), *e - *c);
+            if radius <= f32::EPSILON || toward.length() <= f32::EPSILON {
+                return false;
+            }
+
+            // the end only gives the direction, the radius comes from the start
+            let e = *c + toward / toward.length() * radius;
+
+            let (center, start, end) = (add(sketch, *c), add(sketch, *s), add(sketch, e));
+            sketch.add(Shape::Arc { center, start, end }, construction);
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+/// four lines through shared corners
+fn rectangle(sketch: &mut Sketch, corners: [Point2d; 4], add: &mut impl FnMut(&mut Sketch, Point2d) -> usize, construction: bool) {
+    let indices = corners.map(|p| add(sketch, p));
+
+    for i in 0..4 {
+        sketch.add(Shape::Line { start: indices[i], end: indices[(i + 1) % 4] }, construction);
+    }
+}
+
+/// center of the circle through three points, or `None` if they are collinear
+fn circumcenter(a: Point2d, b: Point2d, c: Point2d) -> Option<Point2d> {
+    let (ab, ac) = (b - a, c - a);
+    let d = 2.0 * ab.cross(&ac);
+    if d.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let (ab2, ac2) = (ab.dot(&ab), ac.dot(&ac));
+    let offset = Point2d::new(ac.y * ab2 - ab.y * ac2, ab.x * ac2 - ac.x * ab2) / d;
+
+    Some(a + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SketchElement;
+
+    fn draw(tool: Tool, clicks: &[Point2d]) -> Sketch {
+        let mut sketch = Sketch::new();
+        let mut state = ToolState::default();
+        state.set_tool(tool);
+
+        for click in clicks {
+            state.click(*click, None, &mut sketch);
+        }
+
+        sketch
+    }
+
+    fn p(x: f32, y: f32) -> Point2d {
+        Point2d::new(x, y)
+    }
+
+    #[test]
+    fn polyline_shares_points() {
+        let mut sketch = Sketch::new();
+        let mut state = ToolState::default();
+        state.set_tool(Tool::Polyline);
+
+        for click in [p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)] {
+            state.click(click, None, &mut sketch);
+        }
+        state.finish();
+        assert!(state.is_idle());
+
+        // a new chain starts after finishing
+        state.click(p(5.0, 5.0), None, &mut sketch);
+
+        assert_eq!(sketch.points(), [p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)]);
+        assert_eq!(sketch.elements().iter().map(|e| e.shape).collect::<Vec<_>>(), [
+            Shape::Line { start: 0, end: 1 },
+            Shape::Line { start: 1, end: 2 },
+        ]);
+    }
+
+    #[test]
+    fn rectangles_are_closed() {
+        for sketch in [
+            draw(Tool::CornerRectangle, &[p(0.0, 0.0), p(2.0, 1.0)]),
+            draw(Tool::CenterRectangle, &[p(1.0, 0.5), p(2.0, 1.0)]),
+        ] {
+            assert_eq!(sketch.points(), [p(0.0, 0.0), p(2.0, 0.0), p(2.0, 1.0), p(0.0, 1.0)]);
+            assert_eq!(sketch.elements().len(), 4);
+            assert_eq!(sketch.elements()[3].shape, Shape::Line { start: 3, end: 0 });
+        }
+    }
+
+    #[test]
+    fn circle_from_center_and_point() {
+        let sketch = draw(Tool::Circle, &[p(1.0, 1.0), p(4.0, 5.0)]);
+
+        assert_eq!(sketch.elements(), [SketchElement { shape: Shape::Circle { center: 0, radius: 5.0 }, construction: false }]);
+    }
+
+    #[test]
+    fn three_point_arc_runs_through_middle() {
+        // clockwise clicks over the top of the circle
+        let sketch = draw(Tool::ThreePointArc, &[p(-1.0, 0.0), p(1.0, 0.0), p(0.0, 1.0)]);
+
+        assert!(sketch.point(0).length() < 1e-6);
+        assert_eq!(sketch.elements()[0].shape, Shape::Arc { center: 0, start: 1, end: 2 });
+        assert_eq!((sketch.point(1), sketch.point(2)), (p(1.0, 0.0), p(-1.0, 0.0)));
+
+        let polyline = sketch.tessellate(&sketch.elements()[0]);
+        assert!(polyline.iter().all(|q| q.y >= -1e-6));
+    }
+
+    #[test]
+    fn center_arc_end_is_on_radius() {
+        let sketch = draw(Tool::CenterArc, &[p(0.0, 0.0), p(2.0, 0.0), p(0.0, 5.0)]);
+
+        assert_eq!(sketch.point(2), p(0.0, 2.0));
+    }
+
+    #[test]
+    fn collinear_arc_is_rejected() {
+        let sketch = draw(Tool::ThreePointArc, &[p(0.0, 0.0), p(2.0, 0.0), p(1.0, 0.0)]);
+
+        assert!(sketch.is_empty());
+    }
+
+    #[test]
+    fn cancel_drops_clicks() {
+        let mut sketch = Sketch::new();
+        let mut state = ToolState::default();
+        state.click(p(0.0, 0.0), None, &mut sketch);
+        state.cancel();
+        state.click(p(1.0, 0.0), None, &mut sketch);
+        state.click(p(1.0, 1.0), None, &mut sketch);
+
+        assert_eq!(sketch.points(), [p(1.0, 0.0), p(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn snapped_clicks_reuse_points() {
+        let mut sketch = draw(Tool::Line, &[p(0.0, 0.0), p(2.0, 0.0)]);
+        let mut state = ToolState::default();
+        state.set_tool(Tool::Line);
+
+        // from the end of the first line, so the two are connected
+        state.click(p(2.0, 0.0), Some(1), &mut sketch);
+        state.click(p(2.0, 3.0), None, &mut sketch);
+
+        assert_eq!(sketch.points().len(), 3);
+        assert_eq!(sketch.elements()[1].shape, Shape::Line { start: 1, end: 2 });
+
+        // a rectangle with a corner on the far end
+        state.set_tool(Tool::CornerRectangle);
+        state.click(p(2.0, 3.0), Some(2), &mut sketch);
+        state.click(p(4.0, 4.0), None, &mut sketch);
+
+        assert_eq!(sketch.points().len(), 6);
+        assert_eq!(sketch.elements()[2].shape, Shape::Line { start: 2, end: 3 });
+    }
+}
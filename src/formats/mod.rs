@@ -1,6 +1,11 @@
 
+// .ph files
+pub mod part;
+
 // .obj files
 pub mod wavefront;
 
-// TODO: .stl
+// .stl files
+pub mod stl;
+
 // TODO: others? (dxf, dstv, 3mf)
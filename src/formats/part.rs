@@ -0,0 +1,1081 @@
+
+//! native `.ph` part files
+//!
+//! A part file is plain text: a version header followed by every [`ModelEntity`]
+//! in history order. Each entity is a record that starts with its kind and ends
+//! with `end`. Imported meshes embed their data as Wavefront text, prefixed by
+//! the number of lines to read.
+//!
+//! ```text
+//! phobia-part 15
+//! block
+//! origin 0 0 0
+//! dim 3 4 5
+//! end
+//! ```
+//!
+//! Readers accept every version up to [`VERSION`], so bumping the version on a
+//! schema change keeps older parts opening.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use obj::{Obj, ObjData};
+
+use crate::model::{Anchor, Axis, Block, Chamfer, ChamferSize, Combine, Constraint, Direction, Extent, Extrude, Fillet, Hole, HoleKind, ImportedModel, Material, ModelEntity, Operation, Orientation, Pattern, PatternKind, Plane, Revolve, Shape, Sketch, Transform};
+use crate::prelude::*;
+
+const MAGIC: &str = "phobia-part";
+
+/// current version of the part file schema
+pub const VERSION: u32 = 15;
+
+#[derive(Debug)]
+pub enum PartError {
+    Io(io::Error),
+    Obj(obj::ObjError),
+
+    /// file was written by a newer version of the application
+    Version(u32),
+
+    /// malformed record
+    Parse { line: usize, reason: String },
+}
+
+impl fmt::Display for PartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartError::Io(e) => write!(f, "I/O error: {}", e),
+            PartError::Obj(e) => write!(f, "embedded mesh error: {}", e),
+            PartError::Version(v) => write!(f, "unsupported part version {} (newest supported is {})", v, VERSION),
+            PartError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for PartError {}
+
+impl From<io::Error> for PartError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<obj::ObjError> for PartError {
+    fn from(value: obj::ObjError) -> Self {
+        Self::Obj(value)
+    }
+}
+
+pub fn save(entities: &[ModelEntity], path: &Path) -> Result<(), PartError> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write(entities, &mut out)?;
+    out.flush()?;
+
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Vec<ModelEntity>, PartError> {
+    let file = File::open(path)?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    read(BufReader::new(file), dir)
+}
+
+pub fn write(entities: &[ModelEntity], out: &mut impl Write) -> Result<(), PartError> {
+    writeln!(out, "{} {}", MAGIC, VERSION)?;
+
+    for entity in entities {
+        match entity {
+            ModelEntity::ImportedModel(model) => {
+                let mut buf = Vec::new();
+                super::wavefront::write_data(&model.obj().data, &mut buf)?;
+
+                let text = String::from_utf8_lossy(&buf);
+                writeln!(out, "imported {} {}", text.lines().count(), model.name())?;
+                for line in text.lines() {
+                    writeln!(out, "{}", line)?;
+                }
+
+                let material = model.material();
+                writeln!(out, "ambient {}", point3d(&material.ambient.into()))?;
+                writeln!(out, "diffuse {}", point3d(&material.diffuse.into()))?;
+                writeln!(out, "specular {}", point3d(&material.specular.into()))?;
+                writeln!(out, "shininess {}", material.shininess)?;
+                writeln!(out, "opacity {}", material.opacity)?;
+
+                let transform = model.transform();
+                writeln!(out, "translation {}", point3d(&transform.translation))?;
+                writeln!(out, "rotation {}", point3d(&transform.rotation))?;
+                writeln!(out, "scale {}", transform.scale)?;
+            },
+            ModelEntity::Sketch(sketch) => {
+                writeln!(out, "sketch")?;
+                let plane = sketch.plane();
+                writeln!(out, "origin {}", point3d(&plane.origin()))?;
+                writeln!(out, "normal {}", point3d(&plane.normal()))?;
+                writeln!(out, "xaxis {}", point3d(&plane.x_axis()))?;
+                writeln!(out, "yaxis {}", point3d(&plane.y_axis()))?;
+                for p in sketch.points() {
+                    writeln!(out, "point {} {}", p.x, p.y)?;
+                }
+
+                for element in sketch.elements() {
+                    let shape = match element.shape {
+                        Shape::Line { start, end } => format!("line {} {}", start, end),
+                        Shape::Circle { center, radius } => format!("circle {} {}", center, radius),
+                        Shape::Arc { center, start, end } => format!("arc {} {} {}", center, start, end),
+                    };
+
+                    match element.construction {
+                        true => writeln!(out, "{} construction", shape)?,
+                        false => writeln!(out, "{}", shape)?,
+                    }
+                }
+
+                for constraint in sketch.constraints() {
+                    match *constraint {
+                        Constraint::Coincident(a, b) => writeln!(out, "coincident {} {}", a, b)?,
+                        Constraint::Horizontal(e) => writeln!(out, "horizontal {}", e)?,
+                        Constraint::Vertical(e) => writeln!(out, "vertical {}", e)?,
+                        Constraint::Parallel(a, b) => writeln!(out, "parallel {} {}", a, b)?,
+                        Constraint::Perpendicular(a, b) => writeln!(out, "perpendicular {} {}", a, b)?,
+                        Constraint::Tangent(a, b) => writeln!(out, "tangent {} {}", a, b)?,
+                        Constraint::EqualLength(a, b) => writeln!(out, "equal {} {}", a, b)?,
+                        Constraint::Concentric(a, b) => writeln!(out, "concentric {} {}", a, b)?,
+                        Constraint::Fixed { point, at } => writeln!(out, "fixed {} {} {}", point, at.x, at.y)?,
+                        Constraint::Distance { a, b, value } => writeln!(out, "distance {} {} {}", a, b, value)?,
+                        Constraint::Radius { element, value } => writeln!(out, "radius {} {}", element, value)?,
+                        Constraint::Diameter { element, value } => writeln!(out, "diameter {} {}", element, value)?,
+                        Constraint::Angle { a, b, value } => writeln!(out, "angle {} {} {}", a, b, value)?,
+                    }
+                }
+            },
+            ModelEntity::Block(block) => {
+                writeln!(out, "block")?;
+                writeln!(out, "origin {}", point3d(&block.origin()))?;
+                writeln!(out, "dim {}", point3d(&block.dim()))?;
+
+                let anchor = match block.anchor() {
+                    Anchor::Corner => "corner",
+                    Anchor::FaceCenter => "face",
+                    Anchor::Centroid => "centroid",
+                };
+                writeln!(out, "anchor {}", anchor)?;
+
+                let orientation = match block.orientation() {
+                    Orientation::XY => "xy",
+                    Orientation::YZ => "yz",
+                    Orientation::ZX => "zx",
+                };
+                writeln!(out, "orientation {}", orientation)?;
+            },
+            ModelEntity::Extrude(extrude) | ModelEntity::Cut(extrude) => {
+                match entity {
+                    ModelEntity::Cut(_) => writeln!(out, "cut")?,
+                    _ => writeln!(out, "extrude")?,
+                }
+                writeln!(out, "sketch {}", extrude.sketch())?;
+                match extrude.extent() {
+                    Extent::Blind(distance) => writeln!(out, "blind {}", distance)?,
+                    Extent::Symmetric(distance) => writeln!(out, "symmetric {}", distance)?,
+                    Extent::UpToFace { entity, face } => writeln!(out, "upto {} {}", entity, face)?,
+                }
+            },
+            ModelEntity::Revolve(revolve) => {
+                writeln!(out, "revolve")?;
+                writeln!(out, "sketch {}", revolve.sketch())?;
+                match revolve.axis() {
+                    Axis::Line(element) => writeln!(out, "axis line {}", element)?,
+                    Axis::X => writeln!(out, "axis x")?,
+                    Axis::Y => writeln!(out, "axis y")?,
+                    Axis::Z => writeln!(out, "axis z")?,
+                }
+                writeln!(out, "angle {}", revolve.angle())?;
+                writeln!(out, "segments {}", revolve.segments())?;
+            },
+            ModelEntity::Hole(hole) => {
+                writeln!(out, "hole")?;
+                writeln!(out, "face {} {}", hole.entity(), hole.face())?;
+                writeln!(out, "position {} {}", hole.position().x, hole.position().y)?;
+                writeln!(out, "diameter {}", hole.diameter())?;
+                match hole.depth() {
+                    Some(depth) => writeln!(out, "depth {}", depth)?,
+                    None => writeln!(out, "depth through")?,
+                }
+                match hole.kind() {
+                    HoleKind::Simple => writeln!(out, "simple")?,
+                    HoleKind::Counterbore { diameter, depth } => writeln!(out, "counterbore {} {}", diameter, depth)?,
+                    HoleKind::Countersink { diameter, angle } => writeln!(out, "countersink {} {}", diameter, angle)?,
+                    HoleKind::Tapped { callout, thread_depth } => writeln!(out, "tapped {} {}", thread_depth, callout)?,
+                }
+            },
+            ModelEntity::Combine(combine) => {
+                writeln!(out, "combine")?;
+                writeln!(out, "target {}", combine.target())?;
+                writeln!(out, "tool {}", combine.tool())?;
+                match combine.operation() {
+                    Operation::Union => writeln!(out, "operation union")?,
+                    Operation::Subtract => writeln!(out, "operation subtract")?,
+                    Operation::Intersect => writeln!(out, "operation intersect")?,
+                }
+            },
+            ModelEntity::Fillet(fillet) => {
+                writeln!(out, "fillet")?;
+                writeln!(out, "edge {} {}", fillet.entity(), fillet.edge())?;
+                writeln!(out, "radius {}", fillet.radius())?;
+            },
+            ModelEntity::Chamfer(chamfer) => {
+                writeln!(out, "chamfer")?;
+                writeln!(out, "edge {} {}", chamfer.entity(), chamfer.edge())?;
+                match chamfer.size() {
+                    ChamferSize::Distance(distance) => writeln!(out, "distance {}", distance)?,
+                    ChamferSize::DistanceAngle { distance, angle } => writeln!(out, "distance {} angle {}", distance, angle)?,
+                }
+            },
+            ModelEntity::Pattern(pattern) => {
+                writeln!(out, "pattern")?;
+                writeln!(out, "entity {}", pattern.entity())?;
+                match pattern.kind() {
+                    PatternKind::Linear { first, second } => {
+                        for d in std::iter::once(first).chain(second) {
+                            writeln!(out, "linear {} {} {}", point3d(&d.direction), d.count, d.spacing)?;
+                        }
+                    },
+                    PatternKind::Circular { origin, axis, count, angle } => {
+                        writeln!(out, "circular {} {} {} {}", point3d(&origin), point3d(&axis), count, angle)?;
+                    },
+                }
+
+                if !pattern.skip().is_empty() {
+                    let skip: Vec<String> = pattern.skip().iter().map(|n| n.to_string()).collect();
+                    writeln!(out, "skip {}", skip.join(" "))?;
+                }
+            },
+        }
+
+        writeln!(out, "end")?;
+    }
+
+    Ok(())
+}
+
+/// parse a part file
+///
+/// `dir` is the directory the part was read from, which imported meshes use to
+/// resolve their material libraries.
+pub fn read(input: impl BufRead, dir: PathBuf) -> Result<Vec<ModelEntity>, PartError> {
+    let mut reader = Reader { lines: input.lines(), line: 0 };
+
+    let version: u32 = match reader.record()? {
+        Some(header) if header[0] == MAGIC => reader.parse(header.get(1).map(String::as_str))?,
+        _ => return Err(reader.error("missing part header"))
+    };
+    if version > VERSION {
+        return Err(PartError::Version(version));
+    }
+
+    let mut entities = Vec::new();
+    // history index and line of each hole, which are checked once through holes are measured
+    let mut holes = Vec::new();
+    while let Some(record) = reader.record()? {
+        let entity = match words(&record).as_slice() {
+            // version 1 did not name imported models
+            ["imported", count, name @ ..] => {
+                let name = match name {
+                    [] => String::from("ImportedModel"),
+                    name => name.join(" ")
+                };
+                let count = reader.parse(Some(count))?;
+                let text = reader.raw(count)?;
+                let data = ObjData::load_buf(text.as_bytes())?;
+                let mut model = ImportedModel::new(name, Obj { data, path: dir.clone() });
+
+                // materials were added in version 3, transforms in version 5
+                let mut material = Material::default();
+                let mut transform = Transform::default();
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["ambient", rest @ ..] => material.ambient = reader.point3d(rest)?.to_array(),
+                        ["diffuse", rest @ ..] => material.diffuse = reader.point3d(rest)?.to_array(),
+                        ["specular", rest @ ..] => material.specular = reader.point3d(rest)?.to_array(),
+                        ["shininess", value] => material.shininess = reader.parse(Some(value))?,
+                        ["opacity", value] => material.opacity = reader.parse(Some(value))?,
+                        ["translation", rest @ ..] => transform.translation = reader.point3d(rest)?,
+                        ["rotation", rest @ ..] => transform.rotation = reader.point3d(rest)?,
+                        ["scale", value] => transform.scale = reader.parse(Some(value))?,
+                        _ => return Err(reader.error("unexpected imported model field"))
+                    }
+                }
+                model.set_material(material);
+                model.set_transform(transform);
+
+                ModelEntity::ImportedModel(model)
+            },
+            ["sketch"] => {
+                let (mut origin, mut normal, mut x_axis, mut y_axis) = (None, None, None, None);
+                let mut plane = Plane::default();
+                let mut points = Vec::new();
+                let mut shapes = Vec::new();
+                let mut constraints = Vec::new();
+                while let Some(values) = reader.field()? {
+                    let (values, construction) = match words(&values).as_slice() {
+                        [values @ .., "construction"] => (values.to_vec(), true),
+                        values => (values.to_vec(), false),
+                    };
+
+                    match values.as_slice() {
+                        ["origin", rest @ ..] => origin = Some(reader.point3d(rest)?),
+                        ["normal", rest @ ..] => normal = Some(reader.point3d(rest)?),
+                        ["xaxis", rest @ ..] => x_axis = Some(reader.point3d(rest)?),
+                        ["yaxis", rest @ ..] => y_axis = Some(reader.point3d(rest)?),
+                        // before version 6 sketches were always drawn on the XY plane, offset by `z`
+                        ["plane", rest @ ..] => plane = plane.at(Point3d::new(0.0, 0.0, reader.point3d(rest)?.z)),
+                        ["point", x, y] => points.push(Point2d::new(reader.parse(Some(x))?, reader.parse(Some(y))?)),
+                        ["line", start, end] => shapes.push((Shape::Line {
+                            start: reader.parse(Some(start))?,
+                            end: reader.parse(Some(end))?,
+                        }, construction)),
+                        ["circle", center, radius] => shapes.push((Shape::Circle {
+                            center: reader.parse(Some(center))?,
+                            radius: reader.parse(Some(radius))?,
+                        }, construction)),
+                        ["arc", center, start, end] => shapes.push((Shape::Arc {
+                            center: reader.parse(Some(center))?,
+                            start: reader.parse(Some(start))?,
+                            end: reader.parse(Some(end))?,
+                        }, construction)),
+                        // constraints were added in version 8
+                        ["coincident", a, b] => constraints.push(Constraint::Coincident(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["horizontal", e] => constraints.push(Constraint::Horizontal(reader.parse(Some(e))?)),
+                        ["vertical", e] => constraints.push(Constraint::Vertical(reader.parse(Some(e))?)),
+                        ["parallel", a, b] => constraints.push(Constraint::Parallel(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["perpendicular", a, b] => constraints.push(Constraint::Perpendicular(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["tangent", a, b] => constraints.push(Constraint::Tangent(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["equal", a, b] => constraints.push(Constraint::EqualLength(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["concentric", a, b] => constraints.push(Constraint::Concentric(reader.parse(Some(a))?, reader.parse(Some(b))?)),
+                        ["fixed", point, x, y] => constraints.push(Constraint::Fixed {
+                            point: reader.parse(Some(point))?,
+                            at: Point2d::new(reader.parse(Some(x))?, reader.parse(Some(y))?),
+                        }),
+                        // dimensions were added in version 9
+                        ["distance", a, b, value] => constraints.push(Constraint::Distance {
+                            a: reader.parse(Some(a))?,
+                            b: reader.parse(Some(b))?,
+                            value: reader.parse(Some(value))?,
+                        }),
+                        ["radius", element, value] => constraints.push(Constraint::Radius {
+                            element: reader.parse(Some(element))?,
+                            value: reader.parse(Some(value))?,
+                        }),
+                        ["diameter", element, value] => constraints.push(Constraint::Diameter {
+                            element: reader.parse(Some(element))?,
+                            value: reader.parse(Some(value))?,
+                        }),
+                        ["angle", a, b, value] => constraints.push(Constraint::Angle {
+                            a: reader.parse(Some(a))?,
+                            b: reader.parse(Some(b))?,
+                            value: reader.parse(Some(value))?,
+                        }),
+                        _ => return Err(reader.error("unexpected sketch field"))
+                    }
+                }
+
+                let origin = origin.unwrap_or(plane.origin());
+                let plane = match (normal, x_axis, y_axis) {
+                    // saved axes are used as is, so that planes read back exactly
+                    (Some(normal), Some(x_axis), Some(y_axis)) => Plane::from_frame(origin, normal, x_axis, y_axis),
+                    (Some(normal), Some(x_axis), None) => Plane::with_axis(origin, normal, x_axis),
+                    (Some(normal), None, _) => Plane::new(origin, normal),
+                    _ => plane.at(origin)
+                };
+
+                // before version 7 sketches were a polyline through their points
+                if version < 7 {
+                    ModelEntity::Sketch(Sketch::polyline(plane, points))
+                } else {
+                    let mut sketch = Sketch::on_plane(plane);
+                    for p in points {
+                        sketch.add_point(p);
+                    }
+
+                    for (shape, construction) in shapes {
+                        let referenced = match shape {
+                            Shape::Line { start, end } => vec![start, end],
+                            Shape::Circle { center, .. } => vec![center],
+                            Shape::Arc { center, start, end } => vec![center, start, end],
+                        };
+                        if referenced.iter().any(|&i| i >= sketch.points().len()) {
+                            return Err(reader.error("sketch element refers to a missing point"));
+                        }
+
+                        sketch.add(shape, construction);
+                    }
+
+                    for constraint in constraints {
+                        let missing_point = constraint.points().iter().any(|&i| i >= sketch.points().len());
+                        let missing_element = constraint.elements().iter().any(|&i| i >= sketch.elements().len());
+                        if missing_point || missing_element {
+                            return Err(reader.error("sketch constraint refers to missing geometry"));
+                        }
+
+                        sketch.push_constraint(constraint);
+                    }
+
+                    // saved geometry already satisfies its constraints, so solving leaves it as is
+                    sketch.solve();
+
+                    ModelEntity::Sketch(sketch)
+                }
+            },
+            // blocks before version 4 are anchored at their corner on the XY plane
+            ["block"] => {
+                let (mut origin, mut dim) = (Point3d::default(), Point3d::default());
+                let (mut anchor, mut orientation) = (Anchor::default(), Orientation::default());
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["origin", rest @ ..] => origin = reader.point3d(rest)?,
+                        ["dim", rest @ ..] => dim = reader.point3d(rest)?,
+                        ["anchor", "corner"] => anchor = Anchor::Corner,
+                        ["anchor", "face"] => anchor = Anchor::FaceCenter,
+                        ["anchor", "centroid"] => anchor = Anchor::Centroid,
+                        ["orientation", "xy"] => orientation = Orientation::XY,
+                        ["orientation", "yz"] => orientation = Orientation::YZ,
+                        ["orientation", "zx"] => orientation = Orientation::ZX,
+                        _ => return Err(reader.error("unexpected block field"))
+                    }
+                }
+
+                ModelEntity::Block(Block::new(origin, dim, anchor, orientation))
+            },
+            // extrusions were added in version 10
+            // cuts were added in version 12
+            [kind @ ("extrude" | "cut")] => {
+                let cut = *kind == "cut";
+                let (mut sketch, mut extent) = (None, Extent::Blind(0.0));
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["sketch", index] => sketch = Some(reader.parse::<usize>(Some(index))?),
+                        ["blind", distance] => extent = Extent::Blind(reader.parse(Some(distance))?),
+                        ["symmetric", distance] => extent = Extent::Symmetric(reader.parse(Some(distance))?),
+                        ["upto", entity, face] => extent = Extent::UpToFace {
+                            entity: reader.parse(Some(entity))?,
+                            face: reader.parse(Some(face))?,
+                        },
+                        _ => return Err(reader.error("unexpected extrude field"))
+                    }
+                }
+
+                let extrude = match sketch {
+                    Some(sketch) => Extrude::new(sketch, extent),
+                    None => return Err(reader.error("extrude is missing its sketch")),
+                };
+
+                // features are built from entities earlier in the history
+                if extrude.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("extrude refers to a later entity"));
+                }
+
+                match cut {
+                    true => ModelEntity::Cut(extrude),
+                    false => ModelEntity::Extrude(extrude),
+                }
+            },
+            // revolves were added in version 11
+            ["revolve"] => {
+                let (mut sketch, mut axis, mut angle, mut segments) = (None, Axis::X, 360.0, 48);
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["sketch", index] => sketch = Some(reader.parse::<usize>(Some(index))?),
+                        ["axis", "line", element] => axis = Axis::Line(reader.parse(Some(element))?),
+                        ["axis", "x"] => axis = Axis::X,
+                        ["axis", "y"] => axis = Axis::Y,
+                        ["axis", "z"] => axis = Axis::Z,
+                        ["angle", value] => angle = reader.parse(Some(value))?,
+                        ["segments", value] => segments = reader.parse(Some(value))?,
+                        _ => return Err(reader.error("unexpected revolve field"))
+                    }
+                }
+
+                let revolve = match sketch {
+                    Some(sketch) => Revolve::new(sketch, axis, angle, segments),
+                    None => return Err(reader.error("revolve is missing its sketch")),
+                };
+
+                if revolve.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("revolve refers to a later entity"));
+                }
+
+                ModelEntity::Revolve(revolve)
+            },
+            // holes were added in version 12
+            ["hole"] => {
+                let (mut face, mut position, mut diameter) = (None, Point2d::default(), 1.0);
+                let (mut depth, mut kind) = (None, HoleKind::Simple);
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["face", entity, index] => face = Some((reader.parse::<usize>(Some(entity))?, reader.parse(Some(index))?)),
+                        ["position", x, y] => position = Point2d::new(reader.parse(Some(x))?, reader.parse(Some(y))?),
+                        ["diameter", value] => diameter = reader.parse(Some(value))?,
+                        ["depth", "through"] => depth = None,
+                        ["depth", value] => depth = Some(reader.parse(Some(value))?),
+                        ["simple"] => kind = HoleKind::Simple,
+                        ["counterbore", diameter, depth] => kind = HoleKind::Counterbore {
+                            diameter: reader.parse(Some(diameter))?,
+                            depth: reader.parse(Some(depth))?,
+                        },
+                        ["countersink", diameter, angle] => kind = HoleKind::Countersink {
+                            diameter: reader.parse(Some(diameter))?,
+                            angle: reader.parse(Some(angle))?,
+                        },
+                        ["tapped", thread_depth, callout @ ..] => kind = HoleKind::Tapped {
+                            callout: callout.join(" "),
+                            thread_depth: reader.parse(Some(thread_depth))?,
+                        },
+                        _ => return Err(reader.error("unexpected hole field"))
+                    }
+                }
+
+                let hole = match face {
+                    Some((entity, face)) => Hole::new(entity, face, position, diameter, depth, kind),
+                    None => return Err(reader.error("hole is missing its face")),
+                };
+
+                if hole.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("hole refers to a later entity"));
+                }
+
+                holes.push((entities.len(), reader.line));
+                ModelEntity::Hole(hole)
+            },
+            // combines were added in version 13
+            ["combine"] => {
+                let (mut target, mut tool, mut operation) = (None, None, Operation::Union);
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["target", index] => target = Some(reader.parse::<usize>(Some(index))?),
+                        ["tool", index] => tool = Some(reader.parse::<usize>(Some(index))?),
+                        ["operation", "union"] => operation = Operation::Union,
+                        ["operation", "subtract"] => operation = Operation::Subtract,
+                        ["operation", "intersect"] => operation = Operation::Intersect,
+                        _ => return Err(reader.error("unexpected combine field"))
+                    }
+                }
+
+                let combine = match (target, tool) {
+                    (Some(target), Some(tool)) => Combine::new(target, tool, operation),
+                    _ => return Err(reader.error("combine is missing its target or tool")),
+                };
+
+                if combine.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("combine refers to a later entity"));
+                }
+
+                ModelEntity::Combine(combine)
+            },
+            // fillets and chamfers were added in version 14
+            ["fillet"] => {
+                let (mut edge, mut radius) = (None, 1.0);
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["edge", entity, index] => edge = Some((reader.parse::<usize>(Some(entity))?, reader.parse(Some(index))?)),
+                        ["radius", value] => radius = reader.parse(Some(value))?,
+                        _ => return Err(reader.error("unexpected fillet field"))
+                    }
+                }
+
+                let fillet = match edge {
+                    Some((entity, edge)) => Fillet::new(entity, edge, radius),
+                    None => return Err(reader.error("fillet is missing its edge")),
+                };
+
+                if fillet.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("fillet refers to a later entity"));
+                }
+
+                ModelEntity::Fillet(fillet)
+            },
+            ["chamfer"] => {
+                let (mut edge, mut size) = (None, ChamferSize::Distance(1.0));
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["edge", entity, index] => edge = Some((reader.parse::<usize>(Some(entity))?, reader.parse(Some(index))?)),
+                        ["distance", value] => size = ChamferSize::Distance(reader.parse(Some(value))?),
+                        ["distance", distance, "angle", angle] => size = ChamferSize::DistanceAngle {
+                            distance: reader.parse(Some(distance))?,
+                            angle: reader.parse(Some(angle))?,
+                        },
+                        _ => return Err(reader.error("unexpected chamfer field"))
+                    }
+                }
+
+                let chamfer = match edge {
+                    Some((entity, edge)) => Chamfer::new(entity, edge, size),
+                    None => return Err(reader.error("chamfer is missing its edge")),
+                };
+
+                if chamfer.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("chamfer refers to a later entity"));
+                }
+
+                ModelEntity::Chamfer(chamfer)
+            },
+            // patterns were added in version 15
+            ["pattern"] => {
+                let (mut entity, mut directions, mut circular, mut skip) = (None, Vec::new(), None, Vec::new());
+                while let Some(values) = reader.field()? {
+                    match words(&values).as_slice() {
+                        ["entity", index] => entity = Some(reader.parse::<usize>(Some(index))?),
+                        ["linear", x, y, z, count, spacing] => directions.push(Direction {
+                            direction: reader.point3d(&[x, y, z])?,
+                            count: reader.parse(Some(count))?,
+                            spacing: reader.parse(Some(spacing))?,
+                        }),
+                        ["circular", ox, oy, oz, ax, ay, az, count, angle] => circular = Some(PatternKind::Circular {
+                            origin: reader.point3d(&[ox, oy, oz])?,
+                            axis: reader.point3d(&[ax, ay, az])?,
+                            count: reader.parse(Some(count))?,
+                            angle: reader.parse(Some(angle))?,
+                        }),
+                        ["skip", numbers @ ..] => for n in numbers {
+                            skip.push(reader.parse::<usize>(Some(n))?);
+                        },
+                        _ => return Err(reader.error("unexpected pattern field"))
+                    }
+                }
+
+                let kind = match (circular, directions.as_slice()) {
+                    (Some(kind), []) => kind,
+                    (None, [first]) => PatternKind::Linear { first: *first, second: None },
+                    (None, [first, second]) => PatternKind::Linear { first: *first, second: Some(*second) },
+                    _ => return Err(reader.error("pattern needs one or two directions, or an axis")),
+                };
+                let pattern = match entity {
+                    Some(entity) => Pattern::new(entity, kind, skip),
+                    None => return Err(reader.error("pattern is missing its entity")),
+                };
+
+                if pattern.references().iter().any(|&i| i >= entities.len()) {
+                    return Err(reader.error("pattern refers to a later entity"));
+                }
+
+                ModelEntity::Pattern(pattern)
+            },
+            _ => return Err(reader.error("unknown entity"))
+        };
+
+        entities.push(entity);
+    }
+
+    crate::model::regenerate(&mut entities);
+
+    for (index, line) in holes {
+        if let ModelEntity::Hole(hole) = &entities[index] {
+            if let Some(error) = hole.error() {
+                return Err(PartError::Parse { line, reason: error.into() });
+            }
+        }
+    }
+
+    Ok(entities)
+}
+
+fn words(record: &[String]) -> Vec<&str> {
+    record.iter().map(String::as_str).collect()
+}
+
+fn point3d(point: &Point3d) -> String {
+    format!("{} {} {}", point.x, point.y, point.z)
+}
+
+struct Reader<L> {
+    lines: L,
+    line: usize,
+}
+
+impl<L: Iterator<Item = io::Result<String>>> Reader<L> {
+    fn error(&self, reason: &str) -> PartError {
+        PartError::Parse { line: self.line, reason: reason.into() }
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, PartError> {
+        match self.lines.next() {
+            Some(line) => {
+                self.line += 1;
+                Ok(Some(line?))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// next non-empty, non-comment line split into words
+    fn record(&mut self) -> Result<Option<Vec<String>>, PartError> {
+        while let Some(line) = self.next_line()? {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            return Ok(Some(line.split_whitespace().map(String::from).collect()));
+        }
+
+        Ok(None)
+    }
+
+    /// next record of the current entity, or `None` at its closing `end`
+    fn field(&mut self) -> Result<Option<Vec<String>>, PartError> {
+        match self.record()? {
+            Some(record) if record == ["end"] => Ok(None),
+            Some(record) => Ok(Some(record)),
+            None => Err(self.error("unexpected end of file, expected `end`"))
+        }
+    }
+
+    /// `count` lines verbatim
+    fn raw(&mut self, count: usize) -> Result<String, PartError> {
+        let mut text = String::new();
+        for _ in 0..count {
+            match self.next_line()? {
+                Some(line) => {
+                    text.push_str(&line);
+                    text.push('\n');
+                },
+                None => return Err(self.error("unexpected end of file in embedded mesh"))
+            }
+        }
+
+        Ok(text)
+    }
+
+    fn parse<T: std::str::FromStr>(&self, value: Option<&str>) -> Result<T, PartError> {
+        value
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| self.error(&format!("invalid value `{}`", value.unwrap_or_default())))
+    }
+
+    fn point3d(&self, values: &[&str]) -> Result<Point3d, PartError> {
+        match values {
+            [x, y, z] => Ok(Point3d {
+                x: self.parse(Some(x))?,
+                y: self.parse(Some(y))?,
+                z: self.parse(Some(z))?,
+            }),
+            _ => Err(self.error("expected 3 coordinates"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: [&str; 8] = [
+        "cube.obj",
+        "cube2.obj",
+        "random.obj",
+        "sketch.obj",
+        "teapot.obj",
+        "trapezoid.obj",
+        "twobodies.obj",
+        "twocubes_blender.obj",
+    ];
+
+    fn fixture(name: &str) -> ModelEntity {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("models").join(name);
+
+        ModelEntity::ImportedModel(ImportedModel::new(name.into(), Obj::load(path).unwrap()))
+    }
+
+    fn sample() -> Vec<ModelEntity> {
+        vec![
+            ModelEntity::Block(Block::origin_and_max(Point3d { x: 3.0, y: 4.0, z: 5.0 })),
+            ModelEntity::Sketch(Sketch::polyline(
+                Plane::xy().at(Point3d { x: 0.0, y: 0.0, z: 1.5 }),
+                vec![Point2d::new(0.0, 0.0), Point2d::new(10.25, -3.0), Point2d::new(0.1, 7.0)]
+            )),
+            ModelEntity::Block(Block::two_points(
+                Point3d { x: -1.0, y: -2.5, z: 0.3 },
+                Point3d { x: 1.0, y: 1.0, z: 1.0 }
+            )),
+            ModelEntity::Sketch(curves()),
+            ModelEntity::Block(Block::new(
+                Point3d { x: 4.0, y: 0.0, z: -1.0 },
+                Point3d { x: 2.0, y: 3.0, z: 0.5 },
+                Anchor::Centroid,
+                Orientation::ZX
+            )),
+            ModelEntity::Extrude(Extrude::new(3, Extent::Blind(-2.5))),
+            ModelEntity::Extrude(Extrude::new(3, Extent::UpToFace { entity: 0, face: 4 })),
+            ModelEntity::Revolve(Revolve::new(3, Axis::Line(2), 270.0, 24)),
+            ModelEntity::Revolve(Revolve::new(1, Axis::Z, 360.0, 64)),
+            ModelEntity::Cut(Extrude::new(1, Extent::Symmetric(0.5))),
+            ModelEntity::Hole(Hole::new(0, 5, Point2d::new(0.5, -0.25), 0.3, None, HoleKind::Simple)),
+            ModelEntity::Hole(Hole::new(2, 1, Point2d::default(), 0.2, Some(0.4), HoleKind::Counterbore { diameter: 0.4, depth: 0.1 })),
+            ModelEntity::Hole(Hole::new(0, 4, Point2d::default(), 0.25, Some(1.0), HoleKind::Countersink { diameter: 0.5, angle: 82.0 })),
+            ModelEntity::Hole(Hole::new(0, 4, Point2d::new(1.0, 1.0), 0.5, Some(1.5), HoleKind::Tapped { callout: String::from("M6x1.0 6H"), thread_depth: 1.2 })),
+            ModelEntity::Combine(Combine::new(0, 2, Operation::Union)),
+            ModelEntity::Combine(Combine::new(2, 4, Operation::Intersect)),
+            ModelEntity::Fillet(Fillet::new(0, 3, 0.25)),
+            ModelEntity::Chamfer(Chamfer::new(2, 0, ChamferSize::Distance(0.1))),
+            ModelEntity::Chamfer(Chamfer::new(4, 7, ChamferSize::DistanceAngle { distance: 0.2, angle: 30.0 })),
+            ModelEntity::Pattern(Pattern::new(4, PatternKind::Linear {
+                first: Direction { direction: Point3d { x: 1.0, y: 0.0, z: 0.0 }, count: 3, spacing: 2.5 },
+                second: Some(Direction { direction: Point3d { x: 0.0, y: 0.5, z: 0.5 }, count: 2, spacing: 1.25 }),
+            }, vec![2, 4])),
+            ModelEntity::Pattern(Pattern::new(10, PatternKind::Linear {
+                first: Direction { direction: Point3d { x: 0.0, y: 1.0, z: 0.0 }, count: 2, spacing: 0.5 },
+                second: None,
+            }, Vec::new())),
+            ModelEntity::Pattern(Pattern::new(2, PatternKind::Circular {
+                origin: Point3d { x: 0.5, y: 0.0, z: 0.0 },
+                axis: Point3d { x: 0.0, y: 0.0, z: 1.0 },
+                count: 6,
+                angle: 360.0,
+            }, vec![3])),
+        ]
+    }
+
+    fn curves() -> Sketch {
+        let mut sketch = Sketch::on_plane(Plane::new(Point3d { x: 1.0, y: 2.0, z: 3.0 }, Point3d { x: 0.0, y: 1.0, z: 1.0 }));
+        let center = sketch.add_point(Point2d::new(0.0, 0.0));
+        let start = sketch.add_point(Point2d::new(1.0, 0.0));
+        let end = sketch.add_point(Point2d::new(0.0, 1.0));
+
+        sketch.add(Shape::Circle { center, radius: 2.5 }, false);
+        sketch.add(Shape::Arc { center, start, end }, false);
+        let line = sketch.add(Shape::Line { start: center, end: start }, true);
+
+        sketch.add_constraint(Constraint::Fixed { point: center, at: Point2d::new(0.0, 0.0) });
+        sketch.add_constraint(Constraint::Horizontal(line));
+        sketch.add_constraint(Constraint::EqualLength(0, 1));
+        sketch.add_constraint(Constraint::Radius { element: 0, value: 1.5 });
+        sketch.add_constraint(Constraint::Distance { a: center, b: end, value: 1.5 });
+
+        sketch
+    }
+
+    fn roundtrip(entities: &[ModelEntity]) -> Vec<ModelEntity> {
+        let mut buf = Vec::new();
+        write(entities, &mut buf).unwrap();
+
+        read(buf.as_slice(), PathBuf::new()).unwrap()
+    }
+
+    fn assert_same(expected: &[ModelEntity], actual: &[ModelEntity]) {
+        assert_eq!(expected.len(), actual.len());
+
+        for (a, b) in expected.iter().zip(actual) {
+            match (a, b) {
+                (ModelEntity::ImportedModel(a), ModelEntity::ImportedModel(b)) => {
+                    let (a_data, b_data) = (&a.obj().data, &b.obj().data);
+
+                    assert_eq!(a.name(), b.name());
+                    assert_eq!(a.material(), b.material());
+                    assert_eq!(a.transform(), b.transform());
+                    assert_eq!(a_data.position, b_data.position);
+                    assert_eq!(a_data.normal, b_data.normal);
+                    assert_eq!(a_data.texture, b_data.texture);
+                    assert_eq!(a_data.objects, b_data.objects);
+                },
+                (ModelEntity::Sketch(a), ModelEntity::Sketch(b)) => assert_eq!(a, b),
+                (ModelEntity::Block(a), ModelEntity::Block(b)) => assert_eq!(a, b),
+                (ModelEntity::Extrude(a), ModelEntity::Extrude(b)) | (ModelEntity::Cut(a), ModelEntity::Cut(b)) => {
+                    assert_eq!(a.sketch(), b.sketch());
+                    assert_eq!(a.extent(), b.extent());
+                },
+                (ModelEntity::Revolve(a), ModelEntity::Revolve(b)) => {
+                    assert_eq!((a.sketch(), a.axis(), a.angle(), a.segments()), (b.sketch(), b.axis(), b.angle(), b.segments()));
+                },
+                (ModelEntity::Hole(a), ModelEntity::Hole(b)) => {
+                    assert_eq!((a.entity(), a.face(), a.position(), a.diameter(), a.depth()), (b.entity(), b.face(), b.position(), b.diameter(), b.depth()));
+                    assert_eq!(a.kind(), b.kind());
+                },
+                (ModelEntity::Combine(a), ModelEntity::Combine(b)) => {
+                    assert_eq!((a.target(), a.tool(), a.operation()), (b.target(), b.tool(), b.operation()));
+                },
+                (ModelEntity::Fillet(a), ModelEntity::Fillet(b)) => {
+                    assert_eq!((a.entity(), a.edge(), a.radius()), (b.entity(), b.edge(), b.radius()));
+                },
+                (ModelEntity::Chamfer(a), ModelEntity::Chamfer(b)) => {
+                    assert_eq!((a.entity(), a.edge(), a.size()), (b.entity(), b.edge(), b.size()));
+                },
+                (ModelEntity::Pattern(a), ModelEntity::Pattern(b)) => {
+                    assert_eq!((a.entity(), a.kind(), a.skip()), (b.entity(), b.kind(), b.skip()));
+                },
+                _ => panic!("entity kind changed: {} -> {}", a, b)
+            }
+        }
+    }
+
+    #[test]
+    fn roundtrip_parametric() {
+        let entities = sample();
+
+        assert_same(&entities, &roundtrip(&entities));
+    }
+
+    #[test]
+    fn roundtrip_fixtures() {
+        for name in FIXTURES {
+            let entities = vec![fixture(name)];
+
+            assert_same(&entities, &roundtrip(&entities));
+        }
+    }
+
+    #[test]
+    fn roundtrip_mixed_history() {
+        let mut imported = fixture("twobodies.obj");
+        if let ModelEntity::ImportedModel(model) = &mut imported {
+            model.set_material(Material { diffuse: [0.1, 0.2, 0.3], opacity: 0.5, ..Default::default() });
+            model.set_transform(Transform {
+                translation: Point3d::new(1.0, -2.0, 0.5),
+                rotation: Point3d::new(0.0, 45.0, 90.0),
+                scale: 2.5,
+            });
+        }
+
+        let mut entities = sample();
+        entities.insert(1, imported);
+        entities.push(fixture("cube.obj"));
+
+        assert_same(&entities, &roundtrip(&entities));
+    }
+
+    #[test]
+    fn roundtrip_file() {
+        let path = std::env::temp_dir().join(format!("phobia-roundtrip-{}.ph", std::process::id()));
+        let mut entities = sample();
+        entities.push(fixture("trapezoid.obj"));
+
+        save(&entities, &path).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_same(&entities, &loaded.unwrap());
+    }
+
+    #[test]
+    fn empty_model() {
+        assert!(roundtrip(&[]).is_empty());
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let text = format!("{} {}\n", MAGIC, VERSION + 1);
+
+        assert!(matches!(read(text.as_bytes(), PathBuf::new()), Err(PartError::Version(_))));
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(matches!(read("block\nend\n".as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })));
+    }
+
+    #[test]
+    fn rejects_unterminated_entity() {
+        let text = format!("{} {}\nblock\norigin 0 0 0\n", MAGIC, VERSION);
+
+        assert!(matches!(read(text.as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })));
+    }
+
+    #[test]
+    fn reads_unnamed_version_1_import() {
+        let text = "phobia-part 1\nimported 4\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nend\n";
+        let entities = read(text.as_bytes(), PathBuf::new()).unwrap();
+
+        match entities.as_slice() {
+            [ModelEntity::ImportedModel(model)] => {
+                assert_eq!(model.name(), "ImportedModel");
+                assert_eq!(model.obj().data.position.len(), 3);
+            },
+            _ => panic!("expected a single imported model")
+        }
+    }
+
+    #[test]
+    fn reads_version_5_sketch_plane() {
+        let text = "phobia-part 5\nsketch\nplane 0 0 2\npoint 1 3\nend\n";
+        let entities = read(text.as_bytes(), PathBuf::new()).unwrap();
+
+        match entities.as_slice() {
+            [ModelEntity::Sketch(sketch)] => {
+                assert_eq!(sketch.plane(), &Plane::xy().at(Point3d::new(0.0, 0.0, 2.0)));
+                assert_eq!(sketch.plane().point(&sketch.points()[0]), Point3d::new(1.0, 3.0, 2.0));
+            },
+            _ => panic!("expected a single sketch")
+        }
+    }
+
+    #[test]
+    fn rejects_missing_sketch_point() {
+        let text = format!("{} {}\nsketch\npoint 0 0\nline 0 1\nend\n", MAGIC, VERSION);
+
+        assert!(matches!(read(text.as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })));
+    }
+
+    #[test]
+    fn rejects_missing_constraint_element() {
+        let text = format!("{} {}\nsketch\npoint 0 0\npoint 1 0\nline 0 1\nparallel 0 1\nend\n", MAGIC, VERSION);
+
+        assert!(matches!(read(text.as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })));
+    }
+
+    #[test]
+    fn rejects_forward_extrude_reference() {
+        let text = format!("{} {}\nextrude\nsketch 1\nblind 1\nend\nsketch\nend\n", MAGIC, VERSION);
+
+        assert!(matches!(read(text.as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })));
+    }
+
+    #[test]
+    fn rejects_impossible_hole() {
+        let hole = |kind: &str| format!("{} {}\nblock\ndim 10 10 5\nend\nhole\nface 0 0\ndiameter 2\ndepth 3\n{}\nend\n", MAGIC, VERSION, kind);
+
+        assert!(read(hole("counterbore 4 1").as_bytes(), PathBuf::new()).is_ok());
+        for kind in ["counterbore 1 1", "counterbore 4 3", "countersink 2 90", "countersink 4 0", "countersink 8 90"] {
+            assert!(matches!(read(hole(kind).as_bytes(), PathBuf::new()), Err(PartError::Parse { .. })), "{}", kind);
+        }
+
+        // a through hole is only as deep as the block
+        let through = format!("{} {}\nblock\ndim 10 10 5\nend\nhole\nface 0 0\ndiameter 2\ncounterbore 4 12\nend\n", MAGIC, VERSION);
+        assert!(matches!(read(through.as_bytes(), PathBuf::new()), Err(PartError::Parse { line: 9, .. })));
+    }
+
+    #[test]
+    fn face_references_survive_a_roundtrip() {
+        // a square extruded up by 2, saved before solids had faces of their own, with a hole
+        // in face 1, the end of the sweep, and a second extrusion up to it
+        let text = format!(
+            "{} 12\nsketch\norigin 0 0 0\nnormal 0 0 1\nxaxis 1 0 0\nyaxis 0 1 0\n{}{}end\n{}{}{}",
+            MAGIC,
+            "point 0 0\npoint 4 0\npoint 4 4\npoint 0 4\n",
+            "line 0 1\nline 1 2\nline 2 3\nline 3 0\n",
+            "extrude\nsketch 0\nblind 2\nend\n",
+            "hole\nface 1 1\nposition 0 0\ndiameter 1\ndepth 1\nsimple\nend\n",
+            "extrude\nsketch 0\nupto 1 1\nend\n",
+        );
+
+        let mut entities = read(text.as_bytes(), PathBuf::new()).unwrap();
+        let mut reloaded = roundtrip(&entities);
+        assert_same(&entities, &reloaded);
+
+        for entities in [&mut entities, &mut reloaded] {
+            crate::model::regenerate(entities);
+
+            let faces = entities[1].faces();
+            assert_eq!((faces[0].normal().z, faces[1].normal().z), (-1.0, 1.0));
+
+            // the hole goes down from the top, and the second extrusion ends there
+            let hole = entities[2].edges();
+            assert!(hole.iter().all(|v| v.position[2] <= 2.0 + 1e-4 && v.position[2] >= 1.0 - 1e-4));
+
+            let top = entities[3].vertices().iter().map(|v| v.position[2]).fold(f32::MIN, f32::max);
+            assert!((top - 2.0).abs() < 1e-4);
+        }
+    }
+}
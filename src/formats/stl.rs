@@ -0,0 +1,329 @@
+
+//! stereolithography (`.stl`) files
+//!
+//! Both the ASCII and binary flavors are read. Files are written as binary,
+//! which is smaller and what most slicers expect.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+};
+
+use obj::{Group, IndexTuple, Object, ObjData, SimplePolygon};
+
+use crate::prelude::*;
+
+const HEADER_LEN: usize = 80;
+const FACET_LEN: usize = 50;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Facet {
+    pub normal: Point3d,
+    pub vertices: [Point3d; 3],
+}
+
+impl Facet {
+    /// build a facet, computing the normal from the winding if `normal` is zero
+    pub fn new(normal: Point3d, vertices: [Point3d; 3]) -> Self {
+        let normal = match normal.length() {
+            len if len > f32::EPSILON => normal / len,
+            _ => face_normal(&vertices)
+        };
+
+        Self { normal, vertices }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Solid {
+    pub name: String,
+    pub facets: Vec<Facet>,
+}
+
+impl Solid {
+    /// convert to obj data so that the solid can be used as an imported model
+    pub fn to_obj_data(&self) -> ObjData {
+        let mut data = ObjData::default();
+        let mut group = Group::new(self.name.clone());
+
+        // stl repeats every shared corner, so merge identical positions
+        let mut indices = std::collections::HashMap::new();
+        for facet in self.facets.iter() {
+            data.normal.push(facet.normal.to_array());
+            let normal = data.normal.len() - 1;
+
+            let poly = facet.vertices.iter()
+                .map(|v| {
+                    let key = v.to_array().map(f32::to_bits);
+                    let index = *indices.entry(key).or_insert_with(|| {
+                        data.position.push(v.to_array());
+                        data.position.len() - 1
+                    });
+
+                    IndexTuple(index, None, Some(normal))
+                })
+                .collect();
+
+            group.polys.push(SimplePolygon(poly));
+        }
+
+        let mut object = Object::new(self.name.clone());
+        object.groups.push(group);
+        data.objects.push(object);
+
+        data
+    }
+}
+
+pub fn load(path: &Path) -> io::Result<Solid> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let mut solid = parse(&bytes)?;
+    if solid.name.is_empty() {
+        solid.name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().into())
+            .unwrap_or_else(|| String::from("stl"));
+    }
+
+    Ok(solid)
+}
+
+/// parse either flavor of stl
+pub fn parse(bytes: &[u8]) -> io::Result<Solid> {
+    // binary files may also start with `solid`, so the size is the more reliable check
+    if is_binary(bytes) {
+        parse_binary(bytes)
+    } else {
+        parse_ascii(&String::from_utf8_lossy(bytes))
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    if bytes.len() < HEADER_LEN + 4 {
+        return false;
+    }
+
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    bytes.len() == HEADER_LEN + 4 + count * FACET_LEN || !bytes.starts_with(b"solid")
+}
+
+fn parse_binary(bytes: &[u8]) -> io::Result<Solid> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if bytes.len() < HEADER_LEN + 4 {
+        return Err(invalid("binary stl is missing its header"));
+    }
+
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let body = &bytes[HEADER_LEN + 4..];
+    if body.len() < count * FACET_LEN {
+        return Err(invalid("binary stl is truncated"));
+    }
+
+    let float = |b: &[u8], i: usize| f32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+    let point = |b: &[u8], i: usize| Point3d::new(float(b, i), float(b, i + 4), float(b, i + 8));
+
+    let facets = body
+        .chunks_exact(FACET_LEN)
+        .take(count)
+        .map(|f| Facet::new(point(f, 0), [point(f, 12), point(f, 24), point(f, 36)]))
+        .collect();
+
+    Ok(Solid { name: String::new(), facets })
+}
+
+fn parse_ascii(text: &str) -> io::Result<Solid> {
+    let invalid = |line: usize, msg: &str| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line + 1, msg)
+    );
+
+    let mut solid = Solid::default();
+    let mut normal = Point3d::default();
+    let mut vertices = Vec::with_capacity(3);
+
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let parse = |words: std::str::SplitWhitespace| -> io::Result<Point3d> {
+            let values: Vec<f32> = words
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(i, "invalid number"))?;
+
+            match values.as_slice() {
+                [x, y, z] => Ok(Point3d::new(*x, *y, *z)),
+                _ => Err(invalid(i, "expected 3 coordinates"))
+            }
+        };
+
+        match words.next() {
+            Some("solid") => solid.name = words.collect::<Vec<_>>().join(" "),
+            Some("facet") => {
+                if words.next() != Some("normal") {
+                    return Err(invalid(i, "expected `facet normal`"));
+                }
+
+                normal = parse(words)?;
+                vertices.clear();
+            },
+            Some("vertex") => vertices.push(parse(words)?),
+            Some("endfacet") => match vertices.as_slice() {
+                [a, b, c] => solid.facets.push(Facet::new(normal, [*a, *b, *c])),
+                _ => return Err(invalid(i, "facet does not have 3 vertices"))
+            },
+            _ => ()
+        }
+    }
+
+    Ok(solid)
+}
+
+/// save a triangle list as binary stl
+pub fn save(path: &Path, vertices: &[Vertex]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_binary(vertices, &mut out)?;
+
+    out.flush()
+}
+
+pub fn write_binary(vertices: &[Vertex], out: &mut impl Write) -> io::Result<()> {
+    let facets = facets(vertices);
+
+    let mut header = [0u8; HEADER_LEN];
+    let title = format!("binary stl exported by {}", crate::config::TITLE);
+    header[..title.len()].copy_from_slice(title.as_bytes());
+
+    out.write_all(&header)?;
+    out.write_all(&(facets.len() as u32).to_le_bytes())?;
+
+    for facet in facets {
+        for p in std::iter::once(&facet.normal).chain(facet.vertices.iter()) {
+            for value in p.to_array() {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        // attribute byte count, unused
+        out.write_all(&[0, 0])?;
+    }
+
+    Ok(())
+}
+
+pub fn write_ascii(name: &str, vertices: &[Vertex], out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "solid {}", name)?;
+
+    for facet in facets(vertices) {
+        let n = facet.normal;
+        writeln!(out, "  facet normal {:e} {:e} {:e}", n.x, n.y, n.z)?;
+        writeln!(out, "    outer loop")?;
+        for v in facet.vertices {
+            writeln!(out, "      vertex {:e} {:e} {:e}", v.x, v.y, v.z)?;
+        }
+        writeln!(out, "    endloop")?;
+        writeln!(out, "  endfacet")?;
+    }
+
+    writeln!(out, "endsolid {}", name)
+}
+
+/// group a triangle list into facets
+fn facets(vertices: &[Vertex]) -> Vec<Facet> {
+    vertices
+        .chunks_exact(3)
+        .map(|tri| {
+            let points = [
+                Point3d::from(tri[0].position),
+                Point3d::from(tri[1].position),
+                Point3d::from(tri[2].position),
+            ];
+
+            // prefer the winding; fall back to the vertex normals for degenerate triangles
+            let normal = match face_normal(&points) {
+                n if n.length() > 0.0 => n,
+                _ => tri.iter()
+                    .map(|v| Point3d::from(v.normal))
+                    .fold(Point3d::default(), |acc, n| acc + n)
+                    .normalize()
+            };
+
+            Facet { normal, vertices: points }
+        })
+        .collect()
+}
+
+fn face_normal(vertices: &[Point3d; 3]) -> Point3d {
+    let [a, b, c] = *vertices;
+
+    (b - a).cross(&(c - a)).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangles() -> Vec<Vertex> {
+        [
+            [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0],
+        ]
+        .into_iter()
+        .map(|position| Vertex { position, ..Default::default() })
+        .collect()
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let mut buf = Vec::new();
+        write_binary(&triangles(), &mut buf).unwrap();
+        assert_eq!(buf.len(), HEADER_LEN + 4 + 2 * FACET_LEN);
+
+        let solid = parse(&buf).unwrap();
+        assert_eq!(solid.facets, facets(&triangles()));
+        assert_eq!(solid.facets[0].normal, Point3d::new(0.0, 0.0, 1.0));
+        assert_eq!(solid.facets[1].normal, Point3d::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ascii_roundtrip() {
+        let mut buf = Vec::new();
+        write_ascii("part", &triangles(), &mut buf).unwrap();
+
+        let solid = parse(&buf).unwrap();
+        assert_eq!(solid.name, "part");
+        assert_eq!(solid.facets, facets(&triangles()));
+    }
+
+    #[test]
+    fn zero_normals_are_computed() {
+        let text = "solid zero
+            facet normal 0 0 0
+              outer loop
+                vertex 0 0 0
+                vertex 0 0 2
+                vertex 2 0 0
+              endloop
+            endfacet
+            endsolid zero";
+
+        let solid = parse(text.as_bytes()).unwrap();
+        assert_eq!(solid.facets.len(), 1);
+        assert_eq!(solid.facets[0].normal, Point3d::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn obj_data_merges_corners() {
+        let solid = parse_binary(&{
+            let mut buf = Vec::new();
+            write_binary(&triangles(), &mut buf).unwrap();
+            buf
+        }).unwrap();
+
+        let data = solid.to_obj_data();
+        assert_eq!(data.position.len(), 4);
+        assert_eq!(data.normal.len(), 2);
+        assert_eq!(data.objects[0].groups[0].polys.len(), 2);
+    }
+}
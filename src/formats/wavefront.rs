@@ -1,5 +1,13 @@
 
-use crate::prelude::*;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::model::ModelEntity;
+use crate::prelude::{*, polygon};
 
 
 // Returns a vertex buffer that should be rendered as `TrianglesList`.
@@ -14,13 +22,20 @@ pub fn load_data(data: &obj::ObjData) -> Vec<Vertex> {
         for polygon in object.groups.iter().flat_map(|g| g.polys.iter()) {
             match polygon {
                 obj::SimplePolygon(indices) => {
-                    for v in indices.iter() {
+                    let points: Vec<Point3d> = indices.iter()
+                        .map(|v| data.position[v.0].into())
+                        .collect();
+
+                    // faces without normals are lit flat
+                    let flat = polygon::normal(&points).to_array();
+
+                    for v in polygon::triangulate(&points).into_iter().flatten().map(|i| &indices[i]) {
                         let position = data.position[v.0];
                         let texture = v.1.map(|index| data.texture[index]);
                         let normal = v.2.map(|index| data.normal[index]);
 
                         let texture = texture.unwrap_or([0.0, 0.0]);
-                        let normal = normal.unwrap_or([0.0, 0.0, 0.0]);
+                        let normal = normal.unwrap_or(flat);
 
                         let vertex = Vertex {
                             position,
@@ -38,3 +53,368 @@ pub fn load_data(data: &obj::ObjData) -> Vec<Vertex> {
 
     vertex_data
 }
+
+/// Split obj data into one named part per object, or per group for objects with several groups.
+///
+/// Each part only keeps the positions, texture coordinates and normals it uses.
+pub fn split(data: &obj::ObjData) -> Vec<(String, obj::ObjData)> {
+    let mut parts = Vec::new();
+
+    for object in data.objects.iter() {
+        // groups split by `usemtl` share a name, and belong to the same part
+        let mut names: Vec<&str> = Vec::new();
+        for group in object.groups.iter() {
+            if !names.contains(&group.name.as_str()) {
+                names.push(&group.name);
+            }
+        }
+
+        let single = names.len() == 1;
+        for name in names {
+            let groups: Vec<&obj::Group> = object.groups.iter()
+                .filter(|g| g.name == name && !g.polys.is_empty())
+                .collect();
+
+            if groups.is_empty() {
+                continue;
+            }
+
+            let part_name = match (object.name.as_str(), name) {
+                ("default", group) => group.to_string(),
+                (object, "default") => object.to_string(),
+                (object, _) if single => object.to_string(),
+                (object, group) => format!("{}.{}", object, group),
+            };
+
+            parts.push((part_name, extract(data, &object.name, &groups)));
+        }
+    }
+
+    parts
+}
+
+/// copy groups into new obj data, remapping indices to only the data they use
+fn extract(data: &obj::ObjData, object: &str, groups: &[&obj::Group]) -> obj::ObjData {
+    fn remap<T: Copy>(index: usize, map: &mut HashMap<usize, usize>, from: &[T], to: &mut Vec<T>) -> usize {
+        *map.entry(index).or_insert_with(|| {
+            to.push(from[index]);
+            to.len() - 1
+        })
+    }
+
+    let mut part = obj::ObjData { material_libs: data.material_libs.clone(), ..Default::default() };
+    let (mut positions, mut textures, mut normals) = (HashMap::new(), HashMap::new(), HashMap::new());
+
+    let mut result = obj::Object::new(object.to_string());
+    for group in groups {
+        let polys = group.polys.iter()
+            .map(|obj::SimplePolygon(indices)| obj::SimplePolygon(
+                indices.iter()
+                    .map(|obj::IndexTuple(v, t, n)| obj::IndexTuple(
+                        remap(*v, &mut positions, &data.position, &mut part.position),
+                        t.map(|t| remap(t, &mut textures, &data.texture, &mut part.texture)),
+                        n.map(|n| remap(n, &mut normals, &data.normal, &mut part.normal)),
+                    ))
+                    .collect()
+            ))
+            .collect();
+
+        result.groups.push(obj::Group {
+            name: group.name.clone(),
+            index: group.index,
+            material: group.material.clone(),
+            polys
+        });
+    }
+    part.objects.push(result);
+
+    part
+}
+
+/// Serialize obj data as Wavefront text.
+///
+/// `obj::ObjData::write_to_buf` writes `v/n` for faces without texture coordinates,
+/// which reads back as texture indices, so face records are written here instead.
+pub fn write_data(data: &obj::ObjData, out: &mut impl Write) -> io::Result<()> {
+    for lib in data.material_libs.iter() {
+        writeln!(out, "mtllib {}", lib.filename)?;
+    }
+
+    for p in data.position.iter() {
+        writeln!(out, "v {} {} {}", p[0], p[1], p[2])?;
+    }
+    for t in data.texture.iter() {
+        writeln!(out, "vt {} {}", t[0], t[1])?;
+    }
+    for n in data.normal.iter() {
+        writeln!(out, "vn {} {} {}", n[0], n[1], n[2])?;
+    }
+
+    for object in data.objects.iter() {
+        writeln!(out, "o {}", object.name)?;
+
+        for group in object.groups.iter() {
+            // groups with a non-zero index continue the previous group with a new material
+            if group.index == 0 {
+                writeln!(out, "g {}", group.name)?;
+            }
+
+            match &group.material {
+                Some(obj::ObjMaterial::Ref(name)) => writeln!(out, "usemtl {}", name)?,
+                Some(obj::ObjMaterial::Mtl(mtl)) => writeln!(out, "usemtl {}", mtl.name)?,
+                None => ()
+            }
+
+            for obj::SimplePolygon(indices) in group.polys.iter() {
+                write!(out, "f")?;
+                for obj::IndexTuple(v, t, n) in indices.iter() {
+                    match (t, n) {
+                        (None, None) => write!(out, " {}", v + 1)?,
+                        (Some(t), None) => write!(out, " {}/{}", v + 1, t + 1)?,
+                        (None, Some(n)) => write!(out, " {}//{}", v + 1, n + 1)?,
+                        (Some(t), Some(n)) => write!(out, " {}/{}/{}", v + 1, t + 1, n + 1)?,
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Save entities as a Wavefront file.
+///
+/// Every visible entity is written as its own `o` object. Solids are written as triangles
+/// (`f v//vn`) and sketches as polylines (`l`). Entities that `hidden` marks, and those with
+/// nothing to draw, such as the solids a combine is made of, are left out.
+pub fn save(entities: &[ModelEntity], hidden: &[bool], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write(entities, hidden, &mut out)?;
+
+    out.flush()
+}
+
+pub fn write(entities: &[ModelEntity], hidden: &[bool], out: &mut impl Write) -> io::Result<()> {
+    let mut positions = Indexer::default();
+    let mut normals = Indexer::default();
+
+    // face records refer to the shared arrays, so they are buffered until every entity is indexed
+    let mut objects = Vec::new();
+    for ((entity, (vertices, _)), hidden) in entities.iter().zip(crate::model::bodies(entities)).zip(hidden) {
+        // cuts and holes are part of the solids they were taken from
+        if entity.is_subtractive() || *hidden {
+            continue;
+        }
+
+        let mut records = Vec::new();
+
+        match entity {
+            ModelEntity::Sketch(sketch) => {
+                // construction geometry is only a drawing aid
+                for element in sketch.elements().iter().filter(|e| !e.construction) {
+                    let indices: Vec<String> = sketch.tessellate(element).iter()
+                        .map(|p| (positions.index(sketch.plane().point(p).to_array()) + 1).to_string())
+                        .collect();
+
+                    records.push(format!("l {}", indices.join(" ")));
+                }
+            },
+            _ => {
+                for tri in vertices.chunks_exact(3) {
+                    let corners: Vec<String> = tri.iter()
+                        .map(|v| format!("{}//{}", positions.index(v.position) + 1, normals.index(v.normal) + 1))
+                        .collect();
+
+                    records.push(format!("f {}", corners.join(" ")));
+                }
+            }
+        }
+
+        if !records.is_empty() {
+            objects.push((entity.to_string(), records));
+        }
+    }
+
+    writeln!(out, "# exported by {}", crate::config::TITLE)?;
+    for p in positions.values {
+        writeln!(out, "v {} {} {}", p[0], p[1], p[2])?;
+    }
+    for n in normals.values {
+        writeln!(out, "vn {} {} {}", n[0], n[1], n[2])?;
+    }
+
+    for (name, records) in objects {
+        writeln!(out, "o {}", name)?;
+        for record in records {
+            writeln!(out, "{}", record)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// deduplicates vectors by their exact bit pattern
+#[derive(Default)]
+struct Indexer {
+    indices: HashMap<[u32; 3], usize>,
+    values: Vec<[f32; 3]>,
+}
+
+impl Indexer {
+    /// 0-based index of the value, adding it if it has not been seen
+    fn index(&mut self, value: [f32; 3]) -> usize {
+        // adding zero folds -0.0 into 0.0 so both share an index
+        *self.indices.entry(value.map(|v| (v + 0.0).to_bits())).or_insert_with(|| {
+            self.values.push(value);
+            self.values.len() - 1
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Block, Combine, ImportedModel, Operation, Plane, Sketch, regenerate};
+
+    fn export_visible(entities: &[ModelEntity], hidden: &[bool]) -> String {
+        let mut buf = Vec::new();
+        write(entities, hidden, &mut buf).unwrap();
+
+        String::from_utf8(buf).unwrap()
+    }
+
+    fn export(entities: &[ModelEntity]) -> String {
+        export_visible(entities, &vec![false; entities.len()])
+    }
+
+    fn block() -> ModelEntity {
+        ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 2.0, 3.0)))
+    }
+
+    #[test]
+    fn block_faces_use_vertex_normals() {
+        let text = export(&[block()]);
+        let data = obj::ObjData::load_buf(text.as_bytes()).unwrap();
+
+        // corners are shared between faces
+        assert_eq!(data.position.len(), 8);
+        assert_eq!(data.objects.len(), 1);
+        assert_eq!(data.objects[0].name, "Block");
+
+        let polys = &data.objects[0].groups[0].polys;
+        assert_eq!(polys.len() * 3, block().vertices().len());
+        for obj::SimplePolygon(indices) in polys {
+            assert!(indices.iter().all(|i| i.1.is_none() && i.2.is_some()));
+        }
+    }
+
+    #[test]
+    fn sketch_is_polyline() {
+        let sketch = Sketch::polyline(
+            Plane::default(),
+            vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 0.0), Point2d::new(1.0, 1.0)]
+        );
+        let text = export(&[block(), ModelEntity::Sketch(sketch)]);
+
+        // sketch corners that coincide with the block reuse its positions
+        assert!(text.contains("o Sketch\nl 1 "));
+        assert_eq!(text.lines().filter(|l| l.starts_with("v ")).count(), 9);
+    }
+
+    #[test]
+    fn entities_are_separate_objects() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("models/cube.obj");
+        let cube = ModelEntity::ImportedModel(ImportedModel::new("cube".into(), obj::Obj::load(path).unwrap()));
+
+        let text = export(&[cube, block()]);
+        let data = obj::ObjData::load_buf(text.as_bytes()).unwrap();
+
+        let names: Vec<&str> = data.objects.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["cube", "Block"]);
+    }
+
+    #[test]
+    fn hidden_and_empty_entities_are_left_out() {
+        let mut entities = vec![
+            block(),
+            ModelEntity::Block(Block::two_points(Point3d::new(0.5, 0.0, 0.0), Point3d::new(2.0, 2.0, 3.0))),
+            ModelEntity::Combine(Combine::new(0, 1, Operation::Union)),
+            ModelEntity::Block(Block::two_points(Point3d::new(5.0, 0.0, 0.0), Point3d::new(6.0, 1.0, 1.0))),
+        ];
+        regenerate(&mut entities);
+
+        // the combined blocks have no body of their own, and the last block is hidden
+        let text = export_visible(&entities, &[false, false, false, true]);
+        let names: Vec<&str> = text.lines().filter_map(|l| l.strip_prefix("o ")).collect();
+        assert_eq!(names, ["Union"]);
+    }
+
+    fn fixture(name: &str) -> obj::ObjData {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("models").join(name);
+
+        obj::Obj::load(path).unwrap().data
+    }
+
+    fn split_names(name: &str) -> Vec<String> {
+        split(&fixture(name)).into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn split_by_object() {
+        // every face in the fixture follows `o Cube2`, so `Cube1` has no geometry of its own
+        assert_eq!(split_names("twobodies.obj"), ["Cube2"]);
+    }
+
+    #[test]
+    fn split_by_group() {
+        assert_eq!(split_names("twocubes_blender.obj"), ["CubeA_Mesh", "CubeB_Mesh"]);
+        assert_eq!(split_names("cube.obj"), ["cube"]);
+    }
+
+    #[test]
+    fn split_parts_are_compact() {
+        let data = fixture("twobodies.obj");
+        let parts = split(&data);
+
+        let faces = |d: &obj::ObjData| d.objects.iter()
+            .flat_map(|o| o.groups.iter())
+            .map(|g| g.polys.len())
+            .sum::<usize>();
+
+        assert_eq!(parts.iter().map(|(_, d)| faces(d)).sum::<usize>(), faces(&data));
+        for (_, part) in parts.iter() {
+            assert_eq!(part.position.len(), 8);
+            for obj::SimplePolygon(indices) in part.objects[0].groups.iter().flat_map(|g| g.polys.iter()) {
+                assert!(indices.iter().all(|i| i.0 < part.position.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn polygons_are_triangulated() {
+        // a quad and a concave hexagon, neither with normals
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    v 0 0 1\nv 2 0 1\nv 2 1 1\nv 1 1 1\nv 1 2 1\nv 0 2 1\n\
+                    f 1 2 3 4\nf 5 6 7 8 9 10\n";
+        let vertices = load_data(&obj::ObjData::load_buf(text.as_bytes()).unwrap());
+
+        assert_eq!(vertices.len(), (2 + 4) * 3);
+        for tri in vertices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|v| Point3d::from(v.position));
+            let winding = (b - a).cross(&(c - a));
+
+            assert!(winding.z > 0.0);
+            assert!(tri.iter().all(|v| v.normal == [0.0, 0.0, 1.0]));
+        }
+    }
+
+    #[test]
+    fn file_normals_are_kept() {
+        let text = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0.6 0.8\nf 1//1 2//1 3//1 4//1\n";
+        let vertices = load_data(&obj::ObjData::load_buf(text.as_bytes()).unwrap());
+
+        assert_eq!(vertices.len(), 6);
+        assert!(vertices.iter().all(|v| v.normal == [0.0, 0.6, 0.8]));
+    }
+}
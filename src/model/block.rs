@@ -1,25 +1,118 @@
 
-use super::Point3d;
+use super::{Plane, Point3d};
+use super::brep::{Brep, Curve, Surface};
 use crate::prelude::*;
 
-#[derive(Debug, Default)]
+/// point of the block that is placed at its origin
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    #[default]
+    Corner,
+    /// center of the face lying on the base plane
+    FaceCenter,
+    Centroid,
+}
+
+impl std::fmt::Display for Anchor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Anchor::Corner => write!(f, "Corner"),
+            Anchor::FaceCenter => write!(f, "Face center"),
+            Anchor::Centroid => write!(f, "Centroid"),
+        }
+    }
+}
+
+/// base plane of the block, which holds its length and width
+///
+/// Height is along the plane normal.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    #[default]
+    XY,
+    YZ,
+    ZX,
+}
+
+impl Orientation {
+    /// map (length, width, height) onto the global axes
+    pub fn to_global(&self, local: Point3d) -> Point3d {
+        match self {
+            Orientation::XY => Point3d::new(local.x, local.y, local.z),
+            Orientation::YZ => Point3d::new(local.z, local.x, local.y),
+            Orientation::ZX => Point3d::new(local.y, local.z, local.x),
+        }
+    }
+}
+
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Orientation::XY => write!(f, "XY"),
+            Orientation::YZ => write!(f, "YZ"),
+            Orientation::ZX => write!(f, "ZX"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Block {
     origin: Point3d,
-    dim: Point3d
+    /// length, width and height, local to the orientation
+    dim: Point3d,
+    anchor: Anchor,
+    orientation: Orientation,
 }
 
 impl Block {
+    pub fn new(origin: Point3d, dim: Point3d, anchor: Anchor, orientation: Orientation) -> Self {
+        Self { origin, dim, anchor, orientation }
+    }
+
+    /// block spanning two opposite corners
     pub fn two_points(p1: Point3d, p2: Point3d) -> Self {
-        Self { origin: p1, dim: p2 }
+        Self { origin: p1, dim: p2 - p1, ..Default::default() }
     }
 
     pub fn origin_and_max(dim: Point3d) -> Self {
         Self { dim, ..Default::default() }
     }
 
+    pub fn origin(&self) -> Point3d {
+        self.origin
+    }
+
+    pub fn dim(&self) -> Point3d {
+        self.dim
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// opposite corners, ordered so that negative dimensions still give `min < max`
+    pub fn bounds(&self) -> (Point3d, Point3d) {
+        let offset = match self.anchor {
+            Anchor::Corner => Point3d::default(),
+            Anchor::FaceCenter => Point3d::new(self.dim.x / 2.0, self.dim.y / 2.0, 0.0),
+            Anchor::Centroid => self.dim / 2.0,
+        };
+
+        let a = self.origin - self.orientation.to_global(offset);
+        let b = a + self.orientation.to_global(self.dim);
+
+        (
+            Point3d::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            Point3d::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        )
+    }
+
     pub fn points(&self) -> Vec<Point3d> {
-        let min = self.origin.clone();
-        let max = min + self.dim;
+        let (min, max) = self.bounds();
 
         vec![
             // bottom
@@ -35,33 +128,146 @@ impl Block {
             max
         ]
     }
+
+    /// outward normal and corners of each face, counter-clockwise when viewed from outside
+    pub fn faces(&self) -> [(Point3d, [Point3d; 4]); 6] {
+        let (a, b) = self.bounds();
+        let p = Point3d::new;
+
+        [
+            (p(-1.0, 0.0, 0.0), [p(a.x, a.y, a.z), p(a.x, a.y, b.z), p(a.x, b.y, b.z), p(a.x, b.y, a.z)]),
+            (p( 1.0, 0.0, 0.0), [p(b.x, a.y, a.z), p(b.x, b.y, a.z), p(b.x, b.y, b.z), p(b.x, a.y, b.z)]),
+            (p(0.0, -1.0, 0.0), [p(a.x, a.y, a.z), p(b.x, a.y, a.z), p(b.x, a.y, b.z), p(a.x, a.y, b.z)]),
+            (p(0.0,  1.0, 0.0), [p(a.x, b.y, a.z), p(a.x, b.y, b.z), p(b.x, b.y, b.z), p(b.x, b.y, a.z)]),
+            (p(0.0, 0.0, -1.0), [p(a.x, a.y, a.z), p(a.x, b.y, a.z), p(b.x, b.y, a.z), p(b.x, a.y, a.z)]),
+            (p(0.0, 0.0,  1.0), [p(a.x, a.y, b.z), p(b.x, a.y, b.z), p(b.x, b.y, b.z), p(a.x, b.y, b.z)]),
+        ]
+    }
+
+    /// single shell of the six faces, in the order of [`Block::faces`]
+    pub fn brep(&self) -> Brep {
+        let mut brep = Brep::new();
+        for (normal, corners) in self.faces() {
+            let outer = (0..4).map(|i| brep.add_edge(vec![corners[i], corners[(i + 1) % 4]], Curve::Line)).collect();
+            brep.add_face(Surface::Plane(Plane::new(corners[0], normal)), outer, Vec::new());
+        }
+
+        brep
+    }
 }
 
 impl super::ModelEntityObject for Block {
     fn vertices(&self) -> Vec<Vertex> {
-        use itertools::Itertools;
-
         log::debug!("calculating buffer for block");
 
-        let mut data = Vec::new();
-        let points = self.points();
-        let permutations = (0..points.len()).permutations(3);
+        self.brep().tessellate()
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.brep().outline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelEntityObject;
 
-        for point_set in permutations {
-            let normal: Point3d = point_set
-                .clone().into_iter()
-                .map(|p| points[p])
-                .reduce(|acc, e| acc + e)
-                .unwrap() / 3f32;
+    fn block() -> Block {
+        Block::new(Point3d::new(1.0, -2.0, 0.5), Point3d::new(3.0, 4.0, 5.0), Anchor::Corner, Orientation::XY)
+    }
+
+    fn triangles(block: &Block) -> Vec<([Point3d; 3], Point3d)> {
+        block.vertices()
+            .chunks_exact(3)
+            .map(|t| ([t[0], t[1], t[2]].map(|v| v.position.into()), t[0].normal.into()))
+            .collect()
+    }
 
-            let normal = normal.to_array();
-            log::debug!("normal: {:?}", normal);
+    fn center(block: &Block) -> Point3d {
+        let (min, max) = block.bounds();
 
-            for p in point_set {
-                data.push(Vertex { position: points[p].to_array(), normal, ..Default::default() });
-            }
+        (min + max) / 2.0
+    }
+
+    #[test]
+    fn twelve_triangles() {
+        assert_eq!(block().vertices().len(), 36);
+    }
+
+    #[test]
+    fn normals_are_outward_units() {
+        let block = block();
+
+        for ([a, b, c], normal) in triangles(&block) {
+            assert_eq!(normal.length(), 1.0);
+
+            // the face centroid lies away from the block center along the normal
+            let centroid = (a + b + c) / 3.0;
+            assert!((centroid - center(&block)).dot(&normal) > 0.0);
         }
+    }
+
+    #[test]
+    fn winding_is_counter_clockwise_from_outside() {
+        for ([a, b, c], normal) in triangles(&block()) {
+            let winding = (b - a).cross(&(c - a)).normalize();
+
+            assert!((winding - normal).length() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn negative_dimensions_stay_outward() {
+        let block = Block::origin_and_max(Point3d::new(-1.0, 2.0, -3.0));
+
+        for ([a, b, c], normal) in triangles(&block) {
+            let winding = (b - a).cross(&(c - a)).normalize();
+
+            assert!((winding - normal).length() < 1e-6);
+            assert!(((a + b + c) / 3.0 - center(&block)).dot(&normal) > 0.0);
+        }
+    }
+
+    #[test]
+    fn twelve_edges() {
+        let edges = block().edges();
+        assert_eq!(edges.len(), 24);
+
+        // every edge is parallel to an axis, with one of the block dimensions as length
+        let dim = block().dim();
+        for pair in edges.chunks_exact(2) {
+            let length = (Point3d::from(pair[1].position) - Point3d::from(pair[0].position)).length();
+            assert!([dim.x, dim.y, dim.z].contains(&length));
+        }
+    }
+
+    #[test]
+    fn two_points_span_corners() {
+        let block = Block::two_points(Point3d::new(1.0, 1.0, 1.0), Point3d::new(-1.0, 2.0, 4.0));
+
+        assert_eq!(block.bounds(), (Point3d::new(-1.0, 1.0, 1.0), Point3d::new(1.0, 2.0, 4.0)));
+    }
+
+    #[test]
+    fn anchors() {
+        let origin = Point3d::new(10.0, 0.0, 0.0);
+        let dim = Point3d::new(2.0, 4.0, 6.0);
+        let bounds = |anchor| Block::new(origin, dim, anchor, Orientation::XY).bounds();
+
+        assert_eq!(bounds(Anchor::Corner), (Point3d::new(10.0, 0.0, 0.0), Point3d::new(12.0, 4.0, 6.0)));
+        assert_eq!(bounds(Anchor::FaceCenter), (Point3d::new(9.0, -2.0, 0.0), Point3d::new(11.0, 2.0, 6.0)));
+        assert_eq!(bounds(Anchor::Centroid), (Point3d::new(9.0, -2.0, -3.0), Point3d::new(11.0, 2.0, 3.0)));
+    }
+
+    #[test]
+    fn orientations() {
+        let dim = Point3d::new(2.0, 4.0, 6.0);
+        let bounds = |orientation| Block::new(Point3d::default(), dim, Anchor::FaceCenter, orientation).bounds();
 
-        data
+        // height always runs along the plane normal
+        assert_eq!(bounds(Orientation::XY), (Point3d::new(-1.0, -2.0, 0.0), Point3d::new(1.0, 2.0, 6.0)));
+        assert_eq!(bounds(Orientation::YZ), (Point3d::new(0.0, -1.0, -2.0), Point3d::new(6.0, 1.0, 2.0)));
+        assert_eq!(bounds(Orientation::ZX), (Point3d::new(-2.0, 0.0, -1.0), Point3d::new(2.0, 6.0, 1.0)));
     }
 }
@@ -0,0 +1,483 @@
+
+//! boundary representation of solids
+//!
+//! A solid is made of shells of faces. Each face lies on a surface and is bounded by
+//! loops of coedges, which run along the edges in the direction of their loop. Outer
+//! loops go counter-clockwise seen from outside the solid and inner loops clockwise,
+//! so that a closed shell uses every edge once in each direction.
+//!
+//! Triangles for drawing are a separate step, see [`Brep::tessellate`].
+
+use crate::prelude::{*, polygon};
+use super::Plane;
+
+/// how close points have to be to count as the same vertex
+const TOLERANCE: f32 = 1e-5;
+
+/// geometry of an edge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    Line,
+    /// tessellated sketch curve
+    Polyline,
+    /// arc of a circle about `axis`, counter-clockwise from start to end
+    Circle { center: Point3d, axis: Point3d, radius: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub start: usize,
+    pub end: usize,
+    pub curve: Curve,
+    /// points from start to end, including both
+    pub points: Vec<Point3d>,
+}
+
+impl Edge {
+    /// whether the edge has collapsed to a point, like an arc traced by a point on the axis of a revolve
+    pub fn is_degenerate(&self) -> bool {
+        self.points.iter().all(|p| (*p - self.points[0]).length() <= TOLERANCE)
+    }
+}
+
+/// use of an edge by a loop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coedge {
+    pub edge: usize,
+    /// whether the loop runs from the end of the edge to its start
+    pub reversed: bool,
+}
+
+impl Coedge {
+    pub fn reversed(self) -> Self {
+        Self { reversed: !self.reversed, ..self }
+    }
+}
+
+/// geometry of a face
+#[derive(Debug, Clone, PartialEq)]
+pub enum Surface {
+    /// plane with the outward normal of the face
+    Plane(Plane),
+    /// swept by the first coedge of the outer loop along `direction`
+    Extrusion { direction: Point3d },
+    /// swept by the first coedge of the outer loop by `angle` radians about an axis
+    Revolution { origin: Point3d, axis: Point3d, angle: f32 },
+}
+
+/// Faces on swept surfaces have an outer loop of four coedges: the generating
+/// curve, the path of its end, the swept curve back, and the path of its start back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Face {
+    pub surface: Surface,
+    pub outer: Vec<Coedge>,
+    pub inner: Vec<Vec<Coedge>>,
+}
+
+impl Face {
+    pub fn loops(&self) -> impl Iterator<Item = &Vec<Coedge>> {
+        std::iter::once(&self.outer).chain(self.inner.iter())
+    }
+}
+
+/// connected faces bounding one piece of the solid
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Shell {
+    pub faces: Vec<usize>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Brep {
+    vertices: Vec<Point3d>,
+    edges: Vec<Edge>,
+    faces: Vec<Face>,
+    shells: Vec<Shell>,
+}
+
+impl Brep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vertices(&self) -> &[Point3d] {
+        &self.vertices
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    pub fn shells(&self) -> &[Shell] {
+        &self.shells
+    }
+
+    /// index of the vertex at `point`, adding it if there is none
+    pub fn add_vertex(&mut self, point: Point3d) -> usize {
+        match self.vertices.iter().position(|v| (*v - point).length() <= TOLERANCE) {
+            Some(i) => i,
+            None => {
+                self.vertices.push(point);
+                self.vertices.len() - 1
+            }
+        }
+    }
+
+    /// coedge along the polyline, reusing the edge if another face already has it
+    pub fn add_edge(&mut self, points: Vec<Point3d>, curve: Curve) -> Coedge {
+        let (start, end) = (self.add_vertex(points[0]), self.add_vertex(points[points.len() - 1]));
+        let same = |a: &[Point3d], b: &mut dyn Iterator<Item = &Point3d>| a.iter().zip(b).all(|(p, q)| (*p - *q).length() <= TOLERANCE);
+
+        for (i, edge) in self.edges.iter().enumerate().filter(|(_, e)| e.points.len() == points.len()) {
+            if edge.start == start && edge.end == end && same(&edge.points, &mut points.iter()) {
+                return Coedge { edge: i, reversed: false };
+            }
+            if edge.start == end && edge.end == start && same(&edge.points, &mut points.iter().rev()) {
+                return Coedge { edge: i, reversed: true };
+            }
+        }
+
+        self.edges.push(Edge { start, end, curve, points });
+        Coedge { edge: self.edges.len() - 1, reversed: false }
+    }
+
+    /// start a shell, which the faces added after it belong to
+    pub fn add_shell(&mut self) {
+        self.shells.push(Shell::default());
+    }
+
+    pub fn add_face(&mut self, surface: Surface, outer: Vec<Coedge>, inner: Vec<Vec<Coedge>>) -> usize {
+        if self.shells.is_empty() {
+            self.add_shell();
+        }
+
+        self.faces.push(Face { surface, outer, inner });
+        let index = self.faces.len() - 1;
+        if let Some(shell) = self.shells.last_mut() {
+            shell.faces.push(index);
+        }
+
+        index
+    }
+
+    /// move `faces` of the current shell to its front, in the given order
+    ///
+    /// Face indices are what features on a face refer to, so builders use this to keep
+    /// the caps ahead of the sides, as they were before solids had faces of their own.
+    pub(super) fn lead_with(&mut self, faces: &[usize]) {
+        let Some(shell) = self.shells.last_mut() else {
+            return;
+        };
+
+        // faces are only added to the last shell, so its faces are the last ones
+        let first = shell.faces.iter().copied().min().unwrap_or(self.faces.len());
+        let order: Vec<usize> = faces.iter().copied()
+            .chain(shell.faces.iter().copied().filter(|f| !faces.contains(f)))
+            .collect();
+
+        let moved: Vec<Face> = order.iter().map(|&i| self.faces[i].clone()).collect();
+        for (k, face) in moved.into_iter().enumerate() {
+            self.faces[first + k] = face;
+        }
+    }
+
+    /// points of a coedge in the direction of its loop
+    pub fn points(&self, coedge: Coedge) -> Vec<Point3d> {
+        let points = &self.edges[coedge.edge].points;
+
+        match coedge.reversed {
+            true => points.iter().rev().copied().collect(),
+            false => points.clone(),
+        }
+    }
+
+    /// closed polyline around a loop, without repeating its first point
+    pub fn loop_points(&self, coedges: &[Coedge]) -> Vec<Point3d> {
+        coedges.iter()
+            .flat_map(|c| {
+                let mut points = self.points(*c);
+                points.pop();
+                points
+            })
+            .collect()
+    }
+
+    /// faces with a loop running along `edge`
+    pub fn edge_faces(&self, edge: usize) -> Vec<usize> {
+        self.faces.iter()
+            .enumerate()
+            .filter(|(_, f)| f.loops().flatten().any(|c| c.edge == edge))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// faces sharing an edge with `face`
+    pub fn neighbors(&self, face: usize) -> Vec<usize> {
+        let mut neighbors: Vec<usize> = self.faces[face].loops()
+            .flatten()
+            .flat_map(|c| self.edge_faces(c.edge))
+            .filter(|&f| f != face)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+
+        neighbors
+    }
+
+    /// edges starting or ending at `vertex`
+    pub fn vertex_edges(&self, vertex: usize) -> Vec<usize> {
+        self.edges.iter()
+            .enumerate()
+            .filter(|(_, e)| e.start == vertex || e.end == vertex)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// whether every edge is used once in each direction, as on the boundary of a solid
+    pub fn is_closed(&self) -> bool {
+        let mut uses = vec![(0, 0); self.edges.len()];
+        for coedge in self.faces.iter().flat_map(|f| f.loops().flatten()) {
+            match coedge.reversed {
+                true => uses[coedge.edge].1 += 1,
+                false => uses[coedge.edge].0 += 1,
+            }
+        }
+
+        uses.iter().all(|&u| u == (1, 1))
+    }
+
+    /// triangle list of every face
+    ///
+    /// Normals are smooth across curved faces and sharp at the edges between faces.
+    pub fn tessellate(&self) -> Vec<Vertex> {
+        let mut data = Vec::new();
+        for face in self.faces.iter() {
+            match &face.surface {
+                Surface::Plane(plane) => {
+                    let loops: Vec<Vec<Point3d>> = face.loops().map(|l| self.loop_points(l)).collect();
+                    let project = |l: &Vec<Point3d>| -> Vec<[f32; 2]> { l.iter().map(|p| plane.local(p).to_array()).collect() };
+                    let holes: Vec<Vec<[f32; 2]>> = loops[1..].iter().map(project).collect();
+                    let (points, triangles) = polygon::triangulate_with_holes(&project(&loops[0]), &holes);
+
+                    // the joined points are copies of the projected ones, which map back to the exact corners
+                    let corners: Vec<([f32; 2], Point3d)> = loops.iter().flat_map(|l| project(l).into_iter().zip(l.iter().copied())).collect();
+                    let lift = |p: [f32; 2]| corners.iter()
+                        .find(|(q, _)| *q == p)
+                        .map_or_else(|| plane.point(&Point2d::new(p[0], p[1])), |(_, corner)| *corner);
+
+                    let normal = plane.normal().to_array();
+                    for t in triangles {
+                        data.extend(t.map(|i| Vertex { position: lift(points[i]).to_array(), normal, ..Default::default() }));
+                    }
+                },
+                surface => data.extend(self.sweep(face, surface)),
+            }
+        }
+
+        data
+    }
+
+    /// grid of points swept by the generator of a face, in rows along the path
+    fn sweep(&self, face: &Face, surface: &Surface) -> Vec<Vertex> {
+        let [generator, path, opposite, ..] = face.outer[..] else {
+            log::warn!("Swept face needs four coedges, found {}", face.outer.len());
+            return Vec::new();
+        };
+
+        let generator = self.points(generator);
+        let opposite: Vec<Point3d> = self.points(opposite).into_iter().rev().collect();
+        let steps = self.points(path).len() - 1;
+
+        let grid: Vec<Vec<Point3d>> = (0..=steps)
+            .map(|k| {
+                let t = k as f32 / steps as f32;
+                generator.iter()
+                    .zip(opposite.iter())
+                    .map(|(&a, &b)| match surface {
+                        Surface::Revolution { origin, axis, angle } => *origin + turn(a - *origin, *axis, angle * t),
+                        _ => a + (b - a) * t,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // cell normals, added up at their corners
+        let mut normals = vec![vec![Point3d::default(); generator.len()]; steps + 1];
+        for k in 0..steps {
+            for i in 0..generator.len() - 1 {
+                let (a, b, c, d) = (grid[k][i], grid[k][i + 1], grid[k + 1][i + 1], grid[k + 1][i]);
+                let normal = polygon::normal(&[a, b, c, d]);
+                for (kk, ii) in [(k, i), (k, i + 1), (k + 1, i + 1), (k + 1, i)] {
+                    normals[kk][ii] = normals[kk][ii] + normal;
+                }
+            }
+        }
+
+        let mut data = Vec::new();
+        for k in 0..steps {
+            for i in 0..generator.len() - 1 {
+                let corners = [(k, i), (k, i + 1), (k + 1, i + 1), (k + 1, i)];
+                for triangle in [[0, 1, 2], [0, 2, 3]] {
+                    let [a, b, c] = triangle.map(|j| corners[j]);
+                    let area = (grid[b.0][b.1] - grid[a.0][a.1]).cross(&(grid[c.0][c.1] - grid[a.0][a.1]));
+                    if area.length() <= f32::EPSILON {
+                        continue;
+                    }
+
+                    data.extend([a, b, c].map(|(kk, ii)| Vertex {
+                        position: grid[kk][ii].to_array(),
+                        normal: normals[kk][ii].normalize().to_array(),
+                        ..Default::default()
+                    }));
+                }
+            }
+        }
+
+        data
+    }
+
+    /// line list of the edges where faces meet, leaving out seams and collapsed edges
+    pub fn outline(&self) -> Vec<Vertex> {
+        self.edges.iter()
+            .enumerate()
+            .filter(|(i, e)| !e.is_degenerate() && self.edge_faces(*i).len() != 1)
+            .flat_map(|(_, e)| e.points.windows(2).flat_map(|pair| [pair[0], pair[1]]).collect::<Vec<_>>())
+            .map(Vertex::from)
+            .collect()
+    }
+
+    /// planes of the flat faces, centered on their outer loop
+    pub fn planes(&self) -> Vec<Plane> {
+        self.faces.iter()
+            .filter_map(|face| match &face.surface {
+                Surface::Plane(plane) => {
+                    let points = self.loop_points(&face.outer);
+                    let center = points.iter().fold(Point3d::default(), |acc, &p| acc + p) / points.len() as f32;
+
+                    Some(Plane::new(center, plane.normal()))
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// add a face swept from `generator`, closing it with the paths of its ends
+    ///
+    /// `path` gives the path of each point of the generator, in the direction of the
+    /// coedge. The sweep has to be on the left of the generator, seen from outside.
+    pub(super) fn add_swept_face(&mut self, surface: Surface, generator: Coedge, path: impl Fn(usize) -> (Vec<Point3d>, Curve)) -> usize {
+        let count = self.points(generator).len();
+        let swept: Vec<Point3d> = (0..count).rev().filter_map(|i| path(i).0.last().copied()).collect();
+
+        let (end_path, end_curve) = path(count - 1);
+        let end_path = self.add_edge(end_path, end_curve);
+        let swept = self.add_edge(swept, self.edges[generator.edge].curve);
+        let (start_path, start_curve) = path(0);
+        let start_path = self.add_edge(start_path, start_curve).reversed();
+
+        self.add_face(surface, vec![generator, end_path, swept, start_path], Vec::new())
+    }
+
+    /// add a flat face bounded by loops of polylines, the outer loop first
+    pub(super) fn add_planar_face(&mut self, loops: Vec<Vec<Vec<Point3d>>>) -> usize {
+        let mut loops: Vec<Vec<Coedge>> = loops.into_iter()
+            .map(|runs| runs.into_iter()
+                .map(|run| {
+                    let curve = curve(&run);
+                    self.add_edge(run, curve)
+                })
+                .collect())
+            .collect();
+
+        let inner = loops.split_off(1.min(loops.len()));
+        let outer = loops.pop().unwrap_or_default();
+        let points = self.loop_points(&outer);
+        let plane = Plane::new(points.first().copied().unwrap_or_default(), polygon::normal(&points));
+
+        self.add_face(Surface::Plane(plane), outer, inner)
+    }
+}
+
+/// closed prism of a single loop swept by `offset`, with a side face for each run of the loop
+///
+/// The loop runs counter-clockwise seen from the end the sweep points to.
+pub(super) fn prism(runs: &[Vec<Point3d>], offset: Point3d) -> Brep {
+    let mut brep = Brep::new();
+    for run in runs {
+        let surface = match run.len() {
+            2 => Surface::Plane(Plane::new(run[0], polygon::normal(&[run[0], run[1], run[1] + offset, run[0] + offset]))),
+            _ => Surface::Extrusion { direction: offset.normalize() },
+        };
+
+        let generator = brep.add_edge(run.clone(), curve(run));
+        brep.add_swept_face(surface, generator, |i| (vec![run[i], run[i] + offset], Curve::Line));
+    }
+
+    let bottom = runs.iter().rev().map(|r| r.iter().rev().copied().collect()).collect();
+    let top = runs.iter().map(|r| r.iter().map(|&p| p + offset).collect()).collect();
+    brep.add_planar_face(vec![bottom]);
+    brep.add_planar_face(vec![top]);
+
+    brep
+}
+
+/// `v` turned by `angle` about the unit `axis`
+pub fn turn(v: Point3d, axis: Point3d, angle: f32) -> Point3d {
+    let (sin, cos) = angle.sin_cos();
+
+    v * cos + axis.cross(&v) * sin + axis * (axis.dot(&v) * (1.0 - cos))
+}
+
+/// curve of an edge through `points`
+pub fn curve(points: &[Point3d]) -> Curve {
+    match points.len() {
+        2 => Curve::Line,
+        _ => Curve::Polyline,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// unit square on the XY plane, swept up by one
+    fn prism() -> Brep {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(x, y)| Point3d::new(x, y, 0.0));
+        let runs: Vec<Vec<Point3d>> = (0..4).map(|i| vec![corners[i], corners[(i + 1) % 4]]).collect();
+
+        super::prism(&runs, Point3d::new(0.0, 0.0, 1.0))
+    }
+
+    #[test]
+    fn shared_edges_make_a_closed_shell() {
+        let brep = prism();
+
+        assert_eq!((brep.vertices().len(), brep.edges().len(), brep.faces().len()), (8, 12, 6));
+        assert_eq!(brep.shells().len(), 1);
+        assert!(brep.is_closed());
+    }
+
+    #[test]
+    fn adjacency() {
+        let brep = prism();
+
+        // every side touches the other sides next to it and both caps
+        assert_eq!(brep.neighbors(0), [1, 3, 4, 5]);
+        assert!(brep.edges().iter().enumerate().all(|(i, _)| brep.edge_faces(i).len() == 2));
+        assert!(brep.vertices().iter().enumerate().all(|(i, _)| brep.vertex_edges(i).len() == 3));
+    }
+
+    #[test]
+    fn tessellation_is_outward() {
+        let brep = prism();
+        let vertices = brep.tessellate();
+
+        assert_eq!(vertices.len(), 36);
+        assert!((volume(&vertices) - 1.0).abs() < 1e-5);
+        assert_eq!(brep.outline().len(), 24);
+        assert_eq!(brep.planes().len(), 6);
+    }
+}
@@ -0,0 +1,171 @@
+
+use crate::prelude::{*, csg::Solid};
+use super::ModelEntity;
+
+/// boolean operation between two solids
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Operation {
+    #[default]
+    Union,
+    /// the tool taken away from the target
+    Subtract,
+    Intersect,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Union => write!(f, "Union"),
+            Operation::Subtract => write!(f, "Subtract"),
+            Operation::Intersect => write!(f, "Intersect"),
+        }
+    }
+}
+
+/// mesh made by combining two earlier solids, which it takes the place of
+///
+/// The solids are referred to by history index, and the mesh is rebuilt from their
+/// bodies when the model regenerates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combine {
+    target: usize,
+    tool: usize,
+    operation: Operation,
+
+    /// triangles and outline, as of the last regeneration
+    mesh: Vec<Vertex>,
+    outline: Vec<Vertex>,
+}
+
+impl Combine {
+    pub fn new(target: usize, tool: usize, operation: Operation) -> Self {
+        Self { target, tool, operation, mesh: Vec::new(), outline: Vec::new() }
+    }
+
+    /// history index of the solid that is kept, or subtracted from
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    /// history index of the solid that is added, subtracted or intersected
+    pub fn tool(&self) -> usize {
+        self.tool
+    }
+
+    pub fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.target, self.tool]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.target = map(self.target);
+        self.tool = map(self.tool);
+    }
+
+    /// combine the bodies of both solids, with the cuts before this feature taken away
+    ///
+    /// `bodies` are those of the `earlier` entities, already cut, as the regeneration has them.
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity], bodies: &[(Vec<Vertex>, Vec<Vertex>)]) {
+        let solid = |i: usize| earlier.get(i).filter(|e| e.is_solid());
+        if solid(self.target).is_none() || solid(self.tool).is_none() || self.target == self.tool {
+            log::warn!("Combine refers to entities {} and {}, which are not two earlier solids", self.target + 1, self.tool + 1);
+            self.mesh.clear();
+            self.outline.clear();
+            return;
+        }
+
+        let ((target, target_edges), (tool, tool_edges)) = (&bodies[self.target], &bodies[self.tool]);
+        let (target, tool) = (Solid::from_vertices(target), Solid::from_vertices(tool));
+
+        // each outline is kept where it stays on the surface of the result
+        let (solid, mut outline, tool_outline) = match self.operation {
+            Operation::Union => (target.union(&tool), tool.clip_lines(target_edges, false), target.clip_lines(tool_edges, false)),
+            Operation::Subtract => (target.subtract(&tool), tool.clip_lines(target_edges, false), target.clip_lines(tool_edges, true)),
+            Operation::Intersect => (target.intersect(&tool), tool.clip_lines(target_edges, true), target.clip_lines(tool_edges, true)),
+        };
+        outline.extend(tool_outline);
+
+        self.mesh = solid.vertices();
+        self.outline = outline;
+    }
+}
+
+impl super::ModelEntityObject for Combine {
+    fn vertices(&self) -> Vec<Vertex> {
+        self.mesh.clone()
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.outline.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Block, bodies, regenerate};
+    use crate::prelude::fixture::{drill_block, drilled_hole, drilled_volume};
+
+    /// two unit cubes overlapping by half, combined
+    fn combine(operation: Operation) -> Vec<ModelEntity> {
+        let mut entities = vec![
+            ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0))),
+            ModelEntity::Block(Block::two_points(Point3d::new(0.5, 0.0, 0.0), Point3d::new(1.5, 1.0, 1.0))),
+            ModelEntity::Combine(Combine::new(0, 1, operation)),
+        ];
+        regenerate(&mut entities);
+
+        entities
+    }
+
+    #[test]
+    fn operations() {
+        for (operation, expected) in [(Operation::Union, 1.5), (Operation::Subtract, 0.5), (Operation::Intersect, 0.5)] {
+            let entities = combine(operation);
+
+            assert!((volume(&entities[2].vertices()) - expected).abs() < 1e-4, "{}", operation);
+        }
+    }
+
+    #[test]
+    fn inputs_are_replaced() {
+        let bodies = bodies(&combine(Operation::Union));
+
+        assert!(bodies[0].0.is_empty() && bodies[1].0.is_empty());
+        assert!((volume(&bodies[2].0) - 1.5).abs() < 1e-4);
+        assert!(!bodies[2].1.is_empty());
+    }
+
+    #[test]
+    fn needs_two_solids() {
+        let mut entities = vec![
+            ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0))),
+            ModelEntity::Combine(Combine::new(0, 0, Operation::Union)),
+        ];
+        regenerate(&mut entities);
+
+        assert!(entities[1].vertices().is_empty());
+    }
+
+    #[test]
+    fn chained_combines_keep_earlier_cuts() {
+        let cube = |x: f32| ModelEntity::Block(Block::two_points(Point3d::new(x, 0.0, 0.0), Point3d::new(x + 1.0, 1.0, 1.0)));
+
+        let mut entities = vec![
+            drill_block(),
+            drilled_hole(0),
+            cube(20.0),
+            ModelEntity::Combine(Combine::new(0, 2, Operation::Union)),
+            cube(30.0),
+            ModelEntity::Combine(Combine::new(3, 4, Operation::Union)),
+        ];
+        regenerate(&mut entities);
+
+        assert!((volume(&entities[5].vertices()) - (502.0 - drilled_volume())).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,633 @@
+
+//! geometric constraints between sketch points and elements, and their solver
+//!
+//! Every point coordinate and circle radius is a variable, and every constraint
+//! is one or more equations that are zero when it holds. The solver takes
+//! minimum norm Gauss-Newton steps, so geometry that is free to move stays
+//! where it is as much as possible.
+
+use super::{Point2d, Shape, SketchElement};
+
+/// largest residual of a solved sketch
+const TOLERANCE: f64 = 1e-6;
+const MAX_ITERATIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint {
+    /// two points at the same position
+    Coincident(usize, usize),
+    /// line parallel to the sketch X axis
+    Horizontal(usize),
+    /// line parallel to the sketch Y axis
+    Vertical(usize),
+    Parallel(usize, usize),
+    Perpendicular(usize, usize),
+    /// line touching a circle or arc, or two circles or arcs touching
+    Tangent(usize, usize),
+    /// same length of two lines, or same radius of two circles or arcs
+    EqualLength(usize, usize),
+    /// circles or arcs sharing a center
+    Concentric(usize, usize),
+    /// point held at a position
+    Fixed { point: usize, at: Point2d },
+
+    /// distance between two points
+    Distance { a: usize, b: usize, value: f32 },
+    Radius { element: usize, value: f32 },
+    Diameter { element: usize, value: f32 },
+    /// counter-clockwise angle from the first line to the second, in degrees
+    Angle { a: usize, b: usize, value: f32 },
+}
+
+impl Constraint {
+    /// sketch points the constraint refers to
+    pub fn points(&self) -> Vec<usize> {
+        match self {
+            Constraint::Coincident(a, b) => vec![*a, *b],
+            Constraint::Fixed { point, .. } => vec![*point],
+            Constraint::Distance { a, b, .. } => vec![*a, *b],
+            _ => Vec::new(),
+        }
+    }
+
+    /// sketch elements the constraint refers to
+    pub fn elements(&self) -> Vec<usize> {
+        match self {
+            Constraint::Horizontal(e) | Constraint::Vertical(e) => vec![*e],
+            Constraint::Parallel(a, b)
+                | Constraint::Perpendicular(a, b)
+                | Constraint::Tangent(a, b)
+                | Constraint::EqualLength(a, b)
+                | Constraint::Concentric(a, b)
+                | Constraint::Angle { a, b, .. } => vec![*a, *b],
+            Constraint::Radius { element, .. } | Constraint::Diameter { element, .. } => vec![*element],
+            _ => Vec::new(),
+        }
+    }
+
+    /// driving value of a dimension, or `None` for geometric constraints
+    pub fn value(&self) -> Option<f32> {
+        match self {
+            Constraint::Distance { value, .. }
+                | Constraint::Radius { value, .. }
+                | Constraint::Diameter { value, .. }
+                | Constraint::Angle { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// change the driving value of a dimension; geometric constraints are left as they are
+    pub fn set_value(&mut self, to: f32) {
+        match self {
+            Constraint::Distance { value, .. }
+                | Constraint::Radius { value, .. }
+                | Constraint::Diameter { value, .. }
+                | Constraint::Angle { value, .. } => *value = to,
+            _ => (),
+        }
+    }
+
+    /// values a dimension can be driven to, which are positive for lengths
+    pub fn range(&self) -> std::ops::RangeInclusive<f32> {
+        match self {
+            Constraint::Angle { .. } => f32::MIN..=f32::MAX,
+            _ => f32::EPSILON..=f32::MAX,
+        }
+    }
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Coincident(..) => write!(f, "Coincident"),
+            Constraint::Horizontal(..) => write!(f, "Horizontal"),
+            Constraint::Vertical(..) => write!(f, "Vertical"),
+            Constraint::Parallel(..) => write!(f, "Parallel"),
+            Constraint::Perpendicular(..) => write!(f, "Perpendicular"),
+            Constraint::Tangent(..) => write!(f, "Tangent"),
+            Constraint::EqualLength(..) => write!(f, "Equal"),
+            Constraint::Concentric(..) => write!(f, "Concentric"),
+            Constraint::Fixed { .. } => write!(f, "Fixed"),
+            Constraint::Distance { .. } => write!(f, "Distance"),
+            Constraint::Radius { .. } => write!(f, "Radius"),
+            Constraint::Diameter { .. } => write!(f, "Diameter"),
+            Constraint::Angle { .. } => write!(f, "Angle"),
+        }
+    }
+}
+
+/// how far the constraints determine the sketch
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// free to move in this many independent ways
+    UnderConstrained(usize),
+    /// nothing can move, which includes an empty sketch
+    #[default]
+    FullyConstrained,
+    /// constraints conflict, or some follow from the others
+    OverConstrained,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::UnderConstrained(dof) => write!(f, "Under constrained ({} DOF)", dof),
+            Status::FullyConstrained => write!(f, "Fully constrained"),
+            Status::OverConstrained => write!(f, "Over constrained"),
+        }
+    }
+}
+
+/// move points and radii to satisfy the constraints
+///
+/// Geometry is left untouched if the constraints cannot be met.
+pub fn solve(points: &mut [Point2d], elements: &mut [SketchElement], constraints: &[Constraint]) -> Status {
+    let system = System::new(points, elements);
+    let mut x = system.variables(points, elements);
+
+    let converged = system.solve(&mut x, constraints);
+    if converged {
+        system.write(&x, points, elements);
+    }
+
+    let jacobian = system.jacobian(&x, constraints);
+    let equations = jacobian.len();
+    let rank = rank(jacobian);
+
+    if !converged || rank < equations {
+        Status::OverConstrained
+    } else if rank == x.len() {
+        Status::FullyConstrained
+    } else {
+        Status::UnderConstrained(x.len() - rank)
+    }
+}
+
+/// layout of the variables: point coordinates, then the radius of every circle
+struct System {
+    shapes: Vec<Shape>,
+    /// variable of each element's radius, for circles
+    radii: Vec<Option<usize>>,
+    count: usize,
+}
+
+impl System {
+    fn new(points: &[Point2d], elements: &[SketchElement]) -> Self {
+        let mut count = 2 * points.len();
+        let radii = elements.iter()
+            .map(|e| match e.shape {
+                Shape::Circle { .. } => {
+                    count += 1;
+                    Some(count - 1)
+                },
+                _ => None,
+            })
+            .collect();
+
+        Self { shapes: elements.iter().map(|e| e.shape).collect(), radii, count }
+    }
+
+    fn variables(&self, points: &[Point2d], elements: &[SketchElement]) -> Vec<f64> {
+        let mut x: Vec<f64> = points.iter()
+            .flat_map(|p| [p.x as f64, p.y as f64])
+            .collect();
+
+        x.extend(elements.iter().filter_map(|e| match e.shape {
+            Shape::Circle { radius, .. } => Some(radius as f64),
+            _ => None,
+        }));
+
+        debug_assert_eq!(x.len(), self.count);
+        x
+    }
+
+    fn write(&self, x: &[f64], points: &mut [Point2d], elements: &mut [SketchElement]) {
+        for (i, p) in points.iter_mut().enumerate() {
+            *p = Point2d::new(x[2 * i] as f32, x[2 * i + 1] as f32);
+        }
+
+        for (element, radius) in elements.iter_mut().zip(self.radii.iter()) {
+            if let (Shape::Circle { radius: r, .. }, Some(var)) = (&mut element.shape, radius) {
+                *r = x[*var] as f32;
+            }
+        }
+    }
+
+    fn point(x: &[f64], index: usize) -> [f64; 2] {
+        [x[2 * index], x[2 * index + 1]]
+    }
+
+    /// center point of a circle or arc
+    fn center(&self, element: usize) -> Option<usize> {
+        match self.shapes[element] {
+            Shape::Circle { center, .. } | Shape::Arc { center, .. } => Some(center),
+            Shape::Line { .. } => None,
+        }
+    }
+
+    fn radius(&self, x: &[f64], element: usize) -> Option<f64> {
+        match self.shapes[element] {
+            Shape::Circle { .. } => self.radii[element].map(|var| x[var]),
+            Shape::Arc { center, start, .. } => Some(distance(Self::point(x, center), Self::point(x, start))),
+            Shape::Line { .. } => None,
+        }
+    }
+
+    /// start point and direction of a line
+    fn line(&self, x: &[f64], element: usize) -> Option<([f64; 2], [f64; 2])> {
+        match self.shapes[element] {
+            Shape::Line { start, end } => {
+                let (a, b) = (Self::point(x, start), Self::point(x, end));
+                Some((a, [b[0] - a[0], b[1] - a[1]]))
+            },
+            _ => None,
+        }
+    }
+
+    /// every equation, including the one keeping each arc's end on its radius
+    fn residuals(&self, x: &[f64], constraints: &[Constraint]) -> Vec<f64> {
+        let mut r = Vec::new();
+
+        for shape in self.shapes.iter() {
+            if let Shape::Arc { center, start, end } = *shape {
+                let c = Self::point(x, center);
+                r.push(distance(c, Self::point(x, end)) - distance(c, Self::point(x, start)));
+            }
+        }
+
+        for constraint in constraints {
+            self.residual(x, constraint, &mut r);
+        }
+
+        r
+    }
+
+    fn residual(&self, x: &[f64], constraint: &Constraint, r: &mut Vec<f64>) {
+        match *constraint {
+            Constraint::Coincident(a, b) => {
+                let (a, b) = (Self::point(x, a), Self::point(x, b));
+                r.extend([a[0] - b[0], a[1] - b[1]]);
+            },
+            Constraint::Fixed { point, at } => {
+                let p = Self::point(x, point);
+                r.extend([p[0] - at.x as f64, p[1] - at.y as f64]);
+            },
+            Constraint::Horizontal(e) => if let Some((_, d)) = self.line(x, e) {
+                r.push(d[1]);
+            },
+            Constraint::Vertical(e) => if let Some((_, d)) = self.line(x, e) {
+                r.push(d[0]);
+            },
+            Constraint::Parallel(a, b) => if let (Some((_, da)), Some((_, db))) = (self.line(x, a), self.line(x, b)) {
+                let (da, db) = (unit(da), unit(db));
+                r.push(da[0] * db[1] - da[1] * db[0]);
+            },
+            Constraint::Perpendicular(a, b) => if let (Some((_, da)), Some((_, db))) = (self.line(x, a), self.line(x, b)) {
+                let (da, db) = (unit(da), unit(db));
+                r.push(da[0] * db[0] + da[1] * db[1]);
+            },
+            Constraint::Tangent(a, b) => {
+                // put the line first, if there is one
+                let (a, b) = match self.line(x, a) {
+                    Some(_) => (a, b),
+                    None => (b, a),
+                };
+
+                match (self.line(x, a), self.center(a), self.center(b), self.radius(x, b)) {
+                    (Some((p, d)), _, Some(center), Some(radius)) => {
+                        let c = Self::point(x, center);
+                        let d = unit(d);
+                        let off = (c[0] - p[0]) * d[1] - (c[1] - p[1]) * d[0];
+
+                        r.push(off.abs() - radius);
+                    },
+                    (None, Some(ca), Some(cb), Some(rb)) => if let Some(ra) = self.radius(x, a) {
+                        let dist = distance(Self::point(x, ca), Self::point(x, cb));
+
+                        // touching from outside unless one circle is inside the other
+                        match dist > ra.max(rb) {
+                            true => r.push(dist - (ra + rb)),
+                            false => r.push(dist - (ra - rb).abs()),
+                        }
+                    },
+                    _ => (),
+                }
+            },
+            Constraint::EqualLength(a, b) => if let (Some(la), Some(lb)) = (self.length(x, a), self.length(x, b)) {
+                r.push(la - lb);
+            },
+            Constraint::Concentric(a, b) => if let (Some(a), Some(b)) = (self.center(a), self.center(b)) {
+                let (a, b) = (Self::point(x, a), Self::point(x, b));
+                r.extend([a[0] - b[0], a[1] - b[1]]);
+            },
+            Constraint::Distance { a, b, value } => {
+                r.push(distance(Self::point(x, a), Self::point(x, b)) - value as f64);
+            },
+            Constraint::Radius { element, value } => if let Some(radius) = self.radius(x, element) {
+                r.push(radius - value as f64);
+            },
+            Constraint::Diameter { element, value } => if let Some(radius) = self.radius(x, element) {
+                r.push(2.0 * radius - value as f64);
+            },
+            Constraint::Angle { a, b, value } => if let (Some((_, da)), Some((_, db))) = (self.line(x, a), self.line(x, b)) {
+                let angle = (da[0] * db[1] - da[1] * db[0]).atan2(da[0] * db[0] + da[1] * db[1]);
+                let off = angle - (value as f64).to_radians();
+
+                // wrapped, so that a full turn off still counts as met
+                r.push(off.sin().atan2(off.cos()));
+            },
+        }
+    }
+
+    /// length of a line, or radius of a circle or arc
+    fn length(&self, x: &[f64], element: usize) -> Option<f64> {
+        match self.line(x, element) {
+            Some((_, d)) => Some(d[0].hypot(d[1])),
+            None => self.radius(x, element),
+        }
+    }
+
+    /// central difference jacobian, one row per equation
+    fn jacobian(&self, x: &[f64], constraints: &[Constraint]) -> Vec<Vec<f64>> {
+        let m = self.residuals(x, constraints).len();
+        let mut jacobian = vec![vec![0.0; x.len()]; m];
+
+        let mut probe = x.to_vec();
+        for j in 0..x.len() {
+            let h = 1e-6 * x[j].abs().max(1.0);
+
+            probe[j] = x[j] + h;
+            let plus = self.residuals(&probe, constraints);
+            probe[j] = x[j] - h;
+            let minus = self.residuals(&probe, constraints);
+            probe[j] = x[j];
+
+            for i in 0..m {
+                jacobian[i][j] = (plus[i] - minus[i]) / (2.0 * h);
+            }
+        }
+
+        jacobian
+    }
+
+    /// returns whether every equation was met
+    fn solve(&self, x: &mut [f64], constraints: &[Constraint]) -> bool {
+        let norm = |r: &[f64]| r.iter().fold(0.0f64, |acc, v| acc.max(v.abs()));
+
+        let mut r = self.residuals(x, constraints);
+        for _ in 0..MAX_ITERATIONS {
+            if norm(&r) < TOLERANCE {
+                return true;
+            }
+
+            // minimum norm step: dx = -J^T (J J^T + damping)^-1 r
+            let j = self.jacobian(x, constraints);
+            let m = r.len();
+            let mut a: Vec<Vec<f64>> = (0..m)
+                .map(|row| (0..m)
+                    .map(|col| j[row].iter().zip(j[col].iter()).map(|(a, b)| a * b).sum())
+                    .collect())
+                .collect();
+            for (i, row) in a.iter_mut().enumerate() {
+                row[i] += 1e-10;
+            }
+
+            let Some(y) = solve_linear(a, r.clone()) else { return false };
+            let step: Vec<f64> = (0..x.len())
+                .map(|col| -(0..m).map(|row| j[row][col] * y[row]).sum::<f64>())
+                .collect();
+
+            // halve the step until it improves the residual
+            let mut scale = 1.0;
+            loop {
+                let trial: Vec<f64> = x.iter().zip(step.iter()).map(|(x, s)| x + s * scale).collect();
+                let trial_r = self.residuals(&trial, constraints);
+
+                if norm(&trial_r) < norm(&r) || scale < 1e-4 {
+                    x.copy_from_slice(&trial);
+                    r = trial_r;
+                    break;
+                }
+
+                scale /= 2.0;
+            }
+        }
+
+        norm(&r) < TOLERANCE
+    }
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn unit(d: [f64; 2]) -> [f64; 2] {
+    let len = d[0].hypot(d[1]).max(1e-12);
+    [d[0] / len, d[1] / len]
+}
+
+/// gaussian elimination with partial pivoting
+fn solve_linear(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-14 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let pivot = a[col].clone();
+        for row in col + 1..n {
+            let factor = a[row][col] / pivot[col];
+            for (v, p) in a[row][col..].iter_mut().zip(&pivot[col..]) {
+                *v -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+
+    Some(x)
+}
+
+/// number of independent rows
+fn rank(mut m: Vec<Vec<f64>>) -> usize {
+    let cols = m.first().map_or(0, Vec::len);
+    let mut rank = 0;
+
+    for col in 0..cols {
+        let Some(pivot) = (rank..m.len()).max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs())) else { break };
+        if m[pivot][col].abs() < 1e-6 {
+            continue;
+        }
+        m.swap(rank, pivot);
+
+        let pivot = m[rank].clone();
+        for row in m[rank + 1..].iter_mut() {
+            let factor = row[col] / pivot[col];
+            for (v, p) in row[col..].iter_mut().zip(&pivot[col..]) {
+                *v -= factor * p;
+            }
+        }
+
+        rank += 1;
+    }
+
+    rank
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: f32, y: f32) -> Point2d {
+        Point2d::new(x, y)
+    }
+
+    fn line(start: usize, end: usize) -> SketchElement {
+        SketchElement { shape: Shape::Line { start, end }, construction: false }
+    }
+
+    fn near(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn horizontal_and_vertical() {
+        let mut points = vec![p(0.0, 0.0), p(3.0, 0.5), p(3.2, 4.0)];
+        let mut elements = vec![line(0, 1), line(1, 2)];
+        let constraints = [Constraint::Horizontal(0), Constraint::Vertical(1)];
+
+        assert_eq!(solve(&mut points, &mut elements, &constraints), Status::UnderConstrained(4));
+        assert!(near(points[0].y, points[1].y));
+        assert!(near(points[1].x, points[2].x));
+    }
+
+    #[test]
+    fn minimal_movement() {
+        // only the line's own end points move, and equally, for a horizontal constraint
+        let mut points = vec![p(0.0, 0.0), p(4.0, 1.0)];
+        let mut elements = vec![line(0, 1)];
+        solve(&mut points, &mut elements, &[Constraint::Horizontal(0)]);
+
+        assert!(near(points[0].y, 0.5) && near(points[1].y, 0.5));
+        assert!(near(points[0].x, 0.0) && near(points[1].x, 4.0));
+    }
+
+    #[test]
+    fn fully_constrained_rectangle() {
+        let mut points = vec![p(0.0, 0.0), p(2.1, 0.1), p(2.0, 1.2), p(-0.1, 0.9)];
+        let mut elements = vec![line(0, 1), line(1, 2), line(2, 3), line(3, 0)];
+        let constraints = [
+            Constraint::Fixed { point: 0, at: p(0.0, 0.0) },
+            Constraint::Horizontal(0),
+            Constraint::Horizontal(2),
+            Constraint::Vertical(1),
+            Constraint::Vertical(3),
+            Constraint::Fixed { point: 2, at: p(2.0, 1.0) },
+        ];
+
+        assert_eq!(solve(&mut points, &mut elements, &constraints), Status::FullyConstrained);
+        for (point, expected) in points.iter().zip([p(0.0, 0.0), p(2.0, 0.0), p(2.0, 1.0), p(0.0, 1.0)]) {
+            assert!(near(point.x, expected.x) && near(point.y, expected.y), "{:?}", points);
+        }
+    }
+
+    #[test]
+    fn redundant_is_over_constrained() {
+        let mut points = vec![p(0.0, 0.0), p(1.0, 0.0)];
+        let mut elements = vec![line(0, 1)];
+        let constraints = [Constraint::Horizontal(0), Constraint::Parallel(0, 0)];
+
+        assert_eq!(solve(&mut points, &mut elements, &constraints), Status::OverConstrained);
+    }
+
+    #[test]
+    fn conflict_leaves_geometry() {
+        let mut points = vec![p(0.0, 0.0), p(1.0, 1.0)];
+        let mut elements = vec![line(0, 1)];
+        let constraints = [
+            Constraint::Fixed { point: 0, at: p(0.0, 0.0) },
+            Constraint::Fixed { point: 1, at: p(1.0, 1.0) },
+            Constraint::Horizontal(0),
+        ];
+
+        assert_eq!(solve(&mut points, &mut elements, &constraints), Status::OverConstrained);
+        assert_eq!(points, [p(0.0, 0.0), p(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn parallel_perpendicular_equal() {
+        let mut points = vec![p(0.0, 0.0), p(2.0, 0.1), p(0.0, 1.0), p(1.0, 1.3), p(3.0, 0.0), p(3.2, 2.0)];
+        let mut elements = vec![line(0, 1), line(2, 3), line(4, 5)];
+        let constraints = [
+            Constraint::Parallel(0, 1),
+            Constraint::Perpendicular(0, 2),
+            Constraint::EqualLength(0, 1),
+        ];
+        solve(&mut points, &mut elements, &constraints);
+
+        let d = |a: usize, b: usize| points[b] - points[a];
+        assert!(near(d(0, 1).cross(&d(2, 3)), 0.0));
+        assert!(near(d(0, 1).dot(&d(4, 5)), 0.0));
+        assert!(near(d(0, 1).length(), d(2, 3).length()));
+    }
+
+    #[test]
+    fn tangent_and_concentric() {
+        let mut points = vec![p(0.0, 0.0), p(-2.0, 1.3), p(2.0, 1.5), p(0.2, -0.1)];
+        let mut elements = vec![
+            SketchElement { shape: Shape::Circle { center: 0, radius: 1.0 }, construction: false },
+            line(1, 2),
+            SketchElement { shape: Shape::Circle { center: 3, radius: 0.5 }, construction: false },
+        ];
+        let constraints = [Constraint::Tangent(1, 0), Constraint::Concentric(0, 2)];
+        solve(&mut points, &mut elements, &constraints);
+
+        let Shape::Circle { radius, .. } = elements[0].shape else { unreachable!() };
+        let (a, d) = (points[1], points[2] - points[1]);
+        let off = (points[0] - a).cross(&d) / d.length();
+        assert!(near(off.abs(), radius));
+        assert_eq!(points[0], points[3]);
+    }
+
+    #[test]
+    fn dimensions_drive_geometry() {
+        let mut points = vec![p(0.0, 0.0), p(3.0, 0.2), p(0.1, 2.0), p(5.0, 5.0)];
+        let mut elements = vec![
+            line(0, 1),
+            line(0, 2),
+            SketchElement { shape: Shape::Circle { center: 3, radius: 1.0 }, construction: false },
+        ];
+        let constraints = [
+            Constraint::Fixed { point: 0, at: p(0.0, 0.0) },
+            Constraint::Horizontal(0),
+            Constraint::Distance { a: 0, b: 1, value: 4.0 },
+            Constraint::Angle { a: 0, b: 1, value: 60.0 },
+            Constraint::Distance { a: 0, b: 2, value: 2.0 },
+            Constraint::Diameter { element: 2, value: 3.0 },
+        ];
+        assert_eq!(solve(&mut points, &mut elements, &constraints), Status::UnderConstrained(2));
+
+        assert!(near(points[1].x, 4.0) && near(points[1].y, 0.0));
+        assert!(near(points[2].x, 1.0) && near(points[2].y, 3.0f32.sqrt()));
+        assert!(matches!(elements[2].shape, Shape::Circle { radius, .. } if near(radius, 1.5)));
+    }
+
+    #[test]
+    fn arc_end_stays_on_radius() {
+        let mut points = vec![p(0.0, 0.0), p(1.0, 0.0), p(0.0, 1.0)];
+        let mut elements = vec![SketchElement { shape: Shape::Arc { center: 0, start: 1, end: 2 }, construction: false }];
+        let constraints = [Constraint::Fixed { point: 1, at: p(2.0, 0.0) }];
+        solve(&mut points, &mut elements, &constraints);
+
+        assert!(near(points[2].distance(&points[0]), points[1].distance(&points[0])));
+    }
+}
@@ -1,17 +1,36 @@
 
-use obj::Obj;
-
 use super::*;
-use crate::{prelude::*, formats::wavefront};
+use crate::prelude::{*, csg::Solid};
+
+/// how far apart triangles may be and still count as the same face
+const FACE_TOLERANCE: f32 = 1e-4;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ModelEntity {
-    ImportedModel(Obj),
+    ImportedModel(ImportedModel),
 
     Sketch(Sketch),
 
-    // TODO: add anchor
-    Block(Block)
+    Block(Block),
+
+    Extrude(Extrude),
+
+    Revolve(Revolve),
+
+    /// extrusion that removes material from the solids before it
+    Cut(Extrude),
+
+    Hole(Hole),
+
+    /// boolean of two earlier solids, which are drawn as part of it
+    Combine(Combine),
+
+    Fillet(Fillet),
+
+    Chamfer(Chamfer),
+
+    /// copies of an earlier entity, solid or subtractive like it
+    Pattern(Pattern),
 }
 
 impl ModelEntity {
@@ -19,22 +38,323 @@ impl ModelEntity {
         use ModelEntity::*;
 
         match self {
-            ImportedModel(obj) => wavefront::load(obj),
+            ImportedModel(model) => model.vertices(),
+            Sketch(sketch) => sketch.vertices(),
             Block(bl) => bl.vertices(),
-            _ => todo!()
+            Extrude(extrude) => extrude.vertices(),
+            Revolve(revolve) => revolve.vertices(),
+            Cut(cut) => cut.vertices(),
+            Hole(hole) => hole.vertices(),
+            Combine(combine) => combine.vertices(),
+            Fillet(fillet) => fillet.vertices(),
+            Chamfer(chamfer) => chamfer.vertices(),
+            Pattern(pattern) => pattern.vertices(),
+        }
+    }
+
+    pub fn edges(&self) -> Vec<Vertex> {
+        use ModelEntity::*;
+
+        match self {
+            Block(bl) => bl.edges(),
+            Sketch(sketch) => sketch.edges(),
+            Extrude(extrude) => extrude.edges(),
+            Revolve(revolve) => revolve.edges(),
+            Cut(cut) => cut.edges(),
+            Hole(hole) => hole.edges(),
+            Combine(combine) => combine.edges(),
+            Fillet(fillet) => fillet.edges(),
+            Chamfer(chamfer) => chamfer.edges(),
+            Pattern(pattern) => pattern.edges(),
+            ImportedModel(_) => Vec::new()
+        }
+    }
+
+    /// history indices of the entities this one is built from, which come before it
+    pub fn references(&self) -> Vec<usize> {
+        match self {
+            ModelEntity::Extrude(extrude) => extrude.references(),
+            ModelEntity::Revolve(revolve) => revolve.references(),
+            ModelEntity::Cut(cut) => cut.references(),
+            ModelEntity::Hole(hole) => hole.references(),
+            ModelEntity::Combine(combine) => combine.references(),
+            ModelEntity::Fillet(fillet) => fillet.references(),
+            ModelEntity::Chamfer(chamfer) => chamfer.references(),
+            ModelEntity::Pattern(pattern) => pattern.references(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// whether the entity encloses material, which cuts and holes can remove
+    pub fn is_solid(&self) -> bool {
+        match self {
+            ModelEntity::Pattern(pattern) => pattern.original().is_some_and(|e| e.is_solid()),
+            _ => matches!(self, ModelEntity::ImportedModel(_) | ModelEntity::Block(_) | ModelEntity::Extrude(_) | ModelEntity::Revolve(_) | ModelEntity::Combine(_)),
+        }
+    }
+
+    /// whether the entity removes material from the solids before it rather than adding its own
+    pub fn is_subtractive(&self) -> bool {
+        match self {
+            ModelEntity::Pattern(pattern) => pattern.original().is_some_and(|e| e.is_subtractive()),
+            _ => matches!(self, ModelEntity::Cut(_) | ModelEntity::Hole(_) | ModelEntity::Fillet(_) | ModelEntity::Chamfer(_)),
+        }
+    }
+
+    /// whether this subtractive feature takes material away from the entity at history index `index`
+    ///
+    /// Cuts and holes go through every solid before them, fillets and chamfers only change their own edge.
+    pub fn removes_from(&self, index: usize) -> bool {
+        match self {
+            ModelEntity::Fillet(fillet) => fillet.entity() == index,
+            ModelEntity::Chamfer(chamfer) => chamfer.entity() == index,
+            ModelEntity::Pattern(pattern) => pattern.original().is_some_and(|e| e.removes_from(index)),
+            _ => self.is_subtractive(),
+        }
+    }
+
+    /// same entity with its references renumbered, or `None` if it has none that change
+    pub(super) fn remapped(&self, map: impl Fn(usize) -> usize) -> Option<Self> {
+        if self.references().iter().all(|&r| map(r) == r) {
+            return None;
+        }
+
+        let mut entity = self.clone();
+        match &mut entity {
+            ModelEntity::Extrude(extrude) => extrude.remap(map),
+            ModelEntity::Revolve(revolve) => revolve.remap(map),
+            ModelEntity::Cut(cut) => cut.remap(map),
+            ModelEntity::Hole(hole) => hole.remap(map),
+            ModelEntity::Combine(combine) => combine.remap(map),
+            ModelEntity::Fillet(fillet) => fillet.remap(map),
+            ModelEntity::Chamfer(chamfer) => chamfer.remap(map),
+            ModelEntity::Pattern(pattern) => pattern.remap(map),
+            _ => return None,
+        }
+
+        Some(entity)
+    }
+
+    /// boundary representation of entities built from exact geometry
+    pub fn brep(&self) -> Option<Brep> {
+        match self {
+            ModelEntity::Block(block) => Some(block.brep()),
+            ModelEntity::Extrude(extrude) => Some(extrude.brep()),
+            ModelEntity::Revolve(revolve) => Some(revolve.brep()),
+            _ => None,
+        }
+    }
+
+    /// planes of the flat faces, with outward normals, that a sketch can be placed on
+    pub fn faces(&self) -> Vec<Plane> {
+        match self {
+            ModelEntity::Block(_) | ModelEntity::Extrude(_) | ModelEntity::Revolve(_) => {
+                self.brep().map(|brep| brep.planes()).unwrap_or_default()
+            },
+            ModelEntity::Pattern(_) if !self.is_solid() => Vec::new(),
+            ModelEntity::ImportedModel(_) | ModelEntity::Combine(_) | ModelEntity::Pattern(_) => {
+                // coplanar triangles make up a single face
+                let mut faces: Vec<Plane> = Vec::new();
+                for tri in self.vertices().chunks_exact(3) {
+                    let points = [tri[0], tri[1], tri[2]].map(|v| Point3d::from(v.position));
+                    let normal = crate::prelude::polygon::normal(&points);
+                    if normal.length() == 0.0 {
+                        continue;
+                    }
+
+                    let known = faces.iter().any(|f| {
+                        f.normal().dot(&normal) > 1.0 - FACE_TOLERANCE && f.distance(&points[0]).abs() < FACE_TOLERANCE
+                    });
+                    if !known {
+                        faces.push(Plane::new((points[0] + points[1] + points[2]) / 3.0, normal));
+                    }
+                }
+
+                faces
+            },
+            ModelEntity::Sketch(_) | ModelEntity::Cut(_) | ModelEntity::Hole(_) | ModelEntity::Fillet(_) | ModelEntity::Chamfer(_) => Vec::new(),
         }
     }
+
+    pub fn material(&self) -> Material {
+        match self {
+            ModelEntity::ImportedModel(model) => model.material(),
+            ModelEntity::Pattern(pattern) => pattern.original().map(|e| e.material()).unwrap_or_default(),
+            _ => Material::default()
+        }
+    }
+}
+
+/// bring every feature up to date with the entities it is built from, in history order
+///
+/// The bodies of the entities before the last combine are cut as the pass goes, so that
+/// each combine starts from them instead of taking every earlier cut away again.
+pub fn regenerate(entities: &mut [ModelEntity]) {
+    let last = entities.iter().rposition(|e| matches!(e, ModelEntity::Combine(_)));
+    let mut bodies = Vec::new();
+
+    for i in 0..entities.len() {
+        let (earlier, rest) = entities.split_at_mut(i);
+
+        match &mut rest[0] {
+            ModelEntity::Extrude(extrude) => extrude.resolve(earlier),
+            ModelEntity::Revolve(revolve) => revolve.resolve(earlier),
+            ModelEntity::Cut(cut) => cut.resolve(earlier),
+            ModelEntity::Hole(hole) => hole.resolve(earlier),
+            ModelEntity::Combine(combine) => combine.resolve(earlier, &bodies),
+            ModelEntity::Fillet(fillet) => fillet.resolve(earlier),
+            ModelEntity::Chamfer(chamfer) => chamfer.resolve(earlier),
+            ModelEntity::Pattern(pattern) => pattern.resolve(earlier),
+            _ => (),
+        }
+
+        if last.is_some_and(|last| i < last) {
+            bodies.push(body(&entities[i], true));
+            take_away(&entities[..=i], &mut bodies);
+        }
+    }
+}
+
+/// triangles and outline of every entity, with the material of later cuts and holes taken away
+///
+/// Subtractive features have neither, as they only change the solids before them, and
+/// neither do the solids that a later combine is made of. Whether an entity is hidden
+/// plays no part, so hidden cuts and holes still take material away.
+pub fn bodies(entities: &[ModelEntity]) -> Vec<(Vec<Vertex>, Vec<Vertex>)> {
+    bodies_except(entities, |_| false)
+}
+
+/// [`bodies`], left empty for the entities at the history indices that `skip` accepts
+pub(super) fn bodies_except(entities: &[ModelEntity], skip: impl Fn(usize) -> bool) -> Vec<(Vec<Vertex>, Vec<Vertex>)> {
+    let combined: Vec<usize> = entities.iter()
+        .filter(|e| matches!(e, ModelEntity::Combine(_)))
+        .flat_map(|e| e.references())
+        .collect();
+
+    let mut bodies = Vec::with_capacity(entities.len());
+    for (i, entity) in entities.iter().enumerate() {
+        bodies.push(body(entity, !combined.contains(&i) && !skip(i)));
+        take_away(&entities[..=i], &mut bodies);
+    }
+
+    bodies
+}
+
+/// whether the entity at history index `index` is a pattern of a solid that no later feature cuts or combines
+///
+/// Its copies are then all alike, and can be drawn as instances of the original.
+pub(super) fn is_instanced(entities: &[ModelEntity], index: usize) -> bool {
+    let changes = |e: &ModelEntity| e.removes_from(index) || (matches!(e, ModelEntity::Combine(_)) && e.references().contains(&index));
+
+    matches!(&entities[index], ModelEntity::Pattern(_)) && entities[index].is_solid() && !entities[index + 1..].iter().any(changes)
+}
+
+/// triangles and outline of `entity` before any cuts, or neither for subtractive features
+fn body(entity: &ModelEntity, keep: bool) -> (Vec<Vertex>, Vec<Vertex>) {
+    match entity.is_subtractive() || !keep {
+        true => (Vec::new(), Vec::new()),
+        false => (entity.vertices(), entity.edges()),
+    }
+}
+
+/// take the material of the last entity away from the bodies before it, if it is subtractive
+fn take_away(entities: &[ModelEntity], bodies: &mut [(Vec<Vertex>, Vec<Vertex>)]) {
+    let Some((feature, earlier)) = entities.split_last().filter(|(e, _)| e.is_subtractive()) else {
+        return;
+    };
+
+    let tool = Solid::from_vertices(&feature.vertices());
+    let tool_edges = feature.edges();
+
+    for (j, _) in earlier.iter().enumerate().filter(|(j, e)| e.is_solid() && feature.removes_from(*j)) {
+        let (vertices, edges) = &mut bodies[j];
+        if vertices.is_empty() {
+            continue;
+        }
+
+        let solid = Solid::from_vertices(vertices);
+        if !solid.overlaps(&tool) {
+            continue;
+        }
+
+        // outlines of the solid outside the cut, and of the cut inside the solid
+        let mut outline = tool.clip_lines(edges, false);
+        outline.extend(solid.clip_lines(&tool_edges, true));
+
+        *vertices = solid.subtract(&tool).vertices();
+        *edges = outline;
+    }
 }
 
-impl ToString for ModelEntity {
-    
-    fn to_string(&self) -> String {
+impl std::fmt::Display for ModelEntity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use ModelEntity::*;
 
         match self {
-            ImportedModel(_) => "ImportedModel",
-            Sketch(_) => "Sketch",
-            Block(_) => "Block",
-        }.into()
+            ImportedModel(model) => write!(f, "{}", model.name()),
+            Sketch(_) => write!(f, "Sketch"),
+            Block(_) => write!(f, "Block"),
+            Extrude(_) => write!(f, "Extrude"),
+            Revolve(_) => write!(f, "Revolve"),
+            Cut(_) => write!(f, "Cut"),
+            Hole(_) => write!(f, "Hole"),
+            Combine(combine) => write!(f, "{}", combine.operation()),
+            Fillet(_) => write!(f, "Fillet"),
+            Chamfer(_) => write!(f, "Chamfer"),
+            Pattern(pattern) => match pattern.kind() {
+                PatternKind::Linear { .. } => write!(f, "Linear Pattern"),
+                PatternKind::Circular { .. } => write!(f, "Circular Pattern"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::fixture::{drill_block, drilled_hole, drilled_volume};
+
+    #[test]
+    fn block_faces_are_outward() {
+        let block = ModelEntity::Block(Block::origin_and_max(Point3d::new(2.0, 2.0, 2.0)));
+        let center = Point3d::new(1.0, 1.0, 1.0);
+
+        let faces = block.faces();
+        assert_eq!(faces.len(), 6);
+        assert!(faces.iter().all(|f| f.distance(&center) < 0.0));
+    }
+
+    #[test]
+    fn coplanar_triangles_are_one_face() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("models").join("cube.obj");
+        let cube = ModelEntity::ImportedModel(ImportedModel::new("cube".into(), obj::Obj::load(path).unwrap()));
+
+        assert_eq!(cube.faces().len(), 6);
+    }
+
+    #[test]
+    fn cuts_remove_material_before_them() {
+        // a square cut 1 deep into the top, a through hole, and a block after both
+        let sketch = Sketch::polyline(
+            Plane::xy().at(Point3d::new(0.0, 0.0, 5.0)),
+            vec![Point2d::new(6.0, 6.0), Point2d::new(8.0, 6.0), Point2d::new(8.0, 8.0), Point2d::new(6.0, 8.0), Point2d::new(6.0, 6.0)],
+        );
+        let mut entities = vec![
+            drill_block(),
+            ModelEntity::Sketch(sketch),
+            ModelEntity::Cut(Extrude::new(1, Extent::Blind(-1.0))),
+            drilled_hole(0),
+            drill_block(),
+        ];
+        regenerate(&mut entities);
+
+        let bodies = bodies(&entities);
+        assert!((volume(&bodies[0].0) - (500.0 - 4.0 - drilled_volume())).abs() < 1e-2);
+        assert!((volume(&bodies[4].0) - 500.0).abs() < 1e-3);
+        assert!(bodies[2].0.is_empty() && bodies[3].0.is_empty());
+
+        // the rim of the hole is drawn on the top face, and nothing of the cutter above it
+        assert!(bodies[0].1.iter().all(|v| v.position[2] <= 5.0 + 1e-4));
+        assert!(bodies[0].1.iter().any(|v| (Point3d::from(v.position) - Point3d::new(3.0, 4.0, 5.0)).length() < 1e-4));
     }
 }
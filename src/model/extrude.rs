@@ -0,0 +1,268 @@
+
+use crate::prelude::{*, polygon};
+use super::{ModelEntity, Plane, Sketch};
+use super::brep::{self, Brep, Curve, Surface};
+use super::profile::runs;
+
+/// how far an extrusion reaches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Extent {
+    /// distance along the sketch normal, negative to extrude the other way
+    Blind(f32),
+    /// total distance, split evenly to both sides of the sketch
+    Symmetric(f32),
+    /// up to the plane of a face of an earlier entity
+    UpToFace { entity: usize, face: usize },
+}
+
+impl std::fmt::Display for Extent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Extent::Blind(_) => write!(f, "Blind"),
+            Extent::Symmetric(_) => write!(f, "Symmetric"),
+            Extent::UpToFace { .. } => write!(f, "Up to face"),
+        }
+    }
+}
+
+/// closed profiles of a sketch swept along its normal
+///
+/// The sketch and face are referred to by history index. Their geometry is copied
+/// in when the model regenerates, so that the solid follows edits of its parents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extrude {
+    sketch: usize,
+    extent: Extent,
+
+    /// parent sketch and target face, as of the last regeneration
+    profile: Sketch,
+    target: Option<Plane>,
+}
+
+impl Extrude {
+    pub fn new(sketch: usize, extent: Extent) -> Self {
+        Self { sketch, extent, profile: Sketch::default(), target: None }
+    }
+
+    /// history index of the profile sketch
+    pub fn sketch(&self) -> usize {
+        self.sketch
+    }
+
+    pub fn extent(&self) -> Extent {
+        self.extent
+    }
+
+    pub fn set_extent(&mut self, extent: Extent) {
+        self.extent = extent;
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        match self.extent {
+            Extent::UpToFace { entity, .. } => vec![self.sketch, entity],
+            _ => vec![self.sketch],
+        }
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.sketch = map(self.sketch);
+        if let Extent::UpToFace { entity, .. } = &mut self.extent {
+            *entity = map(*entity);
+        }
+    }
+
+    /// copy in the geometry of the parents, which come earlier in the history
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        match earlier.get(self.sketch) {
+            Some(ModelEntity::Sketch(sketch)) => self.profile = sketch.clone(),
+            _ => log::warn!("Extrude refers to entity {}, which is not an earlier sketch", self.sketch + 1),
+        }
+
+        self.target = match self.extent {
+            Extent::UpToFace { entity, face } => {
+                let plane = earlier.get(entity).and_then(|e| e.faces().get(face).cloned());
+                if plane.is_none() {
+                    log::warn!("Extrude refers to face {} of entity {}, which does not exist", face + 1, entity + 1);
+                }
+
+                plane
+            },
+            _ => None,
+        };
+    }
+
+    /// where a point of the sketch plane starts and ends its sweep
+    fn span(&self, point: Point3d) -> (Point3d, Point3d) {
+        let normal = self.profile.plane().normal();
+
+        match (self.extent, &self.target) {
+            (Extent::Blind(distance), _) => (point, point + normal * distance),
+            (Extent::Symmetric(distance), _) => (point - normal * (distance / 2.0), point + normal * (distance / 2.0)),
+            (Extent::UpToFace { .. }, Some(target)) => {
+                let along = normal.dot(&target.normal());
+                if along.abs() <= f32::EPSILON {
+                    return (point, point);
+                }
+
+                (point, point - normal * (target.distance(&point) / along))
+            },
+            (Extent::UpToFace { .. }, None) => (point, point),
+        }
+    }
+
+    /// faces of the solid, one shell per profile
+    ///
+    /// Each run of a loop between corners sweeps a side face.
+    pub fn brep(&self) -> Brep {
+        let plane = self.profile.plane();
+        let mut brep = Brep::new();
+
+        for profile in self.profile.profiles() {
+            let sweep = {
+                let (start, end) = self.span(plane.point(&profile.outer[0]));
+                end - start
+            };
+            if sweep.length() <= f32::EPSILON {
+                continue;
+            }
+
+            // the sides sweep up from the bottom along the sketch normal, with the material on the left of the loops
+            let up = sweep.dot(&plane.normal()) > 0.0;
+            let span = |p: Point2d| {
+                let (start, end) = self.span(plane.point(&p));
+                if up { (start, end) } else { (end, start) }
+            };
+
+            brep.add_shell();
+            let (mut bottom, mut top) = (Vec::new(), Vec::new());
+            for l in profile.loops() {
+                let (mut lower_loop, mut upper_loop) = (Vec::new(), Vec::new());
+
+                for run in runs(l) {
+                    let (lower, upper): (Vec<Point3d>, Vec<Point3d>) = run.iter().map(|&i| span(l[i])).unzip();
+                    let surface = match lower.len() {
+                        2 => Surface::Plane(Plane::new(lower[0], polygon::normal(&[lower[0], lower[1], upper[1], upper[0]]))),
+                        _ => Surface::Extrusion { direction: (upper[0] - lower[0]).normalize() },
+                    };
+
+                    let generator = brep.add_edge(lower.clone(), brep::curve(&lower));
+                    brep.add_swept_face(surface, generator, |i| (vec![lower[i], upper[i]], Curve::Line));
+
+                    lower_loop.insert(0, lower.into_iter().rev().collect());
+                    upper_loop.push(upper);
+                }
+
+                bottom.push(lower_loop);
+                top.push(upper_loop);
+            }
+
+            // the start of the sweep first, then its end
+            let (bottom, top) = (brep.add_planar_face(bottom), brep.add_planar_face(top));
+            brep.lead_with(&match up {
+                true => [bottom, top],
+                false => [top, bottom],
+            });
+        }
+
+        brep
+    }
+}
+
+impl super::ModelEntityObject for Extrude {
+    fn vertices(&self) -> Vec<Vertex> {
+        self.brep().tessellate()
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.brep().outline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ModelEntityObject, Shape};
+
+    /// square with a square hole, on the XY plane lifted to z = 1
+    fn sketch() -> Sketch {
+        let mut sketch = Sketch::on_plane(Plane::xy().at(Point3d::new(0.0, 0.0, 1.0)));
+        for (min, max) in [(0.0, 4.0), (1.0, 3.0)] {
+            let corners = [(min, min), (max, min), (max, max), (min, max)].map(|(x, y)| sketch.add_point(Point2d::new(x, y)));
+            for i in 0..4 {
+                sketch.add(Shape::Line { start: corners[i], end: corners[(i + 1) % 4] }, false);
+            }
+        }
+
+        sketch
+    }
+
+    fn extrude(extent: Extent) -> Extrude {
+        let mut extrude = Extrude::new(0, extent);
+        extrude.resolve(&[ModelEntity::Sketch(sketch())]);
+
+        extrude
+    }
+
+    #[test]
+    fn blind_is_closed_and_outward() {
+        let vertices = extrude(Extent::Blind(2.0)).vertices();
+
+        assert!((volume(&vertices) - 12.0 * 2.0).abs() < 1e-3);
+        assert!(vertices.iter().all(|v| v.position[2] >= 1.0 && v.position[2] <= 3.0));
+    }
+
+    #[test]
+    fn reversed_and_symmetric() {
+        let reversed = extrude(Extent::Blind(-2.0)).vertices();
+        assert!((volume(&reversed) - 24.0).abs() < 1e-3);
+        assert!(reversed.iter().all(|v| v.position[2] <= 1.0));
+
+        let symmetric = extrude(Extent::Symmetric(2.0)).vertices();
+        assert!((volume(&symmetric) - 24.0).abs() < 1e-3);
+        assert!(symmetric.iter().all(|v| (0.0..=2.0).contains(&v.position[2])));
+    }
+
+    #[test]
+    fn up_to_face() {
+        let block = ModelEntity::Block(crate::model::Block::origin_and_max(Point3d::new(10.0, 10.0, 5.0)));
+        let mut extrude = Extrude::new(1, Extent::UpToFace { entity: 0, face: 5 });
+        extrude.resolve(&[block, ModelEntity::Sketch(sketch())]);
+
+        // top of the block is at z = 5
+        let vertices = extrude.vertices();
+        assert!((volume(&vertices) - 12.0 * 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn brep_is_a_closed_shell() {
+        let brep = extrude(Extent::Blind(2.0)).brep();
+
+        // the two caps, four sides around the outside and four around the hole
+        assert_eq!(brep.faces().len(), 10);
+        assert_eq!(brep.shells().len(), 1);
+        assert!(brep.is_closed());
+        assert!(brep.faces()[..2].iter().all(|f| f.inner.len() == 1));
+
+        // the caps come first, the start of the sweep before its end, whichever way it goes
+        for (distance, start) in [(2.0, -1.0), (-2.0, 1.0)] {
+            let faces = ModelEntity::Extrude(extrude(Extent::Blind(distance))).faces();
+            assert_eq!((faces[0].normal().z, faces[1].normal().z), (start, -start));
+        }
+    }
+
+    #[test]
+    fn edges_skip_smooth_walls() {
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        let center = sketch.add_point(Point2d::default());
+        sketch.add(Shape::Circle { center, radius: 1.0 }, false);
+
+        let mut extrude = Extrude::new(0, Extent::Blind(1.0));
+        extrude.resolve(&[ModelEntity::Sketch(sketch)]);
+
+        // only the two rims, no lines along the wall
+        let edges = extrude.edges();
+        assert!(edges.chunks_exact(2).all(|pair| pair[0].position[2] == pair[1].position[2]));
+    }
+}
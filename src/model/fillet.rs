@@ -0,0 +1,442 @@
+
+//! rounded and bevelled edges
+//!
+//! Fillets and chamfers refer to a straight edge of an earlier block or extrusion by
+//! history index and edge index in its [`Brep`]. They take away the material between
+//! the edge and the new surface, like cuts do, but only from that entity.
+
+use crate::prelude::*;
+use super::{Brep, ModelEntity, Surface};
+use super::brep::{Curve, prism};
+
+/// steps of a full turn of a fillet surface
+const SEGMENTS: usize = 32;
+
+/// why a fillet or chamfer cannot be made on an edge it refers to, which [`Corner::new`] did not accept
+const NOT_A_CORNER: &str = "edge is not a straight convex edge of a block or extrusion";
+
+/// straight convex edge between two flat faces
+#[derive(Debug, Clone, PartialEq)]
+struct Corner {
+    start: Point3d,
+    end: Point3d,
+    /// unit directions along each face, away from the edge
+    inward: [Point3d; 2],
+    /// how far each face reaches from the edge, along its inward direction
+    span: [f32; 2],
+}
+
+impl Corner {
+    fn new(brep: &Brep, edge: usize) -> Option<Self> {
+        let e = brep.edges().get(edge)?;
+        let faces = brep.edge_faces(edge);
+        if e.curve != Curve::Line || faces.len() != 2 {
+            return None;
+        }
+
+        // faces lie to the left of their coedges, seen from outside
+        let inward = |face: usize| {
+            let face = &brep.faces()[face];
+            let Surface::Plane(plane) = &face.surface else {
+                return None;
+            };
+            let coedge = face.loops().flatten().find(|c| c.edge == edge)?;
+            let points = brep.points(*coedge);
+            let u = plane.normal().cross(&(points[1] - points[0])).normalize();
+            let span = face.loops().flatten()
+                .flat_map(|c| brep.points(*c))
+                .map(|p| (p - e.points[0]).dot(&u))
+                .fold(0.0, f32::max);
+
+            Some((plane.normal(), u, span))
+        };
+        let ((n1, u1, s1), (n2, u2, s2)) = (inward(faces[0])?, inward(faces[1])?);
+
+        let convex = u1.dot(&n2) < -1e-4 && u2.dot(&n1) < -1e-4;
+        convex.then(|| Self { start: e.points[0], end: e.points[e.points.len() - 1], inward: [u1, u2], span: [s1, s2] })
+    }
+
+    /// angle between the faces, inside the material
+    fn angle(&self) -> f32 {
+        self.inward[0].dot(&self.inward[1]).clamp(-1.0, 1.0).acos()
+    }
+
+    /// prism of the material outside `section`, which runs from the first face to the second
+    ///
+    /// The prism starts a little outside the corner and past both ends, so that it does not
+    /// share faces with the solid.
+    fn tool(&self, section: Vec<Point3d>) -> Brep {
+        let direction = (self.end - self.start).normalize();
+        let [u1, u2] = self.inward;
+        let lead = (section[0] - self.start).length().max((section[section.len() - 1] - self.start).length()) * 0.05;
+
+        let shift = |p: Point3d| p - direction * lead;
+        let corner = shift(self.start - (u1 + u2) * lead);
+        let section: Vec<Point3d> = section.into_iter().map(shift).collect();
+        let (first, last) = (section[0], section[section.len() - 1]);
+
+        let mut runs = vec![vec![corner, first], section, vec![last, corner]];
+        if polygon::normal(&[corner, first, last]).dot(&direction) < 0.0 {
+            runs = runs.into_iter().rev().map(|r| r.into_iter().rev().collect()).collect();
+        }
+
+        prism(&runs, direction * ((self.end - self.start).length() + 2.0 * lead))
+    }
+
+    /// `section` at both ends of the edge, where the tool reaches past the solid
+    fn ends(&self, section: &[Point3d]) -> Vec<Vertex> {
+        let offset = self.end - self.start;
+
+        [Point3d::default(), offset].iter()
+            .flat_map(|&o| section.windows(2).flat_map(move |pair| [pair[0] + o, pair[1] + o]))
+            .map(Vertex::from)
+            .collect()
+    }
+}
+
+/// straight convex edges of a block or extrusion, which can be filleted or chamfered, and their midpoints
+pub fn blend_edges(entity: &ModelEntity) -> Vec<(usize, Point3d)> {
+    let brep = match entity {
+        ModelEntity::Block(_) | ModelEntity::Extrude(_) => entity.brep().unwrap_or_default(),
+        _ => return Vec::new(),
+    };
+
+    (0..brep.edges().len())
+        .filter_map(|i| Corner::new(&brep, i).map(|c| (i, (c.start + c.end) / 2.0)))
+        .collect()
+}
+
+/// edge `edge` of `entity`, if it is a block or extrusion and the edge can be rounded or bevelled
+fn edge_corner(entity: &ModelEntity, edge: usize) -> Option<Corner> {
+    match entity {
+        ModelEntity::Block(_) | ModelEntity::Extrude(_) => entity.brep().and_then(|brep| Corner::new(&brep, edge)),
+        _ => None,
+    }
+}
+
+/// edge `edge` of the entity at history index `entity`, if it can be rounded or bevelled
+fn corner(earlier: &[ModelEntity], entity: usize, edge: usize, feature: &str) -> Option<Corner> {
+    let corner = earlier.get(entity).and_then(|e| edge_corner(e, edge));
+
+    if corner.is_none() {
+        log::warn!("{} refers to edge {} of entity {}, which is not a straight convex edge of an earlier block or extrusion", feature, edge + 1, entity + 1);
+    }
+
+    corner
+}
+
+/// edge rounded with a constant radius
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fillet {
+    entity: usize,
+    edge: usize,
+    radius: f32,
+
+    /// edge geometry, as of the last regeneration
+    corner: Option<Corner>,
+}
+
+impl Fillet {
+    pub fn new(entity: usize, edge: usize, radius: f32) -> Self {
+        Self { entity, edge, radius, corner: None }
+    }
+
+    /// history index of the entity the edge belongs to
+    pub fn entity(&self) -> usize {
+        self.entity
+    }
+
+    pub fn edge(&self) -> usize {
+        self.edge
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.entity]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.entity = map(self.entity);
+    }
+
+    /// why the fillet cannot round its edge of `entity`, which it refers to, or `None` if it can
+    pub fn error(&self, entity: &ModelEntity) -> Option<&'static str> {
+        match edge_corner(entity, self.edge) {
+            Some(corner) => self.setback(&corner).err(),
+            None => Some(NOT_A_CORNER),
+        }
+    }
+
+    /// copy in the edge, which comes earlier in the history
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        self.corner = corner(earlier, self.entity, self.edge, "Fillet");
+        if let Some(error) = self.corner.as_ref().and_then(|corner| self.setback(corner).err()) {
+            log::warn!("Fillet on edge {} of entity {} cannot be made, as the {}", self.edge + 1, self.entity + 1, error);
+        }
+    }
+
+    /// how far the rounded surface reaches along both faces from the edge
+    ///
+    /// The circle touches both faces, so the fillet cannot reach past the shorter of them.
+    fn setback(&self, corner: &Corner) -> Result<f32, &'static str> {
+        let setback = self.radius / (corner.angle() / 2.0).tan();
+        match setback > corner.span[0].min(corner.span[1]) {
+            true => Err("fillet is wider than the faces at the edge"),
+            false => Ok(setback),
+        }
+    }
+
+    /// edge, and the rounded surface across it from the first face to the second
+    fn section(&self) -> Option<(&Corner, Vec<Point3d>)> {
+        let corner = self.corner.as_ref()?;
+        let [u1, u2] = corner.inward;
+        let angle = corner.angle();
+
+        // the circle touches both faces, which it meets at the setback from the edge
+        let setback = self.setback(corner).ok()?;
+        let center = corner.start + (u1 + u2).normalize() * (self.radius / (angle / 2.0).sin());
+        let (from, to) = (corner.start + u1 * setback - center, corner.start + u2 * setback - center);
+
+        let sweep = std::f32::consts::PI - angle;
+        let steps = ((SEGMENTS as f32 * sweep / std::f32::consts::TAU).ceil() as usize).max(2);
+        let across = (to - from * (from.dot(&to) / from.dot(&from))).normalize() * self.radius;
+        let section = (0..=steps)
+            .map(|k| {
+                let t = sweep * k as f32 / steps as f32;
+                center + from * t.cos() + across * t.sin()
+            })
+            .collect();
+
+        Some((corner, section))
+    }
+}
+
+impl super::ModelEntityObject for Fillet {
+    /// the material taken away, between the edge and the rounded surface
+    fn vertices(&self) -> Vec<Vertex> {
+        self.section().map(|(corner, section)| corner.tool(section).tessellate()).unwrap_or_default()
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.section()
+            .map(|(corner, section)| {
+                let mut edges = corner.ends(&section);
+                edges.extend(corner.tool(section).outline());
+                edges
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// how far a chamfer reaches into the faces at its edge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChamferSize {
+    /// same distance along both faces
+    Distance(f32),
+    /// distance along the first face, and angle from it in degrees
+    DistanceAngle { distance: f32, angle: f32 },
+}
+
+impl std::fmt::Display for ChamferSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChamferSize::Distance(_) => write!(f, "Distance"),
+            ChamferSize::DistanceAngle { .. } => write!(f, "Distance and angle"),
+        }
+    }
+}
+
+/// edge bevelled by a flat face
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chamfer {
+    entity: usize,
+    edge: usize,
+    size: ChamferSize,
+
+    /// edge geometry, as of the last regeneration
+    corner: Option<Corner>,
+}
+
+impl Chamfer {
+    pub fn new(entity: usize, edge: usize, size: ChamferSize) -> Self {
+        Self { entity, edge, size, corner: None }
+    }
+
+    /// history index of the entity the edge belongs to
+    pub fn entity(&self) -> usize {
+        self.entity
+    }
+
+    pub fn edge(&self) -> usize {
+        self.edge
+    }
+
+    pub fn size(&self) -> ChamferSize {
+        self.size
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.entity]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.entity = map(self.entity);
+    }
+
+    /// why the chamfer cannot bevel its edge of `entity`, which it refers to, or `None` if it can
+    pub fn error(&self, entity: &ModelEntity) -> Option<&'static str> {
+        match edge_corner(entity, self.edge) {
+            Some(corner) => self.reach(&corner).err(),
+            None => Some(NOT_A_CORNER),
+        }
+    }
+
+    /// copy in the edge, which comes earlier in the history
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        self.corner = corner(earlier, self.entity, self.edge, "Chamfer");
+        if let Some(error) = self.corner.as_ref().and_then(|corner| self.reach(corner).err()) {
+            log::warn!("Chamfer on edge {} of entity {} cannot be made, as the {}", self.edge + 1, self.entity + 1, error);
+        }
+    }
+
+    /// how far the bevel reaches along the first and the second face from the edge
+    fn reach(&self, corner: &Corner) -> Result<(f32, f32), &'static str> {
+        // the bevel, the faces and the edge make a triangle, with the faces meeting at the edge
+        let (first, second) = match self.size {
+            ChamferSize::Distance(distance) => (distance, distance),
+            ChamferSize::DistanceAngle { distance, angle } => {
+                let angle = angle.to_radians();
+                let far = std::f32::consts::PI - corner.angle() - angle;
+                if far <= f32::EPSILON {
+                    return Err("chamfer angle does not meet the second face");
+                }
+
+                (distance, distance * angle.sin() / far.sin())
+            },
+        };
+
+        match first > corner.span[0] || second > corner.span[1] {
+            true => Err("chamfer is wider than the faces at the edge"),
+            false => Ok((first, second)),
+        }
+    }
+
+    /// edge, and the bevel across it from the first face to the second
+    fn section(&self) -> Option<(&Corner, Vec<Point3d>)> {
+        let corner = self.corner.as_ref()?;
+        let [u1, u2] = corner.inward;
+        let (first, second) = self.reach(corner).ok()?;
+
+        Some((corner, vec![corner.start + u1 * first, corner.start + u2 * second]))
+    }
+}
+
+impl super::ModelEntityObject for Chamfer {
+    /// the material taken away, between the edge and the bevel
+    fn vertices(&self) -> Vec<Vertex> {
+        self.section().map(|(corner, section)| corner.tool(section).tessellate()).unwrap_or_default()
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.section()
+            .map(|(corner, section)| {
+                let mut edges = corner.ends(&section);
+                edges.extend(corner.tool(section).outline());
+                edges
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Block, bodies, regenerate};
+
+    /// 2 x 2 x 2 block, with `feature` made from one of its top edges
+    fn blend(feature: impl Fn(usize) -> ModelEntity) -> Vec<ModelEntity> {
+        let block = ModelEntity::Block(Block::origin_and_max(Point3d::new(2.0, 2.0, 2.0)));
+        let (edge, _) = blend_edges(&block).into_iter()
+            .find(|(_, mid)| mid.z == 2.0 && mid.y == 0.0)
+            .unwrap();
+
+        let mut entities = vec![block, feature(edge)];
+        regenerate(&mut entities);
+
+        entities
+    }
+
+    #[test]
+    fn block_edges_can_be_blended() {
+        let block = ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0)));
+
+        assert_eq!(blend_edges(&block).len(), 12);
+    }
+
+    #[test]
+    fn fillet_rounds_the_edge() {
+        let entities = blend(|edge| ModelEntity::Fillet(Fillet::new(0, edge, 0.5)));
+        let body = &bodies(&entities)[0];
+
+        // a square corner of 0.5 less a quarter circle, along the 2 long edge
+        let removed = 2.0 * 0.25 * (1.0 - std::f32::consts::FRAC_PI_4);
+        assert!((volume(&body.0) - (8.0 - removed)).abs() < 1e-2);
+
+        // the rounded surface is shaded smoothly, with normals between the two faces
+        assert!(body.0.iter().any(|v| v.normal[1] < -0.1 && v.normal[2] > 0.1));
+
+        // the edge itself is gone from the outline, and the arc is drawn on both ends
+        let on_edge = |v: &Vertex| v.position[1].abs() < 1e-3 && (v.position[2] - 2.0).abs() < 1e-3;
+        assert!(!body.1.iter().any(on_edge));
+        assert!(body.1.iter().any(|v| v.position[0].abs() < 1e-4 && v.position[1] > 0.1 && v.position[1] < 0.4));
+    }
+
+    #[test]
+    fn chamfer_distance_and_angle() {
+        let distance = blend(|edge| ModelEntity::Chamfer(Chamfer::new(0, edge, ChamferSize::Distance(0.5))));
+        assert!((volume(&bodies(&distance)[0].0) - (8.0 - 2.0 * 0.125)).abs() < 1e-3);
+
+        // at 60° from the first face, the bevel reaches tan(60°) as far along the second
+        let angled = blend(|edge| ModelEntity::Chamfer(Chamfer::new(0, edge, ChamferSize::DistanceAngle { distance: 0.5, angle: 60.0 })));
+        let removed = 2.0 * 0.5 * 0.5 * (0.5 * 60f32.to_radians().tan());
+        assert!((volume(&bodies(&angled)[0].0) - (8.0 - removed)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn blends_wider_than_the_faces_cut_nothing() {
+        let fillet = blend(|edge| ModelEntity::Fillet(Fillet::new(0, edge, 2.5)));
+        let chamfer = blend(|edge| ModelEntity::Chamfer(Chamfer::new(0, edge, ChamferSize::Distance(2.5))));
+
+        for entities in [&fillet, &chamfer] {
+            assert!(entities[1].vertices().is_empty());
+            assert!((volume(&bodies(entities)[0].0) - 8.0).abs() < 1e-4);
+        }
+
+        // the error is known before the feature is made, from the entity it refers to
+        let ModelEntity::Fillet(wide) = &fillet[1] else { unreachable!() };
+        assert!(wide.error(&fillet[0]).is_some());
+        assert!(Fillet::new(0, wide.edge(), 1.5).error(&fillet[0]).is_none());
+
+        // at 60° from the first face, a bevel of 1.5 reaches 2.6 along the second
+        let angled = Chamfer::new(0, wide.edge(), ChamferSize::DistanceAngle { distance: 1.5, angle: 60.0 });
+        assert!(angled.error(&fillet[0]).is_some());
+    }
+
+    #[test]
+    fn only_straight_convex_edges() {
+        let block = ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0)));
+        let mut entities = vec![block, ModelEntity::Fillet(Fillet::new(0, 99, 0.1))];
+        regenerate(&mut entities);
+
+        assert!(entities[1].vertices().is_empty());
+        assert_eq!(Fillet::new(0, 99, 0.1).error(&entities[0]), Some(NOT_A_CORNER));
+        assert_eq!(Chamfer::new(0, 99, ChamferSize::Distance(0.1)).error(&entities[0]), Some(NOT_A_CORNER));
+    }
+}
@@ -0,0 +1,119 @@
+
+//! undoable edits of a [`Model`](super::Model)
+//!
+//! Every edit is stored as the edit that reverts it. Applying an edit returns
+//! its own inverse, so the same type moves entries between the undo and redo
+//! stacks.
+
+use std::collections::VecDeque;
+
+use super::ModelEntity;
+
+/// number of edits that can be undone
+pub const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone)]
+pub(super) enum Edit {
+    Insert { index: usize, entity: ModelEntity, hidden: bool },
+    Remove { index: usize },
+    /// swap the entity at `index` with `entity`
+    Replace { index: usize, entity: ModelEntity },
+    SetHidden { index: usize, hidden: bool },
+    /// swap the entity at `index` with the one before it
+    MoveUp { index: usize },
+    /// swap the whole history, for resets and opened parts
+    Reset { geometry: Vec<ModelEntity>, hidden: Vec<bool> },
+    /// edits applied in order and reverted in reverse order
+    Batch(Vec<Edit>),
+}
+
+impl Edit {
+    /// apply the edit, returning the edit that reverts it
+    pub(super) fn apply(self, geometry: &mut Vec<ModelEntity>, hidden: &mut Vec<bool>) -> Self {
+        match self {
+            Edit::Insert { index, entity, hidden: h } => {
+                geometry.insert(index, entity);
+                hidden.insert(index, h);
+
+                Edit::Remove { index }
+            },
+            Edit::Remove { index } => Edit::Insert {
+                index,
+                entity: geometry.remove(index),
+                hidden: hidden.remove(index),
+            },
+            Edit::Replace { index, entity } => Edit::Replace {
+                index,
+                entity: std::mem::replace(&mut geometry[index], entity),
+            },
+            Edit::SetHidden { index, hidden: h } => Edit::SetHidden {
+                index,
+                hidden: std::mem::replace(&mut hidden[index], h),
+            },
+            Edit::MoveUp { index } => {
+                geometry.swap(index - 1, index);
+                hidden.swap(index - 1, index);
+
+                Edit::MoveUp { index }
+            },
+            Edit::Reset { geometry: mut g, hidden: mut h } => {
+                std::mem::swap(geometry, &mut g);
+                std::mem::swap(hidden, &mut h);
+
+                Edit::Reset { geometry: g, hidden: h }
+            },
+            Edit::Batch(edits) => {
+                let mut inverse: Vec<Edit> = edits.into_iter()
+                    .map(|e| e.apply(geometry, hidden))
+                    .collect();
+                inverse.reverse();
+
+                Edit::Batch(inverse)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    /// record the inverse of an applied edit, dropping the oldest past [`MAX_HISTORY`]
+    pub(super) fn record(&mut self, inverse: Edit) {
+        self.redo.clear();
+
+        self.undo.push_back(inverse);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.pop_front();
+        }
+    }
+
+    pub(super) fn undo(&mut self) -> Option<Edit> {
+        self.undo.pop_back()
+    }
+
+    pub(super) fn redo(&mut self) -> Option<Edit> {
+        self.redo.pop()
+    }
+
+    /// keep the inverse of an undone edit for redo
+    pub(super) fn undone(&mut self, inverse: Edit) {
+        self.redo.push(inverse);
+    }
+
+    /// keep the inverse of a redone edit for undo
+    pub(super) fn redone(&mut self, inverse: Edit) {
+        self.undo.push_back(inverse);
+    }
+
+    pub(super) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(super) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
@@ -0,0 +1,319 @@
+
+use crate::prelude::{*, polygon::push_triangle};
+use super::{ModelEntity, Plane};
+
+/// steps around the circumference of a hole
+pub const SEGMENTS: usize = 32;
+
+/// included angle of a drill point, in degrees
+const DRILL_POINT: f32 = 118.0;
+
+/// how a hole opens at the face
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoleKind {
+    Simple,
+    Counterbore { diameter: f32, depth: f32 },
+    /// `angle` is the included angle of the cone, in degrees
+    Countersink { diameter: f32, angle: f32 },
+    /// the hole is the tap drill, and `callout` names the thread, such as `M6x1.0`
+    Tapped { callout: String, thread_depth: f32 },
+}
+
+impl std::fmt::Display for HoleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HoleKind::Simple => write!(f, "Simple"),
+            HoleKind::Counterbore { .. } => write!(f, "Counterbore"),
+            HoleKind::Countersink { .. } => write!(f, "Countersink"),
+            HoleKind::Tapped { .. } => write!(f, "Tapped"),
+        }
+    }
+}
+
+/// hole drilled into a face of an earlier entity
+///
+/// The face is referred to by history and face index, and copied in when the
+/// model regenerates, like the faces [`super::Extrude`] extrudes up to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hole {
+    entity: usize,
+    face: usize,
+    /// center in the coordinates of the face plane
+    position: Point2d,
+    diameter: f32,
+    /// `None` to cut through everything below the face
+    depth: Option<f32>,
+    kind: HoleKind,
+
+    /// face plane, and how deep a through hole goes, as of the last regeneration
+    plane: Option<Plane>,
+    through: f32,
+}
+
+impl Hole {
+    pub fn new(entity: usize, face: usize, position: Point2d, diameter: f32, depth: Option<f32>, kind: HoleKind) -> Self {
+        Self { entity, face, position, diameter, depth, kind, plane: None, through: 0.0 }
+    }
+
+    /// history index of the entity the hole is placed on
+    pub fn entity(&self) -> usize {
+        self.entity
+    }
+
+    pub fn face(&self) -> usize {
+        self.face
+    }
+
+    pub fn position(&self) -> Point2d {
+        self.position
+    }
+
+    pub fn diameter(&self) -> f32 {
+        self.diameter
+    }
+
+    pub fn depth(&self) -> Option<f32> {
+        self.depth
+    }
+
+    pub fn kind(&self) -> &HoleKind {
+        &self.kind
+    }
+
+    /// why the hole cannot be made, or `None` if it can
+    ///
+    /// A counterbore or countersink has to be wider than the hole and shallower than it.
+    /// How deep a through hole goes is only known once it is resolved, so until then only
+    /// blind holes are checked for depth.
+    pub fn error(&self) -> Option<&'static str> {
+        if self.diameter <= 0.0 {
+            return Some("hole diameter must be positive");
+        }
+
+        let depth = self.depth.or(self.plane.is_some().then_some(self.through));
+        match self.kind {
+            HoleKind::Counterbore { diameter, .. } if diameter <= self.diameter => Some("counterbore must be wider than the hole"),
+            HoleKind::Counterbore { depth: bore, .. } if bore <= 0.0 || depth.is_some_and(|d| bore >= d) => {
+                Some("counterbore must be shallower than the hole")
+            },
+            HoleKind::Countersink { diameter, .. } if diameter <= self.diameter => Some("countersink must be wider than the hole"),
+            HoleKind::Countersink { angle, .. } if !(angle > 0.0 && angle < 180.0) => Some("countersink angle must be between 0° and 180°"),
+            HoleKind::Countersink { diameter, angle } if depth.is_some_and(|d| self.sink(diameter, angle) >= d) => {
+                Some("countersink must be shallower than the hole")
+            },
+            _ => None,
+        }
+    }
+
+    /// how deep a countersink of `diameter` and included `angle` in degrees reaches below the face
+    fn sink(&self, diameter: f32, angle: f32) -> f32 {
+        (diameter - self.diameter) / 2.0 / (angle.to_radians() / 2.0).tan()
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.entity]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.entity = map(self.entity);
+    }
+
+    /// copy in the face the hole is placed on, and measure the solids below it
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        self.plane = earlier.get(self.entity).and_then(|e| e.faces().get(self.face).cloned());
+
+        let Some(plane) = &self.plane else {
+            log::warn!("Hole refers to face {} of entity {}, which does not exist", self.face + 1, self.entity + 1);
+            return;
+        };
+
+        self.through = earlier.iter()
+            .filter(|e| e.is_solid())
+            .flat_map(|e| e.vertices())
+            .map(|v| -plane.distance(&v.position.into()))
+            .fold(0.0, f32::max);
+    }
+
+    /// profile of the cutter from the top down, as depth below the face and radius
+    fn profile(&self) -> Vec<(f32, f32)> {
+        let radius = self.diameter / 2.0;
+        let depth = self.depth.unwrap_or(self.through);
+
+        // the cutter starts above the face so that it does not share the face's plane
+        let (mut profile, top) = match self.kind {
+            HoleKind::Counterbore { diameter, depth } => {
+                let lead = diameter * 0.05;
+                (vec![(-lead, diameter / 2.0), (depth, diameter / 2.0), (depth, radius)], diameter)
+            },
+            HoleKind::Countersink { diameter, angle } => {
+                let slope = (angle.to_radians() / 2.0).tan();
+                let lead = diameter * 0.05;
+                (vec![(-lead, diameter / 2.0 + lead * slope), (self.sink(diameter, angle), radius)], diameter)
+            },
+            HoleKind::Simple | HoleKind::Tapped { .. } => (vec![(-self.diameter * 0.05, radius)], self.diameter),
+        };
+
+        match self.depth {
+            Some(_) => {
+                let tip = radius / (DRILL_POINT.to_radians() / 2.0).tan();
+                profile.extend([(depth, radius), (depth + tip, 0.0)]);
+            },
+            // past the far side, so that the bottom does not land on a face either
+            None => profile.push((depth + top * 0.05, radius)),
+        }
+
+        profile
+    }
+
+    /// face plane, and the center of the hole on it, or `None` if there is no hole to make
+    fn center(&self) -> Option<(Plane, Point3d)> {
+        let plane = self.plane.as_ref().filter(|_| self.error().is_none())?;
+
+        Some((plane.clone(), plane.point(&self.position)))
+    }
+}
+
+impl super::ModelEntityObject for Hole {
+    /// the cutter, which is taken away from the solids before the hole
+    fn vertices(&self) -> Vec<Vertex> {
+        let Some((plane, center)) = self.center() else {
+            return Vec::new();
+        };
+
+        let down = -plane.normal();
+        let ring = |(depth, radius): (f32, f32), k: usize| {
+            let angle = std::f32::consts::TAU * k as f32 / SEGMENTS as f32;
+            center + down * depth + (plane.x_axis() * angle.cos() + plane.y_axis() * angle.sin()) * radius
+        };
+
+        let profile = self.profile();
+        let mut data = Vec::new();
+        for k in 0..SEGMENTS {
+            let middle = std::f32::consts::TAU * (k as f32 + 0.5) / SEGMENTS as f32;
+            let radial = plane.x_axis() * middle.cos() + plane.y_axis() * middle.sin();
+
+            // the top faces up and the bottom down, unless it ends in a point
+            let (first, last) = (profile[0], profile[profile.len() - 1]);
+            push_triangle(&mut data, [center + down * first.0, ring(first, k), ring(first, k + 1)], -down);
+            if last.1 > 0.0 {
+                push_triangle(&mut data, [center + down * last.0, ring(last, k), ring(last, k + 1)], down);
+            }
+
+            // the wall faces away from the axis, tilted by its slope
+            for pair in profile.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let outward = down * (a.1 - b.1) + radial * (b.0 - a.0);
+
+                push_triangle(&mut data, [ring(a, k), ring(b, k), ring(b, k + 1)], outward);
+                push_triangle(&mut data, [ring(a, k), ring(b, k + 1), ring(a, k + 1)], outward);
+            }
+        }
+
+        data
+    }
+
+    /// the rim at the face, and every step below it
+    fn edges(&self) -> Vec<Vertex> {
+        let Some((plane, center)) = self.center() else {
+            return Vec::new();
+        };
+
+        let down = -plane.normal();
+        let profile = self.profile();
+
+        // the rim is where the cutter meets the face
+        let (a, b) = (profile[0], profile[1]);
+        let rim = (0.0, a.1 + (b.1 - a.1) * (-a.0 / (b.0 - a.0)));
+
+        let mut data = Vec::new();
+        for (depth, radius) in std::iter::once(rim).chain(profile[1..].iter().copied()).filter(|(_, r)| *r > 0.0) {
+            for k in 0..SEGMENTS {
+                let at = |k: usize| {
+                    let angle = std::f32::consts::TAU * k as f32 / SEGMENTS as f32;
+                    center + down * depth + (plane.x_axis() * angle.cos() + plane.y_axis() * angle.sin()) * radius
+                };
+
+                data.extend([at(k), at(k + 1)].map(Vertex::from));
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelEntityObject;
+    use crate::prelude::fixture::{drill_block, top_face};
+
+    /// hole in the middle of the top face of the drill block
+    fn hole(depth: Option<f32>, kind: HoleKind) -> Hole {
+        let block = drill_block();
+
+        let mut hole = Hole::new(0, top_face(&block), Point2d::default(), 2.0, depth, kind);
+        hole.resolve(&[block]);
+
+        hole
+    }
+
+    #[test]
+    fn through_hole_passes_the_block() {
+        let vertices = hole(None, HoleKind::Simple).vertices();
+
+        assert!(volume(&vertices) > 0.0);
+        assert!(vertices.iter().any(|v| v.position[2] < 0.0));
+        assert!(vertices.iter().any(|v| v.position[2] > 5.0));
+    }
+
+    #[test]
+    fn blind_hole_ends_in_a_drill_point() {
+        let vertices = hole(Some(3.0), HoleKind::Simple).vertices();
+        let bottom = vertices.iter().map(|v| v.position[2]).fold(f32::MAX, f32::min);
+
+        let tip = 1.0 / (DRILL_POINT.to_radians() / 2.0).tan();
+        assert!((bottom - (2.0 - tip)).abs() < 1e-4);
+        assert!(volume(&vertices) > 0.0);
+    }
+
+    #[test]
+    fn counterbore_and_countersink_rims() {
+        let counterbore = hole(Some(3.0), HoleKind::Counterbore { diameter: 4.0, depth: 1.0 });
+        let countersink = hole(Some(3.0), HoleKind::Countersink { diameter: 4.0, angle: 90.0 });
+
+        for hole in [counterbore, countersink] {
+            let edges = hole.edges();
+            let radius = |v: &Vertex| Point2d::new(v.position[0] - 5.0, v.position[1] - 5.0).length();
+
+            // the widest rim sits on the face
+            assert!(edges.iter().all(|v| radius(v) <= 2.0 + 1e-4));
+            assert!(edges.iter().any(|v| (radius(v) - 2.0).abs() < 1e-4 && (v.position[2] - 5.0).abs() < 1e-4));
+            assert!(edges.iter().all(|v| v.position[2] <= 5.0 + 1e-4));
+            assert!(volume(&hole.vertices()) > 0.0);
+        }
+    }
+
+    #[test]
+    fn impossible_hole_cuts_nothing() {
+        for kind in [
+            HoleKind::Counterbore { diameter: 1.0, depth: 1.0 },
+            HoleKind::Counterbore { diameter: 4.0, depth: 3.0 },
+            HoleKind::Countersink { diameter: 2.0, angle: 90.0 },
+            HoleKind::Countersink { diameter: 8.0, angle: 90.0 },
+            HoleKind::Countersink { diameter: 4.0, angle: 0.0 },
+        ] {
+            let hole = hole(Some(3.0), kind);
+
+            assert!(hole.error().is_some());
+            assert!(hole.vertices().is_empty() && hole.edges().is_empty());
+        }
+
+        // a through hole is as deep as the block, once it has been measured
+        let deep = HoleKind::Counterbore { diameter: 4.0, depth: 8.0 };
+        assert!(hole(None, deep.clone()).error().is_some());
+        assert!(Hole::new(0, 0, Point2d::default(), 2.0, None, deep).error().is_none());
+        assert!(hole(None, HoleKind::Counterbore { diameter: 4.0, depth: 4.0 }).error().is_none());
+    }
+}
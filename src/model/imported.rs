@@ -0,0 +1,68 @@
+
+use obj::Obj;
+
+use crate::{prelude::*, formats::wavefront};
+use super::{Material, Transform};
+
+/// mesh read from another format
+#[derive(Debug, Clone)]
+pub struct ImportedModel {
+    name: String,
+    obj: Obj,
+    material: Material,
+    transform: Transform,
+}
+
+impl ImportedModel {
+    /// material is taken from the first resolved `usemtl` of the data, if any
+    pub fn new(name: String, obj: Obj) -> Self {
+        let material = Material::from_obj(&obj.data).unwrap_or_default();
+
+        Self { name, obj, material, transform: Transform::default() }
+    }
+
+    /// split a loaded file into one model per object/group
+    pub fn split(obj: Obj) -> Vec<Self> {
+        wavefront::split(&obj.data)
+            .into_iter()
+            .map(|(name, data)| Self::new(name, Obj { data, path: obj.path.clone() }))
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn obj(&self) -> &Obj {
+        &self.obj
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+}
+
+impl super::ModelEntityObject for ImportedModel {
+    fn vertices(&self) -> Vec<Vertex> {
+        let vertices = wavefront::load(&self.obj);
+        if self.transform.is_identity() {
+            return vertices;
+        }
+
+        vertices.iter()
+            .map(|v| self.transform.vertex(v))
+            .collect()
+    }
+}
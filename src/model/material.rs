@@ -0,0 +1,106 @@
+
+/// surface colors used by the shader
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub opacity: f32,
+}
+
+impl Material {
+    /// first material assigned with `usemtl` that was resolved from a loaded `.mtl` file
+    pub fn from_obj(data: &obj::ObjData) -> Option<Self> {
+        data.objects.iter()
+            .flat_map(|o| o.groups.iter())
+            .find_map(|g| match &g.material {
+                Some(obj::ObjMaterial::Mtl(mtl)) => Some(Self::from(mtl.as_ref())),
+                _ => None
+            })
+    }
+}
+
+impl Material {
+    /// color for entity outlines
+    pub fn outline() -> Self {
+        Self { diffuse: [0.1, 0.1, 0.1], ..Default::default() }
+    }
+
+    /// color for selected geometry
+    pub fn highlight() -> Self {
+        Self { diffuse: [0.15, 0.45, 0.95], ..Default::default() }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            ambient: [1.0, 1.0, 1.0],
+            diffuse: [0.94, 0.68, 0.28],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 1.0,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl From<&obj::Material> for Material {
+    fn from(value: &obj::Material) -> Self {
+        let default = Self::default();
+
+        // `Tr` is transparency, the inverse of `d`
+        let opacity = value.d
+            .or(value.tr.map(|tr| 1.0 - tr))
+            .unwrap_or(default.opacity);
+
+        Self {
+            ambient: value.ka.unwrap_or(default.ambient),
+            diffuse: value.kd.unwrap_or(default.diffuse),
+            specular: value.ks.unwrap_or(default.specular),
+            shininess: value.ns.unwrap_or(default.shininess).max(1.0),
+            opacity: opacity.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_from_mtl() {
+        let text = "mtllib twocubes_blender.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\ng tri\nusemtl Material\nf 1 2 3\n";
+        let mut obj = obj::Obj {
+            data: obj::ObjData::load_buf(text.as_bytes()).unwrap(),
+            path: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("models"),
+        };
+        obj.load_mtls().unwrap();
+
+        let material = Material::from_obj(&obj.data).unwrap();
+        assert_eq!(material.ambient, [1.0, 1.0, 1.0]);
+        assert_eq!(material.diffuse, [0.8, 0.8, 0.8]);
+        assert_eq!(material.specular, [0.5, 0.5, 0.5]);
+        assert_eq!(material.shininess, 250.0);
+        assert_eq!(material.opacity, 1.0);
+    }
+
+    #[test]
+    fn unresolved_material_is_none() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl Missing\nf 1 2 3\n";
+        let data = obj::ObjData::load_buf(text.as_bytes()).unwrap();
+
+        assert_eq!(Material::from_obj(&data), None);
+    }
+
+    #[test]
+    fn transparency_is_inverse_opacity() {
+        let mut mtl = obj::Material::new("glass".into());
+        mtl.tr = Some(0.25);
+        assert_eq!(Material::from(&mtl).opacity, 0.75);
+
+        // `d` wins when both are given
+        mtl.d = Some(0.5);
+        assert_eq!(Material::from(&mtl).opacity, 0.5);
+    }
+}
@@ -1,21 +1,56 @@
 
+#[allow(clippy::module_inception)]
 mod model;
 mod entity;
+mod history;
 
 mod block;
+mod brep;
+mod combine;
+mod constraint;
+mod extrude;
+mod fillet;
+mod hole;
+mod imported;
+mod material;
+mod pattern;
 mod plane;
+mod profile;
+mod revolve;
 mod sketch;
+mod transform;
 
 pub use model::Model;
-pub use entity::ModelEntity;
-pub use block::Block;
+pub use entity::{ModelEntity, bodies, regenerate};
+pub use history::MAX_HISTORY;
+pub use block::{Anchor, Block, Orientation};
+pub use brep::{Brep, Coedge, Curve, Edge, Face, Shell, Surface};
+pub use combine::{Combine, Operation};
+pub use constraint::{Constraint, Status};
+pub use extrude::{Extent, Extrude};
+pub use fillet::{Chamfer, ChamferSize, Fillet, blend_edges};
+pub use hole::{Hole, HoleKind};
+#[cfg(test)]
+pub use hole::SEGMENTS as HOLE_SEGMENTS;
+pub use imported::ImportedModel;
+pub use material::Material;
+pub use pattern::{Direction, Pattern, PatternKind};
 pub use plane::Plane;
-pub use sketch::Sketch;
+pub use profile::Profile;
+pub use revolve::{Axis, Revolve};
+pub use sketch::{Shape, Sketch, SketchElement};
+pub use transform::Transform;
 
 
 use crate::prelude::*;
 
 pub trait ModelEntityObject {
+    /// triangle list of the entity
     fn vertices(&self) -> Vec<Vertex>;
+
+    /// line list of the entity outline
+    fn edges(&self) -> Vec<Vertex> {
+        Vec::new()
+    }
 }
 
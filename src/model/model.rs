@@ -1,23 +1,30 @@
 
-use std::{
-    fs::File,
-    path::PathBuf
-};
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
 
 use glium::{
     Display,
     glutin::surface::WindowSurface
 };
 
-use obj::{Obj, ObjData};
-use crate::prelude::VertexBuffer;
+use obj::Obj;
+use crate::formats::{stl, wavefront, part::{self, PartError}};
+use crate::prelude::{Instance, InstanceBuffer, VertexBuffer};
 use super::*;
+use super::entity::{bodies_except, is_instanced};
+use super::history::{Edit, History};
 
 
 #[derive(Debug, Default)]
 pub struct Model {
     geometry: Vec<ModelEntity>,
-    vb: Option<VertexBuffer>
+    hidden: Vec<bool>,
+    buffers: Option<Vec<EntityBuffer>>,
+    /// flat faces of each entity, worked out when first asked for
+    faces: Vec<OnceCell<Vec<Plane>>>,
+    /// edges of each entity that can be filleted or chamfered, worked out when first asked for
+    blend_edges: Vec<OnceCell<Vec<(usize, Point3d)>>>,
+    history: History,
 }
 
 impl Model {
@@ -31,9 +38,148 @@ impl Model {
         // Self::default()
     }
 
+    /// apply an edit and record it for undo
+    fn edit(&mut self, edit: Edit) {
+        let inverse = edit.apply(&mut self.geometry, &mut self.hidden);
+        self.history.record(inverse);
+
+        self.refresh();
+    }
+
+    /// bring features up to date after the history changed, and drop what was worked out from it
+    fn refresh(&mut self) {
+        regenerate(&mut self.geometry);
+        self.buffers = None;
+        self.faces = self.geometry.iter().map(|_| OnceCell::new()).collect();
+        self.blend_edges = self.geometry.iter().map(|_| OnceCell::new()).collect();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(edit) = self.history.undo() {
+            let inverse = edit.apply(&mut self.geometry, &mut self.hidden);
+            self.history.undone(inverse);
+
+            self.refresh();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(edit) = self.history.redo() {
+            let inverse = edit.apply(&mut self.geometry, &mut self.hidden);
+            self.history.redone(inverse);
+
+            self.refresh();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
     pub fn push(&mut self, entity: ModelEntity) {
-        self.geometry.push(entity);
-        self.vb = None;
+        self.edit(Edit::Insert { index: self.geometry.len(), entity, hidden: false });
+    }
+
+    /// remove every entity, as an undoable edit
+    pub fn clear(&mut self) {
+        self.edit(Edit::Reset { geometry: Vec::new(), hidden: Vec::new() });
+    }
+
+    pub fn entity(&self, index: usize) -> &ModelEntity {
+        &self.geometry[index]
+    }
+
+    /// planes of the flat faces of the entity at `index`, which dialogs can ask for every frame
+    pub fn faces(&self, index: usize) -> &[Plane] {
+        self.faces[index].get_or_init(|| self.geometry[index].faces())
+    }
+
+    /// [`blend_edges`] of the entity at `index`, which dialogs can ask for every frame
+    pub fn blend_edges(&self, index: usize) -> &[(usize, Point3d)] {
+        self.blend_edges[index].get_or_init(|| blend_edges(&self.geometry[index]))
+    }
+
+    /// why `hole` cannot be made at history `index`, measured against the solids before it
+    pub fn hole_error(&self, index: usize, hole: &Hole) -> Option<&'static str> {
+        let mut hole = hole.clone();
+        hole.resolve(&self.geometry[..index.min(self.geometry.len())]);
+
+        hole.error()
+    }
+
+    /// swap in an edited entity, regenerating buffers on the next draw
+    pub fn replace(&mut self, index: usize, entity: ModelEntity) {
+        self.edit(Edit::Replace { index, entity });
+    }
+
+    /// remove an entity along with every feature built from it, as a single edit
+    pub fn remove(&mut self, index: usize) {
+        let mut removed = vec![index];
+        for (i, entity) in self.geometry.iter().enumerate().skip(index + 1) {
+            if entity.references().iter().any(|r| removed.contains(r)) {
+                removed.push(i);
+            }
+        }
+
+        if removed.len() > 1 {
+            log::info!("Removing {} features built from entity {}", removed.len() - 1, index + 1);
+        }
+
+        // later references shift down past the removed entities
+        let map = |r: usize| r - removed.iter().filter(|&&i| i < r).count();
+        let mut edits: Vec<Edit> = self.geometry.iter()
+            .enumerate()
+            .filter(|(i, _)| !removed.contains(i))
+            .filter_map(|(i, e)| e.remapped(map).map(|entity| Edit::Replace { index: i, entity }))
+            .collect();
+        edits.extend(removed.iter().rev().map(|&i| Edit::Remove { index: i }));
+
+        self.edit(Edit::Batch(edits));
+    }
+
+    /// swap an entity with the one before it in the history
+    ///
+    /// Features cannot move above the entities they are built from.
+    pub fn move_up(&mut self, index: usize) {
+        if index == 0 || index >= self.geometry.len() {
+            return;
+        }
+
+        if self.geometry[index].references().contains(&(index - 1)) {
+            log::warn!("{} is built from {} and cannot move above it", self.geometry[index], self.geometry[index - 1]);
+            return;
+        }
+
+        let map = |r: usize| match r {
+            r if r == index => index - 1,
+            r if r == index - 1 => index,
+            r => r,
+        };
+        let mut edits = vec![Edit::MoveUp { index }];
+        edits.extend(self.geometry.iter()
+            .enumerate()
+            .skip(index + 1)
+            .filter_map(|(i, e)| e.remapped(map).map(|entity| Edit::Replace { index: i, entity })));
+
+        self.edit(Edit::Batch(edits));
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        !self.hidden[index]
+    }
+
+    /// show or hide an entity
+    ///
+    /// Hiding only leaves the entity out of the view and the exports. A hidden cut or hole
+    /// still takes material away, as there is no suppression of features.
+    pub fn set_visible(&mut self, index: usize, visible: bool) {
+        if self.hidden[index] == visible {
+            self.edit(Edit::SetHidden { index, hidden: !visible });
+        }
     }
 
     pub fn entities(&self) -> Vec<String> {
@@ -42,61 +188,308 @@ impl Model {
             .collect()
     }
 
-    pub fn save(&mut self, path: &PathBuf) -> Result<(), obj::ObjError> {
-        match &self.geometry[0] {
-            ModelEntity::ImportedModel(obj) => obj.save(path),
-            _ => todo!()
-        }
+    /// save all entities as a `.ph` part
+    pub fn save(&self, path: &Path) -> Result<(), PartError> {
+        part::save(&self.geometry, path)
     }
 
-    pub fn load(&mut self, path: PathBuf) -> Result<(), obj::ObjError> {
-        let file = File::open(&path)?;
-        self.geometry = vec![ModelEntity::ImportedModel(Obj { data: ObjData::load_buf(file)?, path })];
+    /// replace the model with the entities of a `.ph` part
+    pub fn load(&mut self, path: PathBuf) -> Result<(), PartError> {
+        let geometry = part::load(&path)?;
+        let hidden = vec![false; geometry.len()];
 
-        // invalidate cached vertex buffer
-        self.vb = None;
+        // opening a part can be undone like any other edit
+        self.edit(Edit::Reset { geometry, hidden });
 
         Ok(())
     }
 
+    /// import a `.obj` file, adding an entity for each of its objects
     pub fn load_obj(&mut self, path: &PathBuf) -> Result<(), obj::ObjError> {
         if let Some(pathstr) = path.to_str() {
             log::debug!("loading .obj file `{}`", pathstr);
         }
 
-        let loaded = Obj::load(path)?;
-        self.geometry = vec![ModelEntity::ImportedModel(loaded)];
+        let mut loaded = Obj::load(path)?;
+        if let Err(e) = loaded.load_mtls() {
+            // models still load without their materials
+            log::warn!("Failed to load material libraries <{}>", e);
+        }
 
-        // invalidate cached vertex buffer
-        self.vb = None;
+        // all objects of the file are undone together
+        let start = self.geometry.len();
+        let inserts = ImportedModel::split(loaded)
+            .into_iter()
+            .enumerate()
+            .map(|(i, model)| Edit::Insert { index: start + i, entity: ModelEntity::ImportedModel(model), hidden: false })
+            .collect();
+        self.edit(Edit::Batch(inserts));
 
         Ok(())
     }
 
-    pub fn vertex_buffer(&mut self, display: &Display<WindowSurface>) -> &VertexBuffer {
-        if let None = self.vb {
-            self.vb = match self.geometry.len() {
-                0 => Some(crate::prelude::buffer::empty_buffer(display)),
+    /// import a `.stl` file as a new entity
+    pub fn load_stl(&mut self, path: &Path) -> std::io::Result<()> {
+        if let Some(pathstr) = path.to_str() {
+            log::debug!("loading .stl file `{}`", pathstr);
+        }
 
-                _ => {
-                    // should we have <ModelEntity>.vertices take in a vec?
-                    //  Might be performance overhead from the flattening which probably copies items
-                    let vertices: Vec<Vertex> = self.geometry.iter()
-                        .flat_map(|g| g.vertices())
-                        .collect();
+        let solid = stl::load(path)?;
+        let obj = Obj {
+            data: solid.to_obj_data(),
+            path: path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        self.push(ModelEntity::ImportedModel(ImportedModel::new(solid.name, obj)));
 
-                    match VertexBuffer::new(display, &vertices) {
-                        Ok(buffer) => Some( buffer ),
+        Ok(())
+    }
+
+    /// export every visible entity as its own `.obj` object
+    pub fn export_obj(&self, path: &Path) -> std::io::Result<()> {
+        wavefront::save(&self.geometry, &self.hidden, path)
+    }
+
+    /// export the visible solids as binary `.stl`
+    pub fn export_stl(&self, path: &Path) -> std::io::Result<()> {
+        stl::save(path, &self.vertices())
+    }
+
+    /// triangle list of every visible solid, after cuts and holes
+    pub fn vertices(&self) -> Vec<Vertex> {
+        bodies(&self.geometry).into_iter()
+            .zip(self.geometry.iter().zip(self.hidden.iter()))
+            .filter(|(_, (g, hidden))| g.is_solid() && !**hidden)
+            .flat_map(|((vertices, _), _)| vertices)
+            .collect()
+    }
+
+    /// line list of every visible entity's outline
+    pub fn edges(&self) -> Vec<Vertex> {
+        bodies(&self.geometry).into_iter()
+            .zip(self.hidden.iter())
+            .filter(|(_, hidden)| !**hidden)
+            .flat_map(|((_, edges), _)| edges)
+            .collect()
+    }
+
+    /// one buffer per visible entity, so that each can be drawn with its own material
+    pub fn buffers(&mut self, display: &Display<WindowSurface>) -> &[EntityBuffer] {
+        if self.buffers.is_none() {
+            // patterns that are drawn as instances are left out of the bodies, so their copies are never built
+            let instanced: Vec<bool> = (0..self.geometry.len()).map(|i| is_instanced(&self.geometry, i)).collect();
+
+            let buffers = self.geometry.iter()
+                .zip(bodies_except(&self.geometry, |i| instanced[i]))
+                .zip(instanced.iter().zip(self.hidden.iter()))
+                .filter(|(_, (_, hidden))| !**hidden)
+                .filter_map(|((g, (vertices, edges)), (instanced, _))| {
+                    let buffer = |data: &[Vertex]| match VertexBuffer::new(display, data) {
+                        Ok(buffer) => buffer,
                         Err(e) => {
                             log::error!("Failed to produce VertexBuffer <{}>", e);
-                            Some(crate::prelude::buffer::empty_buffer(display))
+                            crate::prelude::buffer::empty_buffer(display)
+                        }
+                    };
+
+                    // copies that no cut changed are drawn as instances of a single one
+                    let original = match g {
+                        ModelEntity::Pattern(pattern) if *instanced => pattern.original().map(|o| (o, pattern.instances())),
+                        _ => None,
+                    };
+                    let (vertices, edges, instances) = match original {
+                        Some((original, instances)) => (original.vertices(), original.edges(), instances),
+                        None => (vertices, edges, vec![Instance::default()]),
+                    };
+                    let instances = match InstanceBuffer::new(display, &instances) {
+                        Ok(instances) => instances,
+                        Err(e) => {
+                            log::error!("Failed to produce InstanceBuffer <{}>", e);
+                            return None;
                         }
-                    }
-                }
-            };
+                    };
+
+                    Some(EntityBuffer {
+                        vertices: buffer(&vertices),
+                        edges: buffer(&edges),
+                        instances,
+                        material: g.material()
+                    })
+                })
+                .collect();
+
+            self.buffers = Some(buffers);
         }
 
         // previous lines ensure this will not panic
-        self.vb.as_ref().unwrap()
+        self.buffers.as_ref().unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub struct EntityBuffer {
+    /// rendered as `TrianglesList`
+    pub vertices: VertexBuffer,
+    /// rendered as `LinesList`
+    pub edges: VertexBuffer,
+    /// placements the vertices and edges are drawn at, once each
+    pub instances: InstanceBuffer,
+    pub material: Material,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("models").join(name)
+    }
+
+    #[test]
+    fn import_appends_entities() {
+        let mut model = Model::new();
+        model.push(ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0))));
+
+        model.load_obj(&fixture("twocubes_blender.obj")).unwrap();
+        model.load_obj(&fixture("cube.obj")).unwrap();
+
+        assert_eq!(model.entities(), ["Block", "CubeA_Mesh", "CubeB_Mesh", "cube"]);
+    }
+
+    #[test]
+    fn history_edits_keep_visibility() {
+        let mut model = Model::new();
+        model.load_obj(&fixture("twocubes_blender.obj")).unwrap();
+        model.set_visible(1, false);
+
+        model.move_up(1);
+        assert_eq!(model.entities(), ["CubeB_Mesh", "CubeA_Mesh"]);
+        assert!(!model.is_visible(0));
+
+        model.remove(0);
+        assert_eq!(model.entities(), ["CubeA_Mesh"]);
+        assert!(model.is_visible(0));
+    }
+
+    /// names, visibility and tessellation of the whole history
+    fn snapshot(model: &Model) -> (Vec<String>, Vec<bool>, Vec<Vertex>) {
+        let visible = (0..model.entities().len()).map(|i| model.is_visible(i)).collect();
+
+        (model.entities(), visible, model.vertices())
+    }
+
+    fn block(size: f32) -> ModelEntity {
+        ModelEntity::Block(Block::origin_and_max(Point3d::new(size, size, size)))
+    }
+
+    #[test]
+    fn undo_restores_prior_geometry() {
+        let mut model = Model::new();
+        let mut snapshots = vec![snapshot(&model)];
+
+        model.push(block(1.0));
+        snapshots.push(snapshot(&model));
+
+        model.load_obj(&fixture("twocubes_blender.obj")).unwrap();
+        snapshots.push(snapshot(&model));
+
+        model.set_visible(1, false);
+        snapshots.push(snapshot(&model));
+
+        model.replace(0, block(2.0));
+        snapshots.push(snapshot(&model));
+
+        model.move_up(2);
+        snapshots.push(snapshot(&model));
+
+        model.remove(1);
+        snapshots.push(snapshot(&model));
+
+        model.clear();
+        snapshots.push(snapshot(&model));
+
+        for expected in snapshots.iter().rev().skip(1) {
+            model.undo();
+            assert_eq!(&snapshot(&model), expected);
+        }
+        assert!(!model.can_undo());
+
+        for expected in snapshots.iter().skip(1) {
+            model.redo();
+            assert_eq!(&snapshot(&model), expected);
+        }
+        assert!(!model.can_redo());
+    }
+
+    #[test]
+    fn only_visible_solids_are_exported() {
+        let mut model = Model::new();
+        model.push(block(1.0));
+        model.push(block(2.0));
+        model.push(ModelEntity::Sketch(Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 0.0)])));
+        model.set_visible(1, false);
+
+        assert_eq!(model.vertices(), block(1.0).vertices());
+    }
+
+    #[test]
+    fn import_is_a_single_edit() {
+        let mut model = Model::new();
+        model.load_obj(&fixture("twocubes_blender.obj")).unwrap();
+        assert_eq!(model.entities().len(), 2);
+
+        model.undo();
+        assert!(model.entities().is_empty());
+        assert!(!model.can_undo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut model = Model::new();
+        model.push(block(1.0));
+        model.undo();
+        assert!(model.can_redo());
+
+        model.push(block(2.0));
+        assert!(!model.can_redo());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut model = Model::new();
+        for i in 0..MAX_HISTORY + 5 {
+            model.push(block(i as f32 + 1.0));
+        }
+
+        while model.can_undo() {
+            model.undo();
+        }
+
+        // the oldest edits can no longer be undone
+        assert_eq!(model.entities().len(), 5);
+    }
+
+    #[test]
+    fn features_follow_their_parents() {
+        let mut model = Model::new();
+        model.push(block(1.0));
+        model.push(ModelEntity::Sketch(Sketch::polyline(Plane::xy(), Vec::new())));
+        model.push(block(2.0));
+        model.push(ModelEntity::Extrude(Extrude::new(1, Extent::Blind(1.0))));
+
+        // the block can move above the sketch, but the extrusion cannot
+        model.move_up(2);
+        assert_eq!(model.entities(), ["Block", "Block", "Sketch", "Extrude"]);
+        assert!(matches!(model.entity(3), ModelEntity::Extrude(e) if e.sketch() == 2));
+        model.move_up(3);
+        assert_eq!(model.entities(), ["Block", "Block", "Sketch", "Extrude"]);
+
+        // removing the sketch removes the extrusion too, in one edit
+        model.remove(2);
+        assert_eq!(model.entities(), ["Block", "Block"]);
+        model.undo();
+        assert_eq!(model.entities(), ["Block", "Block", "Sketch", "Extrude"]);
+
+        model.remove(0);
+        assert!(matches!(model.entity(2), ModelEntity::Extrude(e) if e.sketch() == 1));
     }
 }
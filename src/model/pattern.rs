@@ -0,0 +1,259 @@
+//! repeated copies of an entity
+//!
+//! Patterns refer to an earlier entity or feature by history index and place copies of it
+//! along one or two directions, or around an axis. Copies of solids are drawn as instances
+//! of one buffer, copies of cuts and holes take material away like the original does.
+
+use crate::prelude::*;
+use super::ModelEntity;
+use super::brep::turn;
+
+/// one direction of a linear pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Direction {
+    pub direction: Point3d,
+    /// number of instances along the direction, the original included
+    pub count: usize,
+    pub spacing: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternKind {
+    /// instances along one direction, or on a grid along two
+    Linear { first: Direction, second: Option<Direction> },
+
+    /// instances turned about the axis through `origin`
+    ///
+    /// `angle` in degrees is between the first and the last instance, unless it is a full
+    /// turn, which the instances are spread evenly around.
+    Circular { origin: Point3d, axis: Point3d, count: usize, angle: f32 },
+}
+
+/// copies of an earlier entity
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    entity: usize,
+    kind: PatternKind,
+    /// instance numbers that are left out, where 0 is the original
+    skip: Vec<usize>,
+
+    /// entity that is copied, as of the last regeneration
+    original: Option<Box<ModelEntity>>,
+}
+
+impl Pattern {
+    pub fn new(entity: usize, kind: PatternKind, skip: Vec<usize>) -> Self {
+        Self { entity, kind, skip, original: None }
+    }
+
+    /// history index of the entity that is copied
+    pub fn entity(&self) -> usize {
+        self.entity
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.kind
+    }
+
+    pub fn skip(&self) -> &[usize] {
+        &self.skip
+    }
+
+    /// number of instances, the original and skipped ones included
+    ///
+    /// Linear instances are numbered along the first direction, then the second.
+    pub fn count(&self) -> usize {
+        match self.kind {
+            PatternKind::Linear { first, second } => first.count * second.map_or(1, |s| s.count),
+            PatternKind::Circular { count, .. } => count,
+        }
+    }
+
+    /// entity that is copied, or `None` before regeneration or if it does not exist
+    pub fn original(&self) -> Option<&ModelEntity> {
+        self.original.as_deref()
+    }
+
+    /// placements of the copies, without the original and the skipped instances
+    pub fn instances(&self) -> Vec<Instance> {
+        (1..self.count())
+            .filter(|n| !self.skip.contains(n))
+            .map(|n| self.instance(n))
+            .collect()
+    }
+
+    /// placement of instance number `n`
+    fn instance(&self, n: usize) -> Instance {
+        match self.kind {
+            PatternKind::Linear { first, second } => {
+                let step = |d: Direction, i: usize| d.direction.normalize() * (d.spacing * i as f32);
+                let mut offset = step(first, n % first.count.max(1));
+                if let Some(second) = second {
+                    offset = offset + step(second, n / first.count.max(1));
+                }
+
+                placement(Point3d::default(), Point3d::new(0.0, 0.0, 1.0), 0.0, offset)
+            },
+            PatternKind::Circular { origin, axis, count, angle } => {
+                let step = match (angle.abs() - 360.0).abs() < 1e-3 {
+                    true => angle / count.max(1) as f32,
+                    false => angle / count.saturating_sub(1).max(1) as f32,
+                };
+
+                placement(origin, axis.normalize(), (step * n as f32).to_radians(), Point3d::default())
+            },
+        }
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.entity]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.entity = map(self.entity);
+    }
+
+    /// take a copy of the patterned entity
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        self.original = earlier.get(self.entity).cloned().map(Box::new);
+        if self.original.is_none() {
+            log::warn!("Pattern refers to entity {}, which does not come before it", self.entity + 1);
+        }
+    }
+
+    /// every vertex of `data` at each copy
+    fn copies(&self, data: Vec<Vertex>) -> Vec<Vertex> {
+        self.instances().iter()
+            .flat_map(|instance| data.iter().map(|v| instance.vertex(v)))
+            .collect()
+    }
+}
+
+/// turned by `angle` radians about the unit `axis` through `origin`, then moved by `offset`
+fn placement(origin: Point3d, axis: Point3d, angle: f32, offset: Point3d) -> Instance {
+    let columns = [Point3d::new(1.0, 0.0, 0.0), Point3d::new(0.0, 1.0, 0.0), Point3d::new(0.0, 0.0, 1.0)]
+        .map(|e| turn(e, axis, angle));
+    let translation = origin - turn(origin, axis, angle) + offset;
+
+    let column = |p: Point3d, w: f32| [p.x, p.y, p.z, w];
+    Instance {
+        transform: [column(columns[0], 0.0), column(columns[1], 0.0), column(columns[2], 0.0), column(translation, 1.0)],
+    }
+}
+
+impl super::ModelEntityObject for Pattern {
+    fn vertices(&self) -> Vec<Vertex> {
+        self.copies(self.original().map(|e| e.vertices()).unwrap_or_default())
+    }
+
+    fn edges(&self) -> Vec<Vertex> {
+        self.copies(self.original().map(|e| e.edges()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Block, bodies, regenerate};
+    use crate::prelude::fixture::{drill_block, drilled_hole, drilled_volume};
+    use crate::model::entity::{bodies_except, is_instanced};
+
+    fn assert_near(a: Point3d, b: Point3d) {
+        assert!((a - b).length() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn origin(instance: &Instance) -> Point3d {
+        instance.vertex(&Vertex::default()).position.into()
+    }
+
+    fn linear(count: usize, second: Option<Direction>, skip: Vec<usize>) -> Pattern {
+        let first = Direction { direction: Point3d::new(2.0, 0.0, 0.0), count, spacing: 3.0 };
+
+        Pattern::new(0, PatternKind::Linear { first, second }, skip)
+    }
+
+    #[test]
+    fn linear_grid_with_skipped_instances() {
+        let second = Direction { direction: Point3d::new(0.0, 1.0, 0.0), count: 2, spacing: 5.0 };
+        let pattern = linear(3, Some(second), vec![4]);
+        let instances = pattern.instances();
+
+        assert_eq!(pattern.count(), 6);
+        assert_eq!(instances.len(), 4);
+        for (instance, expected) in instances.iter().zip([(3.0, 0.0), (6.0, 0.0), (0.0, 5.0), (6.0, 5.0)]) {
+            assert_near(origin(instance), Point3d::new(expected.0, expected.1, 0.0));
+        }
+    }
+
+    #[test]
+    fn circular_spacing() {
+        let circular = |angle: f32| Pattern::new(0, PatternKind::Circular {
+            origin: Point3d::new(1.0, 0.0, 0.0),
+            axis: Point3d::new(0.0, 0.0, 2.0),
+            count: 4,
+            angle,
+        }, Vec::new());
+
+        // a full turn spreads the instances evenly, otherwise the last one is at the angle
+        let full = circular(360.0).instances();
+        assert_near(origin(&full[0]), Point3d::new(1.0, -1.0, 0.0));
+        assert_near(origin(&full[1]), Point3d::new(2.0, 0.0, 0.0));
+
+        let part = circular(90.0).instances();
+        assert_near(origin(&part[2]), Point3d::new(1.0, -1.0, 0.0));
+
+        // normals turn with the copies
+        let normal = full[0].vertex(&Vertex { normal: [1.0, 0.0, 0.0], ..Default::default() }).normal;
+        assert_near(normal.into(), Point3d::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn copies_of_holes_and_solids() {
+        // three holes in a row, with the middle one left out
+        let mut entities = vec![
+            drill_block(),
+            drilled_hole(0),
+            ModelEntity::Pattern(Pattern::new(1, PatternKind::Linear {
+                first: Direction { direction: Point3d::new(0.0, 1.0, 0.0), count: 3, spacing: 2.5 },
+                second: None,
+            }, vec![1])),
+        ];
+        regenerate(&mut entities);
+
+        assert!(entities[2].is_subtractive());
+        assert_eq!(entities[2].vertices().len(), entities[1].vertices().len());
+
+        let bodies = bodies(&entities);
+        assert!((volume(&bodies[0].0) - (500.0 - 2.0 * drilled_volume())).abs() < 1e-2);
+        assert!(bodies[2].0.is_empty());
+
+        // copies of a solid are solid
+        let mut entities = vec![
+            ModelEntity::Block(Block::origin_and_max(Point3d::new(1.0, 1.0, 1.0))),
+            ModelEntity::Pattern(linear(3, None, Vec::new())),
+        ];
+        regenerate(&mut entities);
+
+        assert!(entities[1].is_solid());
+        assert!((volume(&entities[1].vertices()) - 2.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn uncut_copies_of_solids_are_instanced() {
+        let mut entities = vec![drill_block(), ModelEntity::Pattern(linear(3, None, Vec::new()))];
+        regenerate(&mut entities);
+
+        // the copies are not built when they are drawn as instances
+        assert!(is_instanced(&entities, 1));
+        assert!(!is_instanced(&entities, 0));
+        assert!(bodies_except(&entities, |i| i == 1)[1].0.is_empty());
+
+        // a later hole changes the copies it goes through, which are then built one by one
+        entities.push(drilled_hole(0));
+        regenerate(&mut entities);
+
+        assert!(!is_instanced(&entities, 1));
+    }
+}
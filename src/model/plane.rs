@@ -1,46 +1,193 @@
 
-use std::ops::{Deref, DerefMut};
-
 use crate::prelude::Vertex;
 use super::{Point2d, Point3d};
 
 
-#[derive(Debug, Default)]
-pub struct Plane(Point3d);
+/// work plane with its own 2d coordinate system
+///
+/// The axes are orthonormal and right handed, so `x_axis × y_axis = normal`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plane {
+    origin: Point3d,
+    normal: Point3d,
+    x_axis: Point3d,
+    y_axis: Point3d,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Self::xy()
+    }
+}
 
 impl Plane {
-    /// create a 3d point depending on the normal direction of the plane
+    /// plane through `origin`, with the X axis picked from the global axes
+    ///
+    /// The X axis follows the global axis after the one the normal is closest to,
+    /// so that the standard planes get the same axes as [`Plane::xy`], [`Plane::yz`] and [`Plane::zx`].
+    pub fn new(origin: Point3d, normal: Point3d) -> Self {
+        let n = normal.normalize();
+        let (ax, ay, az) = (n.x.abs(), n.y.abs(), n.z.abs());
+
+        let seed = if ax >= ay && ax >= az {
+            Point3d::new(0.0, 1.0, 0.0)
+        } else if ay >= az {
+            Point3d::new(0.0, 0.0, 1.0)
+        } else {
+            Point3d::new(1.0, 0.0, 0.0)
+        };
+
+        Self::with_axis(origin, normal, seed)
+    }
+
+    /// plane through `origin` whose X axis is `x_axis` projected onto the plane
+    pub fn with_axis(origin: Point3d, normal: Point3d, x_axis: Point3d) -> Self {
+        let normal = normal.normalize();
+        let x_axis = (x_axis - normal * x_axis.dot(&normal)).normalize();
+        let y_axis = normal.cross(&x_axis);
+
+        Self { origin, normal, x_axis, y_axis }
+    }
+
+    /// plane from axes that are already orthonormal, such as those saved in a part file
+    pub(crate) fn from_frame(origin: Point3d, normal: Point3d, x_axis: Point3d, y_axis: Point3d) -> Self {
+        Self { origin, normal, x_axis, y_axis }
+    }
+
+    /// global XY plane, sketch X along global X
+    pub fn xy() -> Self {
+        Self::new(Point3d::default(), Point3d::new(0.0, 0.0, 1.0))
+    }
+
+    /// global YZ plane, sketch X along global Y
+    pub fn yz() -> Self {
+        Self::new(Point3d::default(), Point3d::new(1.0, 0.0, 0.0))
+    }
+
+    /// global ZX plane, sketch X along global Z
+    pub fn zx() -> Self {
+        Self::new(Point3d::default(), Point3d::new(0.0, 1.0, 0.0))
+    }
+
+    /// same orientation, moved to `origin`
+    pub fn at(&self, origin: Point3d) -> Self {
+        Self { origin, ..self.clone() }
+    }
+
+    pub fn origin(&self) -> Point3d {
+        self.origin
+    }
+
+    pub fn normal(&self) -> Point3d {
+        self.normal
+    }
+
+    pub fn x_axis(&self) -> Point3d {
+        self.x_axis
+    }
+
+    pub fn y_axis(&self) -> Point3d {
+        self.y_axis
+    }
+
+    /// 3d position of a point given in plane coordinates
     pub fn point(&self, point: &Point2d) -> Point3d {
-        // point is assumed to be (x, y) cordinates local to the orientation of the plane
+        self.origin + self.x_axis * point.x + self.y_axis * point.y
+    }
+
+    /// plane coordinates of a 3d point, after projecting it onto the plane
+    pub fn local(&self, point: &Point3d) -> Point2d {
+        let d = *point - self.origin;
 
-        // depending on the orientation of the plane, (x, y) transorms into its part in (x, y, z)
-        // note that point.x might not be result.x
-        // i.e. the plane the global YZ plane, so (x, y) -> (0, x, y)
+        Point2d::new(d.dot(&self.x_axis), d.dot(&self.y_axis))
+    }
 
-        // TODO: implement and replace this placeholder
-        Point3d { x: point.x, y: point.y, z: self.z }
+    /// signed distance of a point from the plane, positive on the normal side
+    pub fn distance(&self, point: &Point3d) -> f32 {
+        (*point - self.origin).dot(&self.normal)
     }
 
     /// create [`Vertex`] from a given point on the plane
     pub fn vertex(&self, point: &Point2d) -> Vertex {
         Vertex {
             position: self.point(point).to_array(),
-            normal: self.to_array(),
+            normal: self.normal.to_array(),
             ..Default::default()
         }
     }
 }
 
-impl Deref for Plane {
-    type Target = Point3d;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    fn assert_near(a: Point3d, b: Point3d) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
     }
-}
 
-impl DerefMut for Plane {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+    fn assert_axes(plane: &Plane, x: [f32; 3], y: [f32; 3], n: [f32; 3]) {
+        assert_eq!(plane.x_axis().to_array(), x);
+        assert_eq!(plane.y_axis().to_array(), y);
+        assert_eq!(plane.normal().to_array(), n);
+    }
+
+    #[test]
+    fn xy_plane() {
+        let plane = Plane::xy().at(Point3d::new(0.0, 0.0, 2.0));
+        assert_axes(&plane, [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]);
+
+        let p = plane.point(&Point2d::new(3.0, 4.0));
+        assert_eq!(p, Point3d::new(3.0, 4.0, 2.0));
+        assert_eq!(plane.local(&p), Point2d::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn yz_plane() {
+        let plane = Plane::yz().at(Point3d::new(2.0, 0.0, 0.0));
+        assert_axes(&plane, [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]);
+
+        let p = plane.point(&Point2d::new(3.0, 4.0));
+        assert_eq!(p, Point3d::new(2.0, 3.0, 4.0));
+        assert_eq!(plane.local(&p), Point2d::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn zx_plane() {
+        let plane = Plane::zx().at(Point3d::new(0.0, 2.0, 0.0));
+        assert_axes(&plane, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+
+        let p = plane.point(&Point2d::new(3.0, 4.0));
+        assert_eq!(p, Point3d::new(4.0, 2.0, 3.0));
+        assert_eq!(plane.local(&p), Point2d::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn arbitrary_plane() {
+        let origin = Point3d::new(1.0, -2.0, 0.5);
+        let plane = Plane::new(origin, Point3d::new(1.0, 1.0, 1.0));
+
+        // axes are orthonormal and right handed
+        assert!((plane.x_axis().length() - 1.0).abs() < 1e-6);
+        assert!(plane.x_axis().dot(&plane.normal()).abs() < 1e-6);
+        assert_near(plane.x_axis().cross(&plane.y_axis()), plane.normal());
+
+        let p = plane.point(&Point2d::new(-1.5, 2.5));
+        assert!(plane.distance(&p).abs() < 1e-5);
+
+        let local = plane.local(&p);
+        assert!((local.x + 1.5).abs() < 1e-5 && (local.y - 2.5).abs() < 1e-5);
+
+        // points off the plane map onto their projection
+        let above = p + plane.normal() * 3.0;
+        assert!((plane.distance(&above) - 3.0).abs() < 1e-5);
+        assert_near(plane.point(&plane.local(&above)), p);
+    }
+
+    #[test]
+    fn given_axis_is_projected() {
+        let plane = Plane::with_axis(Point3d::default(), Point3d::new(0.0, 0.0, 2.0), Point3d::new(1.0, 1.0, 1.0));
+
+        assert_near(plane.x_axis(), Point3d::new(1.0, 1.0, 0.0).normalize());
+        assert_near(plane.y_axis(), Point3d::new(-1.0, 1.0, 0.0).normalize());
     }
 }
@@ -0,0 +1,275 @@
+
+//! closed regions of a sketch, which features turn into solids
+
+use crate::prelude::polygon;
+use super::{Point2d, Shape, Sketch};
+
+/// how far apart sketch points may be and still join two elements
+const JOIN_TOLERANCE: f32 = 1e-4;
+
+/// turn between consecutive loop segments, in radians, above which the loop has a corner
+pub(super) const CORNER_ANGLE: f32 = 0.25;
+
+/// region bounded by an outer loop, with loops cut out of it, in plane coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// counter-clockwise boundary
+    pub outer: Vec<Point2d>,
+    /// clockwise boundaries of the holes
+    pub holes: Vec<Vec<Point2d>>,
+}
+
+impl Profile {
+    /// outer boundary followed by the holes
+    pub fn loops(&self) -> impl Iterator<Item = &Vec<Point2d>> {
+        std::iter::once(&self.outer).chain(self.holes.iter())
+    }
+
+    /// triangles covering the region, indexing into the returned points
+    pub fn triangulate(&self) -> (Vec<Point2d>, Vec<[usize; 3]>) {
+        let outer: Vec<[f32; 2]> = self.outer.iter().map(Point2d::to_array).collect();
+        let holes: Vec<Vec<[f32; 2]>> = self.holes.iter()
+            .map(|h| h.iter().map(Point2d::to_array).collect())
+            .collect();
+        let (points, triangles) = polygon::triangulate_with_holes(&outer, &holes);
+
+        (points.into_iter().map(|[x, y]| Point2d::new(x, y)).collect(), triangles)
+    }
+}
+
+/// split a closed loop at its corners into runs of point indices
+///
+/// Each run ends where the next one starts. A loop without corners, such as a
+/// circle, is a single run from its first point back around to it.
+pub fn runs(points: &[Point2d]) -> Vec<Vec<usize>> {
+    let n = points.len();
+    let corners: Vec<usize> = (0..n)
+        .filter(|&i| {
+            let (before, after) = (points[i] - points[(i + n - 1) % n], points[(i + 1) % n] - points[i]);
+
+            before.cross(&after).atan2(before.dot(&after)).abs() > CORNER_ANGLE
+        })
+        .collect();
+
+    if corners.is_empty() {
+        return vec![(0..=n).map(|i| i % n).collect()];
+    }
+
+    corners.iter()
+        .enumerate()
+        .map(|(c, &start)| {
+            let end = corners[(c + 1) % corners.len()];
+            let mut run = vec![start];
+            let mut i = start;
+            loop {
+                i = (i + 1) % n;
+                run.push(i);
+                if i == end {
+                    break run;
+                }
+            }
+        })
+        .collect()
+}
+
+/// regions enclosed by the sketch, leaving out construction geometry
+///
+/// Loops inside an odd number of other loops are holes of the loop around them.
+/// Elements that branch or do not close a loop are ignored.
+pub fn profiles(sketch: &Sketch) -> Vec<Profile> {
+    let mut loops: Vec<Vec<Point2d>> = loops(sketch).into_iter()
+        .map(|mut l| {
+            if area(&l) < 0.0 {
+                l.reverse();
+            }
+            l
+        })
+        .filter(|l| area(l) > f32::EPSILON)
+        .collect();
+
+    // outer loops come before the holes inside them
+    loops.sort_by(|a, b| area(b).total_cmp(&area(a)));
+
+    let arrays: Vec<Vec<[f32; 2]>> = loops.iter().map(|l| l.iter().map(Point2d::to_array).collect()).collect();
+    let depth: Vec<usize> = (0..loops.len())
+        .map(|i| (0..i).filter(|&j| polygon::contains(&arrays[j], arrays[i][0])).count())
+        .collect();
+
+    let mut profiles: Vec<(usize, Profile)> = Vec::new();
+    for (i, l) in loops.into_iter().enumerate() {
+        match depth[i] % 2 {
+            0 => profiles.push((i, Profile { outer: l, holes: Vec::new() })),
+            _ => {
+                // the smallest loop around it, which is the last one found
+                let parent = profiles.iter_mut()
+                    .rev()
+                    .find(|(j, _)| polygon::contains(&arrays[*j], arrays[i][0]));
+
+                if let Some((_, parent)) = parent {
+                    parent.holes.push(l.into_iter().rev().collect());
+                }
+            }
+        }
+    }
+
+    profiles.into_iter().map(|(_, p)| p).collect()
+}
+
+fn area(points: &[Point2d]) -> f32 {
+    let arrays: Vec<[f32; 2]> = points.iter().map(Point2d::to_array).collect();
+
+    polygon::signed_area(&arrays)
+}
+
+/// closed polylines through the elements, without repeating the first point
+fn loops(sketch: &Sketch) -> Vec<Vec<Point2d>> {
+    struct Edge {
+        from: usize,
+        to: usize,
+        points: Vec<Point2d>,
+    }
+
+    let mut loops = Vec::new();
+    let mut nodes: Vec<Point2d> = Vec::new();
+    let mut node = |p: Point2d| match nodes.iter().position(|n| n.distance(&p) <= JOIN_TOLERANCE) {
+        Some(i) => i,
+        None => {
+            nodes.push(p);
+            nodes.len() - 1
+        }
+    };
+
+    let mut edges = Vec::new();
+    for element in sketch.elements().iter().filter(|e| !e.construction) {
+        let mut points = sketch.tessellate(element);
+
+        match element.shape {
+            Shape::Circle { .. } => {
+                points.pop();
+                loops.push(points);
+            },
+            Shape::Line { start, end } | Shape::Arc { start, end, .. } => {
+                let (from, to) = (node(sketch.point(start)), node(sketch.point(end)));
+                if from == to && matches!(element.shape, Shape::Line { .. }) {
+                    continue;
+                }
+
+                edges.push(Edge { from, to, points });
+            },
+        }
+    }
+
+    let degree = |n: usize| edges.iter().map(|e| (e.from == n) as usize + (e.to == n) as usize).sum::<usize>();
+
+    let mut used = vec![false; edges.len()];
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+
+        let start = edges[first].from;
+        let (mut at, mut current) = (start, first);
+        let mut chain = Vec::new();
+        loop {
+            used[current] = true;
+            let edge = &edges[current];
+
+            // walk the edge away from the node we are at, leaving off its last point
+            match edge.from == at {
+                true => {
+                    chain.extend_from_slice(&edge.points[..edge.points.len() - 1]);
+                    at = edge.to;
+                },
+                false => {
+                    chain.extend(edge.points[1..].iter().rev());
+                    at = edge.from;
+                },
+            }
+
+            if at == start {
+                loops.push(chain);
+                break;
+            }
+
+            if degree(at) != 2 {
+                break;
+            }
+
+            match (0..edges.len()).find(|&i| !used[i] && (edges[i].from == at || edges[i].to == at)) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+    }
+
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Plane;
+
+    fn p(x: f32, y: f32) -> Point2d {
+        Point2d::new(x, y)
+    }
+
+    /// closed polyline, with separate points at each corner as the rectangle tool makes them
+    fn rectangle(sketch: &mut Sketch, min: Point2d, max: Point2d) {
+        let corners = [min, p(max.x, min.y), max, p(min.x, max.y)];
+        for i in 0..4 {
+            let (start, end) = (sketch.add_point(corners[i]), sketch.add_point(corners[(i + 1) % 4]));
+            sketch.add(Shape::Line { start, end }, false);
+        }
+    }
+
+    #[test]
+    fn rectangle_with_hole() {
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        rectangle(&mut sketch, p(0.0, 0.0), p(4.0, 4.0));
+        let center = sketch.add_point(p(2.0, 2.0));
+        sketch.add(Shape::Circle { center, radius: 1.0 }, false);
+
+        let profiles = profiles(&sketch);
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].outer.len(), 4);
+        assert_eq!(profiles[0].holes.len(), 1);
+
+        assert!(area(&profiles[0].outer) > 0.0);
+        assert!(area(&profiles[0].holes[0]) < 0.0);
+    }
+
+    #[test]
+    fn island_in_hole_is_its_own_profile() {
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        rectangle(&mut sketch, p(0.0, 0.0), p(10.0, 10.0));
+        rectangle(&mut sketch, p(2.0, 2.0), p(8.0, 8.0));
+        rectangle(&mut sketch, p(4.0, 4.0), p(6.0, 6.0));
+
+        let profiles = profiles(&sketch);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles.iter().map(|p| p.holes.len()).collect::<Vec<_>>(), [1, 0]);
+    }
+
+    #[test]
+    fn open_and_construction_geometry_is_ignored() {
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0)]);
+        let center = sketch.add_point(p(5.0, 5.0));
+        sketch.add(Shape::Circle { center, radius: 1.0 }, true);
+
+        assert!(profiles(&sketch).is_empty());
+    }
+
+    #[test]
+    fn arc_closes_a_loop() {
+        // half disc, a diameter and an arc over the top
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        let [c, s, e] = [p(0.0, 0.0), p(1.0, 0.0), p(-1.0, 0.0)].map(|q| sketch.add_point(q));
+        sketch.add(Shape::Arc { center: c, start: s, end: e }, false);
+        sketch.add(Shape::Line { start: e, end: s }, false);
+
+        let profiles = profiles(&sketch);
+        assert_eq!(profiles.len(), 1);
+        assert!((area(&profiles[0].outer) / 2.0 - std::f32::consts::FRAC_PI_2).abs() < 0.01);
+    }
+}
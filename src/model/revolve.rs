@@ -0,0 +1,321 @@
+
+use crate::prelude::*;
+use super::{ModelEntity, Shape, Sketch};
+use super::brep::{self, Brep, Curve, Surface, turn};
+use super::profile::runs;
+
+/// line a sketch is revolved about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    /// line element of the profile sketch, usually construction geometry
+    Line(usize),
+    X,
+    Y,
+    Z,
+}
+
+impl std::fmt::Display for Axis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Axis::Line(element) => write!(f, "Sketch line {}", element + 1),
+            Axis::X => write!(f, "X axis"),
+            Axis::Y => write!(f, "Y axis"),
+            Axis::Z => write!(f, "Z axis"),
+        }
+    }
+}
+
+/// most steps a full turn can be divided into
+pub const MAX_SEGMENTS: usize = 1024;
+
+/// closed profiles of a sketch swept around an axis
+///
+/// Like [`super::Extrude`], the sketch is referred to by history index and copied
+/// in when the model regenerates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revolve {
+    sketch: usize,
+    axis: Axis,
+    /// sweep in degrees, counter-clockwise about the axis direction
+    angle: f32,
+    /// steps of a full turn, from 3 up to [`MAX_SEGMENTS`]
+    segments: usize,
+
+    /// parent sketch, as of the last regeneration
+    profile: Sketch,
+}
+
+impl Revolve {
+    pub fn new(sketch: usize, axis: Axis, angle: f32, segments: usize) -> Self {
+        Self { sketch, axis, angle, segments: segments.clamp(3, MAX_SEGMENTS), profile: Sketch::default() }
+    }
+
+    /// history index of the profile sketch
+    pub fn sketch(&self) -> usize {
+        self.sketch
+    }
+
+    pub fn axis(&self) -> Axis {
+        self.axis
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    /// history indices this feature is built from
+    pub fn references(&self) -> Vec<usize> {
+        vec![self.sketch]
+    }
+
+    /// renumber the history indices this feature is built from
+    pub(super) fn remap(&mut self, map: impl Fn(usize) -> usize) {
+        self.sketch = map(self.sketch);
+    }
+
+    /// copy in the geometry of the sketch, which comes earlier in the history
+    pub(super) fn resolve(&mut self, earlier: &[ModelEntity]) {
+        match earlier.get(self.sketch) {
+            Some(ModelEntity::Sketch(sketch)) => self.profile = sketch.clone(),
+            _ => log::warn!("Revolve refers to entity {}, which is not an earlier sketch", self.sketch + 1),
+        }
+
+        match self.line() {
+            Some((origin, direction)) => {
+                let plane = self.profile.plane();
+                if plane.distance(&origin).abs() > 1e-4 || plane.normal().dot(&direction).abs() > 1e-4 {
+                    log::warn!("Revolve axis does not lie in the sketch plane");
+                }
+            },
+            None => log::warn!("Revolve axis {} is not a line of the sketch", self.axis),
+        }
+
+        if Self::crosses_axis(&self.profile, self.axis) {
+            log::warn!("Revolve profile crosses the axis {}", self.axis);
+        }
+    }
+
+    /// whether a profile of `sketch` lies on both sides of `axis`, which would sweep through itself
+    pub fn crosses_axis(sketch: &Sketch, axis: Axis) -> bool {
+        let Some((origin, direction)) = line(sketch, axis) else {
+            return false;
+        };
+
+        let plane = sketch.plane();
+        sketch.profiles().iter().any(|profile| {
+            let sides: Vec<f32> = profile.outer.iter()
+                .map(|p| direction.cross(&(plane.point(p) - origin)).dot(&plane.normal()))
+                .collect();
+
+            sides.iter().any(|&s| s > 1e-4) && sides.iter().any(|&s| s < -1e-4)
+        })
+    }
+
+    /// point on the axis and its unit direction
+    fn line(&self) -> Option<(Point3d, Point3d)> {
+        line(&self.profile, self.axis)
+    }
+
+    /// sweep in radians, number of steps, and whether it closes on itself
+    fn sweep(&self) -> (f32, usize, bool) {
+        let sweep = self.angle.clamp(-360.0, 360.0).to_radians();
+        let steps = (self.segments as f32 * sweep.abs() / std::f32::consts::TAU).ceil() as usize;
+
+        (sweep, steps.max(1), self.angle.abs() >= 360.0)
+    }
+
+    /// faces of the solid, one shell per profile
+    ///
+    /// Each run of a loop between corners sweeps a side face, and a partial turn is
+    /// capped by the profile at both ends. Profiles across the axis give no faces.
+    pub fn brep(&self) -> Brep {
+        let mut brep = Brep::new();
+        let Some((origin, axis)) = self.line() else {
+            return brep;
+        };
+
+        if Self::crosses_axis(&self.profile, self.axis) {
+            return brep;
+        }
+
+        let plane = self.profile.plane();
+        let (sweep, steps, closed) = self.sweep();
+        let at = |p: Point3d, k: usize| origin + turn(p - origin, axis, sweep * k as f32 / steps as f32);
+        let path = |p: Point3d| {
+            let center = origin + axis * axis.dot(&(p - origin));
+            let circle = Curve::Circle { center, axis: axis * sweep.signum(), radius: (p - center).length() };
+
+            ((0..=steps).map(|k| at(p, k)).collect(), circle)
+        };
+
+        for profile in self.profile.profiles() {
+            // the sides sweep to the left of the loops seen from outside, which turns them around when travelling against the sketch normal
+            let centroid = profile.outer.iter().fold(Point2d::default(), |acc, &p| acc + p) / profile.outer.len() as f32;
+            let travel = axis.cross(&(plane.point(&centroid) - origin)) * sweep.signum();
+            let loops: Vec<Vec<Point2d>> = profile.loops()
+                .map(|l| match travel.dot(&plane.normal()) < 0.0 {
+                    true => l.iter().rev().copied().collect(),
+                    false => l.clone(),
+                })
+                .collect();
+
+            brep.add_shell();
+            let (mut start, mut end) = (Vec::new(), Vec::new());
+            for l in loops.iter() {
+                let (mut start_loop, mut end_loop) = (Vec::new(), Vec::new());
+
+                for run in runs(l) {
+                    let points: Vec<Point3d> = run.iter().map(|&i| plane.point(&l[i])).collect();
+                    let generator = brep.add_edge(points.clone(), brep::curve(&points));
+                    brep.add_swept_face(Surface::Revolution { origin, axis, angle: sweep }, generator, |i| path(points[i]));
+
+                    end_loop.push(points.iter().map(|&p| at(p, steps)).collect());
+                    start_loop.insert(0, points.into_iter().rev().collect());
+                }
+
+                start.push(start_loop);
+                end.push(end_loop);
+            }
+
+            if !closed {
+                let caps = [brep.add_planar_face(start), brep.add_planar_face(end)];
+                brep.lead_with(&caps);
+            }
+        }
+
+        brep
+    }
+}
+
+/// point on `axis` of `sketch` and its unit direction
+fn line(sketch: &Sketch, axis: Axis) -> Option<(Point3d, Point3d)> {
+    match axis {
+        Axis::Line(element) => match sketch.elements().get(element)?.shape {
+            Shape::Line { start, end } => {
+                let plane = sketch.plane();
+                let (start, end) = (plane.point(&sketch.point(start)), plane.point(&sketch.point(end)));
+
+                match (end - start).length() > f32::EPSILON {
+                    true => Some((start, (end - start).normalize())),
+                    false => None,
+                }
+            },
+            _ => None,
+        },
+        Axis::X => Some((Point3d::default(), Point3d::new(1.0, 0.0, 0.0))),
+        Axis::Y => Some((Point3d::default(), Point3d::new(0.0, 1.0, 0.0))),
+        Axis::Z => Some((Point3d::default(), Point3d::new(0.0, 0.0, 1.0))),
+    }
+}
+
+impl super::ModelEntityObject for Revolve {
+    fn vertices(&self) -> Vec<Vertex> {
+        self.brep().tessellate()
+    }
+
+    /// profile at both ends of the sweep, and the circles traced by its corners
+    fn edges(&self) -> Vec<Vertex> {
+        self.brep().outline()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ModelEntityObject, Plane};
+
+    /// rectangle from `min` to `max` on the XY plane, with a construction line along X
+    fn sketch(min: Point2d, max: Point2d) -> Sketch {
+        let mut sketch = Sketch::on_plane(Plane::xy());
+        let corners = [min, Point2d::new(max.x, min.y), max, Point2d::new(min.x, max.y)].map(|p| sketch.add_point(p));
+        for i in 0..4 {
+            sketch.add(Shape::Line { start: corners[i], end: corners[(i + 1) % 4] }, false);
+        }
+
+        let (start, end) = (sketch.add_point(Point2d::new(0.0, 0.0)), sketch.add_point(Point2d::new(1.0, 0.0)));
+        sketch.add(Shape::Line { start, end }, true);
+
+        sketch
+    }
+
+    fn revolve(sketch: Sketch, axis: Axis, angle: f32) -> Revolve {
+        let mut revolve = Revolve::new(0, axis, angle, 64);
+        revolve.resolve(&[ModelEntity::Sketch(sketch)]);
+
+        revolve
+    }
+
+    /// volume of a unit high ring from radius 1 to 2, swept in `steps` flat wedges
+    fn ring(sweep: f32, steps: usize) -> f32 {
+        steps as f32 / 2.0 * (sweep.to_radians() / steps as f32).sin() * (2f32.powi(2) - 1.0)
+    }
+
+    #[test]
+    fn full_turn_about_model_axis() {
+        let revolve = revolve(sketch(Point2d::new(1.0, 0.0), Point2d::new(2.0, 1.0)), Axis::Y, 360.0);
+
+        assert!((volume(&revolve.vertices()) - ring(360.0, 64)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn partial_turn_is_capped() {
+        for angle in [90.0, -90.0] {
+            let revolve = revolve(sketch(Point2d::new(1.0, 0.0), Point2d::new(2.0, 1.0)), Axis::Y, angle);
+
+            assert!((volume(&revolve.vertices()) - ring(90.0, 16)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn about_sketch_line() {
+        let revolve = revolve(sketch(Point2d::new(0.0, 1.0), Point2d::new(1.0, 2.0)), Axis::Line(4), 360.0);
+
+        assert!((volume(&revolve.vertices()) - ring(360.0, 64)).abs() < 1e-3);
+
+        // corner circles stay in the planes across the axis at each end
+        assert!(revolve.edges().iter().all(|v| v.position[0].abs() < 1e-5 || (v.position[0] - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn brep_is_a_closed_shell() {
+        for angle in [360.0, 90.0, -90.0] {
+            let brep = revolve(sketch(Point2d::new(1.0, 0.0), Point2d::new(2.0, 1.0)), Axis::Y, angle).brep();
+
+            assert!(brep.is_closed());
+            assert_eq!(brep.faces().len(), if angle == 360.0 { 4 } else { 6 });
+        }
+
+        // the side on the axis collapses, leaving arcs that are only points
+        let solid = revolve(sketch(Point2d::new(0.0, 0.0), Point2d::new(2.0, 1.0)), Axis::Y, 360.0).brep();
+        assert!(solid.is_closed());
+        assert!(solid.edges().iter().any(|e| e.is_degenerate()));
+    }
+
+    #[test]
+    fn axis_must_be_a_line() {
+        let mut sketch = sketch(Point2d::new(1.0, 0.0), Point2d::new(2.0, 1.0));
+        let center = sketch.add_point(Point2d::new(5.0, 5.0));
+        let circle = sketch.add(Shape::Circle { center, radius: 1.0 }, true);
+
+        assert!(revolve(sketch, Axis::Line(circle), 360.0).vertices().is_empty());
+    }
+
+    #[test]
+    fn profile_across_the_axis_is_rejected() {
+        let sketch = sketch(Point2d::new(-1.0, 0.0), Point2d::new(2.0, 1.0));
+
+        assert!(Revolve::crosses_axis(&sketch, Axis::Y));
+        assert!(!Revolve::crosses_axis(&sketch, Axis::X));
+        assert!(revolve(sketch, Axis::Y, 360.0).vertices().is_empty());
+    }
+
+    #[test]
+    fn segments_are_bounded() {
+        assert_eq!(Revolve::new(0, Axis::Y, 360.0, 0).segments(), 3);
+        assert_eq!(Revolve::new(0, Axis::Y, 360.0, usize::MAX).segments(), MAX_SEGMENTS);
+    }
+}
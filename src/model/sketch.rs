@@ -1,25 +1,432 @@
 
+use std::f32::consts::TAU;
+
 use crate::prelude::*;
 
-use super::{Plane, Point2d};
+use super::{constraint, profile, Constraint, Plane, Point2d, Profile, Status};
+
+/// segments used to draw a full circle
+const CIRCLE_SEGMENTS: usize = 64;
+
+/// dashes used to draw a construction element
+const DASHES: usize = 12;
+
+/// geometry of a sketch element, referring to sketch points by index
+///
+/// Elements share points, so that moving a point moves every element attached to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Line { start: usize, end: usize },
+    Circle { center: usize, radius: f32 },
+    /// counter-clockwise from `start` to `end`, with the radius given by `start`
+    Arc { center: usize, start: usize, end: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SketchElement {
+    pub shape: Shape,
+    /// reference geometry that is drawn dashed and does not make up a profile
+    pub construction: bool,
+}
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Sketch {
     plane: Plane,
-    points: Vec<Point2d>
+    points: Vec<Point2d>,
+    elements: Vec<SketchElement>,
+    constraints: Vec<Constraint>,
 }
 
 impl Sketch {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn on_plane(plane: Plane) -> Self {
+        Self { plane, ..Default::default() }
+    }
+
+    /// connected lines through `points`
+    pub fn polyline(plane: Plane, points: Vec<Point2d>) -> Self {
+        let elements = (1..points.len())
+            .map(|i| SketchElement { shape: Shape::Line { start: i - 1, end: i }, construction: false })
+            .collect();
+
+        Self { plane, points, elements, ..Default::default() }
+    }
+
+    pub fn plane(&self) -> &Plane {
+        &self.plane
+    }
+
+    pub fn set_plane(&mut self, plane: Plane) {
+        self.plane = plane;
+    }
+
+    pub fn points(&self) -> &[Point2d] {
+        &self.points
+    }
+
+    pub fn point(&self, index: usize) -> Point2d {
+        self.points[index]
+    }
+
+    pub fn set_point(&mut self, index: usize, point: Point2d) {
+        self.points[index] = point;
+    }
+
+    pub fn elements(&self) -> &[SketchElement] {
+        &self.elements
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// add a constraint and move the geometry to satisfy it
+    ///
+    /// A constraint that conflicts with the others, or follows from them, is not kept, and
+    /// the geometry stays where it was.
+    pub fn add_constraint(&mut self, constraint: Constraint) -> Status {
+        let (points, elements) = (self.points.clone(), self.elements.clone());
+        self.constraints.push(constraint);
+
+        let status = self.solve();
+        if status == Status::OverConstrained {
+            self.constraints.pop();
+            self.points = points;
+            self.elements = elements;
+        }
+
+        status
+    }
+
+    /// add a constraint without solving, for several that are solved together afterwards
+    pub fn push_constraint(&mut self, constraint: Constraint) {
+        self.constraints.push(constraint);
+    }
+
+    /// drive a dimension to `value` and solve
+    ///
+    /// The dimension keeps its old value, and the geometry stays, if the new one cannot be met.
+    pub fn set_dimension(&mut self, index: usize, value: f32) -> Status {
+        let (previous, points, elements) = (self.constraints[index], self.points.clone(), self.elements.clone());
+        self.constraints[index].set_value(value);
+
+        let status = self.solve();
+        if status == Status::OverConstrained {
+            self.constraints[index] = previous;
+            self.points = points;
+            self.elements = elements;
+        }
+
+        status
+    }
+
+    /// current value of what a dimension measures, or `None` for geometric constraints
+    pub fn measure(&self, constraint: &Constraint) -> Option<f32> {
+        match *constraint {
+            Constraint::Distance { a, b, .. } => Some(self.points[a].distance(&self.points[b])),
+            Constraint::Radius { element, .. } => self.radius(element),
+            Constraint::Diameter { element, .. } => self.radius(element).map(|r| 2.0 * r),
+            Constraint::Angle { a, b, .. } => {
+                let (da, db) = (self.direction(a)?, self.direction(b)?);
+
+                Some(da.cross(&db).atan2(da.dot(&db)).to_degrees())
+            },
+            _ => None,
+        }
+    }
+
+    /// radius of a circle or arc
+    pub fn radius(&self, element: usize) -> Option<f32> {
+        match self.elements[element].shape {
+            Shape::Circle { radius, .. } => Some(radius),
+            Shape::Arc { center, start, .. } => Some(self.points[center].distance(&self.points[start])),
+            Shape::Line { .. } => None,
+        }
+    }
+
+    /// vector from the start to the end of a line
+    pub fn direction(&self, element: usize) -> Option<Point2d> {
+        match self.elements[element].shape {
+            Shape::Line { start, end } => Some(self.points[end] - self.points[start]),
+            _ => None,
+        }
+    }
+
+    pub fn remove_constraint(&mut self, index: usize) -> Constraint {
+        self.constraints.remove(index)
+    }
+
+    /// move points and radii to satisfy every constraint
+    ///
+    /// Geometry is left as it is if the constraints conflict.
+    pub fn solve(&mut self) -> Status {
+        constraint::solve(&mut self.points, &mut self.elements, &self.constraints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// closed regions of the sketch, for features that turn it into a solid
+    pub fn profiles(&self) -> Vec<Profile> {
+        profile::profiles(self)
+    }
+
+    /// add a point, returning its index
+    pub fn add_point(&mut self, point: Point2d) -> usize {
+        self.points.push(point);
+        self.points.len() - 1
+    }
+
+    /// add an element, returning its index
+    pub fn add(&mut self, shape: Shape, construction: bool) -> usize {
+        self.elements.push(SketchElement { shape, construction });
+        self.elements.len() - 1
+    }
+
+    /// polyline that approximates an element, in plane coordinates
+    pub fn tessellate(&self, element: &SketchElement) -> Vec<Point2d> {
+        match element.shape {
+            Shape::Line { start, end } => vec![self.points[start], self.points[end]],
+            Shape::Circle { center, radius } => arc(self.points[center], radius, 0.0, TAU),
+            Shape::Arc { center, start, end } => {
+                let c = self.points[center];
+                let (from, to) = (self.points[start] - c, self.points[end] - c);
+
+                // sweep counter-clockwise, so a zero sweep is a full turn
+                let mut sweep = to.angle() - from.angle();
+                if sweep <= 0.0 {
+                    sweep += TAU;
+                }
+
+                arc(c, from.length(), from.angle(), sweep)
+            },
+        }
+    }
+
+    /// closest point within `tolerance` of `target`
+    pub fn point_near(&self, target: Point2d, tolerance: f32) -> Option<usize> {
+        self.points.iter()
+            .enumerate()
+            .map(|(i, p)| (i, p.distance(&target)))
+            .filter(|(_, d)| *d <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// closest element within `tolerance` of `target`
+    pub fn element_near(&self, target: Point2d, tolerance: f32) -> Option<usize> {
+        self.elements.iter()
+            .enumerate()
+            .map(|(i, element)| {
+                let polyline = self.tessellate(element);
+                let d = polyline.windows(2)
+                    .map(|pair| segment_distance(pair[0], pair[1], target))
+                    .fold(f32::INFINITY, f32::min);
+
+                (i, d)
+            })
+            .filter(|(_, d)| *d <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
+fn segment_distance(a: Point2d, b: Point2d, p: Point2d) -> f32 {
+    let ab = b - a;
+    let t = match ab.dot(&ab) {
+        len if len <= f32::EPSILON => 0.0,
+        len => ((p - a).dot(&ab) / len).clamp(0.0, 1.0),
+    };
+
+    p.distance(&(a + ab * t))
+}
+
+/// points along a circular arc, with segments no longer than those of a full circle
+fn arc(center: Point2d, radius: f32, start: f32, sweep: f32) -> Vec<Point2d> {
+    let segments = ((sweep / TAU * CIRCLE_SEGMENTS as f32).ceil() as usize).max(1);
+
+    (0..=segments)
+        .map(|i| {
+            let angle = start + sweep * i as f32 / segments as f32;
+            center + Point2d::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+/// every other piece of a polyline, split finely enough to give at least [`DASHES`] dashes
+fn dashed(polyline: &[Point2d]) -> Vec<(Point2d, Point2d)> {
+    let segments = polyline.len().saturating_sub(1);
+    if segments == 0 {
+        return Vec::new();
+    }
+
+    let split = (2 * DASHES).div_ceil(segments);
+    let pieces: Vec<Point2d> = polyline
+        .windows(2)
+        .flat_map(|pair| (0..split).map(move |i| pair[0] + (pair[1] - pair[0]) * (i as f32 / split as f32)))
+        .chain(polyline.last().copied())
+        .collect();
+
+    pieces.windows(2).step_by(2).map(|pair| (pair[0], pair[1])).collect()
 }
 
 impl super::ModelEntityObject for Sketch {
+    /// sketches have no surface
     fn vertices(&self) -> Vec<Vertex> {
-        self.points
-            .iter()
-            .map(|p| self.plane.vertex(p))
-            .collect()
+        Vec::new()
+    }
+
+    /// line list of every element, with zero normals so that it is drawn unlit
+    fn edges(&self) -> Vec<Vertex> {
+        let mut data = Vec::new();
+        for element in self.elements.iter() {
+            let polyline = self.tessellate(element);
+
+            let segments = match element.construction {
+                true => dashed(&polyline),
+                false => polyline.windows(2).map(|pair| (pair[0], pair[1])).collect(),
+            };
+
+            for (a, b) in segments {
+                data.push(self.plane.point(&a).into());
+                data.push(self.plane.point(&b).into());
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ModelEntityObject;
+
+    #[test]
+    fn renders_as_polyline() {
+        let plane = Plane::yz().at(Point3d::new(2.0, 0.0, 0.0));
+        let sketch = Sketch::polyline(plane, vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 0.0), Point2d::new(1.0, 3.0)]);
+
+        assert!(sketch.vertices().is_empty());
+
+        let edges: Vec<[f32; 3]> = sketch.edges().iter().map(|v| v.position).collect();
+        assert_eq!(edges, [[2.0, 0.0, 0.0], [2.0, 1.0, 0.0], [2.0, 1.0, 0.0], [2.0, 1.0, 3.0]]);
+        assert!(sketch.edges().iter().all(|v| v.normal == [0.0; 3]));
+    }
+
+    #[test]
+    fn circle_is_closed() {
+        let mut sketch = Sketch::new();
+        let center = sketch.add_point(Point2d::new(1.0, 2.0));
+        sketch.add(Shape::Circle { center, radius: 3.0 }, false);
+
+        let polyline = sketch.tessellate(&sketch.elements()[0]);
+        assert_eq!(polyline.len(), CIRCLE_SEGMENTS + 1);
+        assert!(polyline[0].distance(polyline.last().unwrap()) < 1e-5);
+        assert!(polyline.iter().all(|p| (p.distance(&Point2d::new(1.0, 2.0)) - 3.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn arc_runs_counter_clockwise() {
+        let mut sketch = Sketch::new();
+        let center = sketch.add_point(Point2d::new(0.0, 0.0));
+        let start = sketch.add_point(Point2d::new(0.0, 2.0));
+        let end = sketch.add_point(Point2d::new(2.0, 0.0));
+        sketch.add(Shape::Arc { center, start, end }, false);
+
+        // from +Y round through -X and -Y to +X, three quarters of a turn
+        let polyline = sketch.tessellate(&sketch.elements()[0]);
+        assert_eq!(polyline.len(), CIRCLE_SEGMENTS * 3 / 4 + 1);
+        assert!(polyline.iter().any(|p| p.distance(&Point2d::new(-2.0, 0.0)) < 1e-5));
+        assert!(polyline.last().unwrap().distance(&Point2d::new(2.0, 0.0)) < 1e-5);
+    }
+
+    #[test]
+    fn construction_is_dashed() {
+        let mut sketch = Sketch::new();
+        let start = sketch.add_point(Point2d::new(0.0, 0.0));
+        let end = sketch.add_point(Point2d::new(24.0, 0.0));
+        sketch.add(Shape::Line { start, end }, true);
+
+        let edges = sketch.edges();
+        assert_eq!(edges.len(), 2 * DASHES);
+        assert_eq!(edges[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(edges[1].position, [1.0, 0.0, 0.0]);
+        assert_eq!(edges[2].position, [2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn picks_nearest() {
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(4.0, 0.0)]);
+        let center = sketch.add_point(Point2d::new(0.0, 5.0));
+        sketch.add(Shape::Circle { center, radius: 1.0 }, false);
+
+        assert_eq!(sketch.point_near(Point2d::new(3.9, 0.1), 0.5), Some(1));
+        assert_eq!(sketch.point_near(Point2d::new(2.0, 0.1), 0.5), None);
+        assert_eq!(sketch.element_near(Point2d::new(2.0, 0.1), 0.5), Some(0));
+        assert_eq!(sketch.element_near(Point2d::new(0.0, 3.9), 0.5), Some(1));
+        assert_eq!(sketch.element_near(Point2d::new(0.0, 5.0), 0.5), None);
+    }
+
+    #[test]
+    fn constraints_move_points() {
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(2.0, 0.4)]);
+        assert_eq!(sketch.solve(), Status::UnderConstrained(4));
+
+        sketch.add_constraint(Constraint::Fixed { point: 0, at: Point2d::new(0.0, 0.0) });
+        let status = sketch.add_constraint(Constraint::Horizontal(0));
+
+        assert_eq!(status, Status::UnderConstrained(1));
+        assert!(sketch.point(1).y.abs() < 1e-5);
+        assert!(sketch.point(0).length() < 1e-5);
+    }
+
+    #[test]
+    fn conflicting_constraint_is_not_kept() {
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 1.0)]);
+        sketch.add_constraint(Constraint::Fixed { point: 0, at: Point2d::new(0.0, 0.0) });
+        sketch.add_constraint(Constraint::Fixed { point: 1, at: Point2d::new(1.0, 1.0) });
+        let before = sketch.clone();
+
+        assert_eq!(sketch.add_constraint(Constraint::Horizontal(0)), Status::OverConstrained);
+        assert_eq!(sketch, before);
+
+        // the sketch is left solvable
+        assert_eq!(sketch.solve(), Status::FullyConstrained);
+    }
+
+    #[test]
+    fn unmet_dimension_keeps_its_value() {
+        // two sides of a triangle, which the third cannot be longer than
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 0.0), Point2d::new(1.0, 1.0)]);
+        sketch.add_constraint(Constraint::Distance { a: 0, b: 1, value: 1.0 });
+        sketch.add_constraint(Constraint::Distance { a: 1, b: 2, value: 1.0 });
+        sketch.add_constraint(Constraint::Distance { a: 0, b: 2, value: 1.5 });
+        let before = sketch.clone();
+
+        assert_eq!(sketch.set_dimension(2, 3.0), Status::OverConstrained);
+        assert_eq!(sketch, before);
+    }
+
+    #[test]
+    fn dimension_is_driven() {
+        let mut sketch = Sketch::polyline(Plane::xy(), vec![Point2d::new(0.0, 0.0), Point2d::new(2.0, 0.0), Point2d::new(2.0, 1.0)]);
+        let length = Constraint::Distance { a: 0, b: 1, value: 0.0 };
+        let angle = Constraint::Angle { a: 0, b: 1, value: 0.0 };
+
+        assert_eq!(sketch.measure(&length), Some(2.0));
+        assert!((sketch.measure(&angle).unwrap() - 90.0).abs() < 1e-4);
+        assert_eq!(sketch.measure(&Constraint::Horizontal(0)), None);
+
+        sketch.add_constraint(Constraint::Fixed { point: 0, at: Point2d::new(0.0, 0.0) });
+        sketch.add_constraint(Constraint::Horizontal(0));
+        sketch.add_constraint(Constraint::Distance { a: 0, b: 1, value: 2.0 });
+
+        sketch.set_dimension(2, 5.0);
+        assert!(sketch.point(1).distance(&Point2d::new(5.0, 0.0)) < 1e-4);
     }
 }
@@ -0,0 +1,94 @@
+
+use super::Point3d;
+use crate::prelude::*;
+
+/// placement of a mesh: scaled about the origin, then rotated, then translated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Point3d,
+    /// rotation about the X, Y and Z axes in degrees, applied in that order
+    pub rotation: Point3d,
+    /// uniform scale
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Point3d::default(),
+            rotation: Point3d::default(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn point(&self, point: Point3d) -> Point3d {
+        self.rotate(point * self.scale) + self.translation
+    }
+
+    /// directions are only rotated; a negative scale also flips them
+    pub fn direction(&self, direction: Point3d) -> Point3d {
+        self.rotate(direction * self.scale.signum())
+    }
+
+    pub fn vertex(&self, vertex: &Vertex) -> Vertex {
+        Vertex {
+            position: self.point(vertex.position.into()).to_array(),
+            normal: self.direction(vertex.normal.into()).to_array(),
+            ..*vertex
+        }
+    }
+
+    fn rotate(&self, p: Point3d) -> Point3d {
+        let (sx, cx) = self.rotation.x.to_radians().sin_cos();
+        let (sy, cy) = self.rotation.y.to_radians().sin_cos();
+        let (sz, cz) = self.rotation.z.to_radians().sin_cos();
+
+        let p = Point3d::new(p.x, p.y * cx - p.z * sx, p.y * sx + p.z * cx);
+        let p = Point3d::new(p.x * cy + p.z * sy, p.y, -p.x * sy + p.z * cy);
+
+        Point3d::new(p.x * cz - p.y * sz, p.x * sz + p.y * cz, p.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Point3d, b: Point3d) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn identity() {
+        let p = Point3d::new(1.0, -2.0, 3.0);
+
+        assert!(Transform::default().is_identity());
+        assert_eq!(Transform::default().point(p), p);
+    }
+
+    #[test]
+    fn scale_rotate_translate() {
+        let transform = Transform {
+            translation: Point3d::new(0.0, 0.0, 1.0),
+            rotation: Point3d::new(0.0, 0.0, 90.0),
+            scale: 2.0,
+        };
+
+        assert_near(transform.point(Point3d::new(1.0, 0.0, 0.0)), Point3d::new(0.0, 2.0, 1.0));
+        assert_near(transform.direction(Point3d::new(1.0, 0.0, 0.0)), Point3d::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rotations_apply_in_order() {
+        // X first takes +Y to +Z, which the Y rotation then takes to +X
+        let transform = Transform { rotation: Point3d::new(90.0, 90.0, 0.0), ..Default::default() };
+
+        assert_near(transform.point(Point3d::new(0.0, 1.0, 0.0)), Point3d::new(1.0, 0.0, 0.0));
+    }
+}
@@ -5,3 +5,8 @@ use crate::prelude::*;
 pub fn empty_buffer(display: &Display) -> VertexBuffer {
     VertexBuffer::new(display, &[]).unwrap()
 }
+
+/// single copy of a buffer, where its vertices already are
+pub fn identity_instances(display: &Display) -> Result<InstanceBuffer, glium::vertex::BufferCreationError> {
+    InstanceBuffer::new(display, &[Instance::default()])
+}
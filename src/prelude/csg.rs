@@ -0,0 +1,703 @@
+
+//! boolean operations on closed triangle meshes
+//!
+//! Each mesh is split by a BSP tree built from the faces of the other, following
+//! csg.js. Inputs should be closed and face outward. Work is done in `f64` so that
+//! splits of splits stay on their planes.
+//!
+//! Splitting leaves corners of one polygon on the edges of its neighbours, so the
+//! result of each boolean is welded and those corners are added to the edges they
+//! sit on, which keeps the triangles of the result meeting edge to edge.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use super::{Point3d, Vertex};
+
+/// distance below which a point counts as lying on a plane
+const EPSILON: f64 = 1e-5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Vector {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Vector {
+    fn dot(&self, rhs: &Self) -> f64 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    fn cross(&self, rhs: &Self) -> Self {
+        Self {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+
+    fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    fn lerp(&self, rhs: &Self, t: f64) -> Self {
+        *self + (*rhs - *self) * t
+    }
+}
+
+impl Add for Vector {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z }
+    }
+}
+
+impl Sub for Vector {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y, z: self.z - rhs.z }
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self { x: self.x * rhs, y: self.y * rhs, z: self.z * rhs }
+    }
+}
+
+impl Neg for Vector {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self * -1.0
+    }
+}
+
+impl From<[f32; 3]> for Vector {
+    fn from(value: [f32; 3]) -> Self {
+        Self { x: value[0] as f64, y: value[1] as f64, z: value[2] as f64 }
+    }
+}
+
+impl From<Vector> for [f32; 3] {
+    fn from(value: Vector) -> Self {
+        [value.x as f32, value.y as f32, value.z as f32]
+    }
+}
+
+/// polygon corner, with the attributes that are interpolated along splits
+#[derive(Debug, Clone, Copy)]
+struct Corner {
+    position: Vector,
+    normal: Vector,
+    texture: [f64; 2],
+}
+
+impl Corner {
+    fn lerp(&self, rhs: &Self, t: f64) -> Self {
+        Self {
+            position: self.position.lerp(&rhs.position, t),
+            normal: self.normal.lerp(&rhs.normal, t),
+            texture: [0, 1].map(|i| self.texture[i] + (rhs.texture[i] - self.texture[i]) * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Plane {
+    normal: Vector,
+    /// distance of the plane from the origin, along its normal
+    w: f64,
+}
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+impl Plane {
+    fn from_points(a: Vector, b: Vector, c: Vector) -> Option<Self> {
+        let normal = (b - a).cross(&(c - a));
+        let length = normal.length();
+        if length <= f64::EPSILON {
+            return None;
+        }
+
+        let normal = normal * (1.0 / length);
+        Some(Self { normal, w: normal.dot(&a) })
+    }
+
+    fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.w = -self.w;
+    }
+
+    fn side(&self, point: &Vector) -> u8 {
+        match self.normal.dot(point) - self.w {
+            t if t < -EPSILON => BACK,
+            t if t > EPSILON => FRONT,
+            _ => COPLANAR,
+        }
+    }
+
+    /// sort `polygon` to the side of the plane it is on, splitting it if it spans both
+    fn split(
+        &self,
+        polygon: Polygon,
+        coplanar_front: &mut Vec<Polygon>,
+        coplanar_back: &mut Vec<Polygon>,
+        front: &mut Vec<Polygon>,
+        back: &mut Vec<Polygon>,
+    ) {
+        let sides: Vec<u8> = polygon.corners.iter().map(|c| self.side(&c.position)).collect();
+
+        match sides.iter().fold(COPLANAR, |acc, side| acc | side) {
+            COPLANAR => match self.normal.dot(&polygon.plane.normal) > 0.0 {
+                true => coplanar_front.push(polygon),
+                false => coplanar_back.push(polygon),
+            },
+            FRONT => front.push(polygon),
+            BACK => back.push(polygon),
+            _ => {
+                let (mut f, mut b) = (Vec::new(), Vec::new());
+                let n = polygon.corners.len();
+                for i in 0..n {
+                    let j = (i + 1) % n;
+                    let (si, sj) = (sides[i], sides[j]);
+                    let (ci, cj) = (polygon.corners[i], polygon.corners[j]);
+
+                    if si != BACK {
+                        f.push(ci);
+                    }
+                    if si != FRONT {
+                        b.push(ci);
+                    }
+
+                    if si | sj == SPANNING {
+                        let t = (self.w - self.normal.dot(&ci.position)) / self.normal.dot(&(cj.position - ci.position));
+                        let corner = ci.lerp(&cj, t);
+                        f.push(corner);
+                        b.push(corner);
+                    }
+                }
+
+                // both halves keep the plane of the original, rather than one rebuilt from rounded corners
+                if f.len() >= 3 {
+                    front.push(Polygon { corners: f, plane: polygon.plane });
+                }
+                if b.len() >= 3 {
+                    back.push(Polygon { corners: b, plane: polygon.plane });
+                }
+            },
+        }
+    }
+
+    /// sort a segment like [`Plane::split`], with segments on the plane going to `coplanar`
+    fn split_segment(&self, [a, b]: [Vector; 2], coplanar: &mut Vec<[Vector; 2]>, front: &mut Vec<[Vector; 2]>, back: &mut Vec<[Vector; 2]>) {
+        let (sa, sb) = (self.side(&a), self.side(&b));
+
+        match sa | sb {
+            COPLANAR => coplanar.push([a, b]),
+            FRONT => front.push([a, b]),
+            BACK => back.push([a, b]),
+            _ => {
+                let t = (self.w - self.normal.dot(&a)) / self.normal.dot(&(b - a));
+                let middle = a.lerp(&b, t);
+
+                match sa == FRONT {
+                    true => {
+                        front.push([a, middle]);
+                        back.push([middle, b]);
+                    },
+                    false => {
+                        back.push([a, middle]);
+                        front.push([middle, b]);
+                    },
+                }
+            },
+        }
+    }
+}
+
+/// convex planar polygon
+#[derive(Debug, Clone)]
+struct Polygon {
+    corners: Vec<Corner>,
+    plane: Plane,
+}
+
+impl Polygon {
+    fn flip(&mut self) {
+        self.corners.reverse();
+        for corner in self.corners.iter_mut() {
+            corner.normal = -corner.normal;
+        }
+        self.plane.flip();
+    }
+}
+
+/// BSP tree node; the solid is on the back of every plane
+#[derive(Debug, Default)]
+struct Node {
+    plane: Option<Plane>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+    polygons: Vec<Polygon>,
+}
+
+impl Node {
+    fn new(polygons: Vec<Polygon>) -> Self {
+        let mut node = Self::default();
+        node.build(polygons);
+
+        node
+    }
+
+    /// swap inside and outside
+    fn invert(&mut self) {
+        for polygon in self.polygons.iter_mut() {
+            polygon.flip();
+        }
+        if let Some(plane) = &mut self.plane {
+            plane.flip();
+        }
+        if let Some(front) = &mut self.front {
+            front.invert();
+        }
+        if let Some(back) = &mut self.back {
+            back.invert();
+        }
+
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// the parts of `polygons` outside this tree
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let Some(plane) = self.plane else {
+            return polygons;
+        };
+
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+        for polygon in polygons {
+            plane.split(polygon, &mut coplanar_front, &mut coplanar_back, &mut front, &mut back);
+        }
+        front.append(&mut coplanar_front);
+        back.append(&mut coplanar_back);
+
+        let mut front = match &self.front {
+            Some(node) => node.clip_polygons(front),
+            None => front,
+        };
+        if let Some(node) = &self.back {
+            front.extend(node.clip_polygons(back));
+        }
+
+        front
+    }
+
+    /// remove the parts of this tree's polygons inside `other`
+    fn clip_to(&mut self, other: &Node) {
+        self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+
+        if let Some(front) = &mut self.front {
+            front.clip_to(other);
+        }
+        if let Some(back) = &mut self.back {
+            back.clip_to(other);
+        }
+    }
+
+    /// the parts of `segments` inside this tree, or outside it
+    ///
+    /// Segments on the boundary are kept either way.
+    fn clip_segments(&self, segments: Vec<[Vector; 2]>, inside: bool) -> Vec<[Vector; 2]> {
+        let Some(plane) = self.plane else {
+            return segments;
+        };
+
+        let (mut coplanar, mut front, mut back) = (Vec::new(), Vec::new(), Vec::new());
+        for segment in segments {
+            plane.split_segment(segment, &mut coplanar, &mut front, &mut back);
+        }
+        match inside {
+            true => back.append(&mut coplanar),
+            false => front.append(&mut coplanar),
+        }
+
+        let mut kept = match (&self.front, inside) {
+            (Some(node), _) => node.clip_segments(front, inside),
+            (None, true) => Vec::new(),
+            (None, false) => front,
+        };
+        kept.extend(match (&self.back, inside) {
+            (Some(node), _) => node.clip_segments(back, inside),
+            (None, true) => back,
+            (None, false) => Vec::new(),
+        });
+
+        kept
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = self.polygons.clone();
+        if let Some(front) = &self.front {
+            polygons.extend(front.all_polygons());
+        }
+        if let Some(back) = &self.back {
+            polygons.extend(back.all_polygons());
+        }
+
+        polygons
+    }
+
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        let Some(first) = polygons.first() else {
+            return;
+        };
+
+        let plane = *self.plane.get_or_insert(first.plane);
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        let (mut coplanar_front, mut coplanar_back) = (Vec::new(), Vec::new());
+        for polygon in polygons {
+            plane.split(polygon, &mut coplanar_front, &mut coplanar_back, &mut front, &mut back);
+        }
+        self.polygons.append(&mut coplanar_front);
+        self.polygons.append(&mut coplanar_back);
+
+        if !front.is_empty() {
+            self.front.get_or_insert_with(Default::default).build(front);
+        }
+        if !back.is_empty() {
+            self.back.get_or_insert_with(Default::default).build(back);
+        }
+    }
+}
+
+/// merge corners closer than [`EPSILON`], and add corners that lie on an edge of another
+/// polygon to that edge, dropping polygons that collapse on the way
+fn repair(polygons: Vec<Polygon>) -> Vec<Polygon> {
+    // welded positions, found through the grid cells around each corner
+    let mut points: Vec<Vector> = Vec::new();
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let cell = |p: &Vector| [p.x, p.y, p.z].map(|v| (v / EPSILON).floor() as i64);
+
+    // most corners are exact copies of one seen before, which saves searching the cells
+    let mut exact: HashMap<[u64; 3], usize> = HashMap::new();
+
+    let mut weld = |p: Vector| {
+        let bits = [p.x, p.y, p.z].map(f64::to_bits);
+        if let Some(&found) = exact.get(&bits) {
+            return (found, points[found]);
+        }
+
+        let [x, y, z] = cell(&p);
+        for key in (-1..=1).flat_map(|i| (-1..=1).flat_map(move |j| (-1..=1).map(move |k| [x + i, y + j, z + k]))) {
+            if let Some(&found) = cells.get(&key).and_then(|near| near.iter().find(|&&i| (points[i] - p).length() <= EPSILON)) {
+                exact.insert(bits, found);
+                return (found, points[found]);
+            }
+        }
+
+        exact.insert(bits, points.len());
+        points.push(p);
+        cells.entry([x, y, z]).or_default().push(points.len() - 1);
+        (points.len() - 1, p)
+    };
+
+    let welded: Vec<(Polygon, Vec<usize>)> = polygons.into_iter()
+        .filter_map(|mut polygon| {
+            let mut indices: Vec<usize> = Vec::new();
+            let mut corners = Vec::new();
+            for mut corner in polygon.corners {
+                let (index, position) = weld(corner.position);
+                if indices.last() != Some(&index) && indices.first() != Some(&index) {
+                    corner.position = position;
+                    indices.push(index);
+                    corners.push(corner);
+                }
+            }
+
+            // welding can fold a sliver flat
+            let area = (2..corners.len()).fold(Vector::default(), |sum, i| {
+                sum + (corners[i - 1].position - corners[0].position).cross(&(corners[i].position - corners[0].position))
+            });
+
+            polygon.corners = corners;
+            (indices.len() >= 3 && area.length() > EPSILON * EPSILON).then_some((polygon, indices))
+        })
+        .collect();
+
+    // an edge that a neighbour runs back along already meets it end to end
+    let edges: HashSet<(usize, usize)> = welded.iter()
+        .flat_map(|(_, indices)| (0..indices.len()).map(|i| (indices[i], indices[(i + 1) % indices.len()])))
+        .collect();
+
+    // corners along x, so that the ones on an edge are found from its extent
+    let mut by_x: Vec<usize> = (0..points.len()).collect();
+    by_x.sort_by(|&a, &b| points[a].x.total_cmp(&points[b].x));
+
+    welded.into_iter()
+        .map(|(polygon, indices)| {
+            let mut corners = Vec::with_capacity(polygon.corners.len());
+            for i in 0..indices.len() {
+                let (a, b) = (polygon.corners[i], polygon.corners[(i + 1) % indices.len()]);
+                corners.push(a);
+                if edges.contains(&(indices[(i + 1) % indices.len()], indices[i])) {
+                    continue;
+                }
+
+                let edge = b.position - a.position;
+                let length = edge.length();
+                let (low, high) = (a.position.x.min(b.position.x) - EPSILON, a.position.x.max(b.position.x) + EPSILON);
+                let start = by_x.partition_point(|&p| points[p].x < low);
+
+                let mut inside: Vec<(f64, Vector)> = by_x[start..].iter()
+                    .take_while(|&&p| points[p].x <= high)
+                    .filter(|&&p| p != indices[i] && p != indices[(i + 1) % indices.len()])
+                    .map(|&p| (points[p], (points[p] - a.position).dot(&edge) / (length * length)))
+                    .filter(|(p, t)| *t > 0.0 && *t < 1.0 && (*p - a.position).cross(&edge).length() <= EPSILON * length)
+                    .map(|(p, t)| (t, p))
+                    .collect();
+                inside.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+                corners.extend(inside.into_iter().map(|(t, p)| Corner { position: p, ..a.lerp(&b, t) }));
+            }
+
+            Polygon { corners, ..polygon }
+        })
+        .collect()
+}
+
+/// closed mesh that booleans operate on
+#[derive(Debug, Clone, Default)]
+pub struct Solid {
+    polygons: Vec<Polygon>,
+}
+
+impl Solid {
+    /// solid bounded by a triangle list, leaving out degenerate triangles
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        let polygons = vertices.chunks_exact(3)
+            .filter_map(|t| {
+                let corners: Vec<Corner> = t.iter()
+                    .map(|v| Corner {
+                        position: v.position.into(),
+                        normal: v.normal.into(),
+                        texture: v.texture.map(f64::from),
+                    })
+                    .collect();
+                let plane = Plane::from_points(corners[0].position, corners[1].position, corners[2].position)?;
+
+                Some(Polygon { corners, plane })
+            })
+            .collect();
+
+        Self { polygons }
+    }
+
+    /// triangle list of the boundary
+    pub fn vertices(&self) -> Vec<Vertex> {
+        let mut data = Vec::new();
+        for polygon in self.polygons.iter() {
+            let vertex = |c: &Corner| {
+                // corners without a usable normal take the one of their face
+                let normal = match c.normal.length() {
+                    len if len > f64::EPSILON => c.normal * (1.0 / len),
+                    _ => polygon.plane.normal,
+                };
+
+                Vertex { position: c.position.into(), normal: normal.into(), texture: c.texture.map(|t| t as f32) }
+            };
+
+            // corners added along an edge would give flat triangles in a fan, so ears are cut at real corners instead
+            let corners = &polygon.corners;
+            let straight = |ring: &[usize], k: usize| {
+                let m = ring.len();
+                let (p, c, q) = (corners[ring[(k + m - 1) % m]].position, corners[ring[k]].position, corners[ring[(k + 1) % m]].position);
+                (c - p).cross(&(q - p)).length() <= EPSILON * (q - p).length()
+            };
+
+            let mut ring: Vec<usize> = (0..corners.len()).collect();
+            while ring.len() > 3 {
+                let k = (0..ring.len()).find(|&k| !straight(&ring, k)).unwrap_or(0);
+                let m = ring.len();
+                data.extend([ring[(k + m - 1) % m], ring[k], ring[(k + 1) % m]].map(|i| vertex(&corners[i])));
+                ring.remove(k);
+            }
+            data.extend(ring.iter().map(|&i| vertex(&corners[i])));
+        }
+
+        data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    /// corners of the axis aligned box around the solid
+    pub fn bounds(&self) -> Option<(Point3d, Point3d)> {
+        let mut corners = self.polygons.iter().flat_map(|p| p.corners.iter()).map(|c| Point3d::from(<[f32; 3]>::from(c.position)));
+        let first = corners.next()?;
+
+        Some(corners.fold((first, first), |(min, max), p| (
+            Point3d::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+            Point3d::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+        )))
+    }
+
+    /// whether the boxes around both solids meet, which they must for a boolean to change anything
+    pub fn overlaps(&self, other: &Solid) -> bool {
+        match (self.bounds(), other.bounds()) {
+            (Some((a_min, a_max)), Some((b_min, b_max))) => {
+                a_min.x <= b_max.x && b_min.x <= a_max.x
+                    && a_min.y <= b_max.y && b_min.y <= a_max.y
+                    && a_min.z <= b_max.z && b_min.z <= a_max.z
+            },
+            _ => false,
+        }
+    }
+
+    pub fn union(&self, other: &Solid) -> Solid {
+        let (mut a, mut b) = (Node::new(self.polygons.clone()), Node::new(other.polygons.clone()));
+        a.clip_to(&b);
+        b.clip_to(&a);
+        b.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.build(b.all_polygons());
+
+        Solid { polygons: repair(a.all_polygons()) }
+    }
+
+    pub fn subtract(&self, other: &Solid) -> Solid {
+        let (mut a, mut b) = (Node::new(self.polygons.clone()), Node::new(other.polygons.clone()));
+        a.invert();
+        a.clip_to(&b);
+        b.clip_to(&a);
+        b.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.build(b.all_polygons());
+        a.invert();
+
+        Solid { polygons: repair(a.all_polygons()) }
+    }
+
+    pub fn intersect(&self, other: &Solid) -> Solid {
+        let (mut a, mut b) = (Node::new(self.polygons.clone()), Node::new(other.polygons.clone()));
+        a.invert();
+        b.clip_to(&a);
+        b.invert();
+        a.clip_to(&b);
+        b.clip_to(&a);
+        a.build(b.all_polygons());
+        a.invert();
+
+        Solid { polygons: repair(a.all_polygons()) }
+    }
+
+    /// the parts of a line list inside the solid, or outside it
+    ///
+    /// Lines on the boundary are kept either way, so that outlines survive on shared faces.
+    pub fn clip_lines(&self, lines: &[Vertex], inside: bool) -> Vec<Vertex> {
+        if self.polygons.is_empty() {
+            return match inside {
+                true => Vec::new(),
+                false => lines.to_vec(),
+            };
+        }
+
+        let segments = lines.chunks_exact(2).map(|l| [l[0].position.into(), l[1].position.into()]).collect();
+
+        Node::new(self.polygons.clone())
+            .clip_segments(segments, inside)
+            .into_iter()
+            .flat_map(|[a, b]| [a, b].map(|p: Vector| Vertex::from(Point3d::from(<[f32; 3]>::from(p)))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::{Block, ModelEntityObject}, prelude::volume};
+
+    fn cube(min: f32, size: f32) -> Solid {
+        let min = Point3d::new(min, min, min);
+
+        Solid::from_vertices(&Block::two_points(min, min + Point3d::new(size, size, size)).vertices())
+    }
+
+    #[test]
+    fn overlapping_cubes() {
+        let (a, b) = (cube(0.0, 2.0), cube(1.0, 2.0));
+
+        assert!((volume(&a.union(&b).vertices()) - 15.0).abs() < 1e-4);
+        assert!((volume(&a.subtract(&b).vertices()) - 7.0).abs() < 1e-4);
+        assert!((volume(&a.intersect(&b).vertices()) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shared_faces() {
+        // the second cube sits on top of the first, and the third is cut from its corner
+        let (a, b) = (cube(0.0, 2.0), Solid::from_vertices(&Block::two_points(Point3d::new(0.0, 0.0, 2.0), Point3d::new(2.0, 2.0, 4.0)).vertices()));
+        assert!((volume(&a.union(&b).vertices()) - 16.0).abs() < 1e-4);
+
+        let corner = Solid::from_vertices(&Block::two_points(Point3d::new(1.0, 1.0, 1.0), Point3d::new(2.0, 2.0, 2.0)).vertices());
+        assert!((volume(&a.subtract(&corner).vertices()) - 7.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn disjoint_solids() {
+        let (a, b) = (cube(0.0, 1.0), cube(5.0, 1.0));
+
+        assert!(!a.overlaps(&b));
+        assert!((volume(&a.subtract(&b).vertices()) - 1.0).abs() < 1e-4);
+        assert!(a.intersect(&b).is_empty());
+    }
+
+    #[test]
+    fn lines_are_clipped() {
+        let solid = cube(0.0, 2.0);
+        let line = [Point3d::new(-1.0, 1.0, 1.0), Point3d::new(3.0, 1.0, 1.0)].map(Vertex::from);
+
+        let length = |lines: &[Vertex]| lines.chunks_exact(2)
+            .map(|l| (Point3d::from(l[1].position) - Point3d::from(l[0].position)).length())
+            .sum::<f32>();
+
+        assert!((length(&solid.clip_lines(&line, true)) - 2.0).abs() < 1e-5);
+        assert!((length(&solid.clip_lines(&line, false)) - 2.0).abs() < 1e-5);
+    }
+
+    /// whether every edge of the triangles is shared by exactly two of them
+    fn is_watertight(solid: &Solid) -> bool {
+        let mut uses: HashMap<[[u32; 3]; 2], usize> = HashMap::new();
+        for t in solid.vertices().chunks_exact(3) {
+            for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+                let mut key = [t[a].position, t[b].position].map(|p| p.map(f32::to_bits));
+                key.sort();
+                *uses.entry(key).or_default() += 1;
+            }
+        }
+
+        uses.values().all(|&n| n == 2)
+    }
+
+    #[test]
+    fn results_are_watertight() {
+        let (a, b) = (cube(0.0, 2.0), cube(1.0, 2.0));
+        let on_top = Solid::from_vertices(&Block::two_points(Point3d::new(0.5, 0.5, 2.0), Point3d::new(1.5, 1.5, 3.0)).vertices());
+        let through = Solid::from_vertices(&Block::two_points(Point3d::new(0.5, 0.5, -1.0), Point3d::new(1.5, 1.25, 3.0)).vertices());
+
+        for solid in [a.union(&b), a.subtract(&b), a.intersect(&b), a.union(&on_top), a.subtract(&through), a.subtract(&through).union(&b)] {
+            assert!(!solid.is_empty());
+            assert!(is_watertight(&solid));
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! entities that tests across the model share
+
+use crate::model::{Block, Hole, HoleKind, ModelEntity, HOLE_SEGMENTS};
+use super::{Point2d, Point3d};
+
+/// 10 x 10 x 5 block, with a corner at the origin
+pub fn drill_block() -> ModelEntity {
+    ModelEntity::Block(Block::origin_and_max(Point3d::new(10.0, 10.0, 5.0)))
+}
+
+/// index of the face of `block` that faces up
+pub fn top_face(block: &ModelEntity) -> usize {
+    block.faces().iter().position(|f| f.normal().z > 0.5).unwrap()
+}
+
+/// through hole of diameter 2 at (3, 3) in the top of the [`drill_block`] at history index `entity`
+pub fn drilled_hole(entity: usize) -> ModelEntity {
+    ModelEntity::Hole(Hole::new(entity, top_face(&drill_block()), Point2d::new(-2.0, -2.0), 2.0, None, HoleKind::Simple))
+}
+
+/// material a [`drilled_hole`] takes out of the block, a prism with a side per hole segment
+pub fn drilled_volume() -> f32 {
+    let segments = HOLE_SEGMENTS as f32;
+
+    5.0 * segments / 2.0 * (std::f32::consts::TAU / segments).sin()
+}
@@ -2,13 +2,21 @@
 pub type Display = glium::Display<glium::glutin::surface::WindowSurface>;
 
 pub mod buffer;
+pub mod csg;
+#[cfg(test)]
+pub mod fixture;
+pub mod polygon;
 mod point;
 mod vertex;
 
 pub use point::{Point2d, Point3d};
-pub use vertex::{Vertex, VertexBuffer};
+pub use vertex::{Instance, InstanceBuffer, Vertex, VertexBuffer};
+#[cfg(test)]
+pub use vertex::volume;
 
 /// register components with opengl compatibility
+#[allow(non_local_definitions)]
 pub fn register() {
     glium::implement_vertex!(Vertex, position, normal, texture);
+    glium::implement_vertex!(Instance, transform);
 }
@@ -1,34 +1,99 @@
-use std::ops::{Add, Div};
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
 use super::Vertex;
 
 
-pub type Point2d = winit::dpi::PhysicalPosition<f32>;
-// #[derive(Debug, Default, Clone, Copy)]
-// pub struct Point2d {
-//     pub x: f32,
-//     pub y: f32
-// }
-
-// impl Point2d {
-//     pub fn to_array(&self) -> [f32; 2] {
-//         [self.x, self.y]
-//     }
-// }
-
-// impl From<PhysicalSize<f64>> for Point2d {
-//     fn from(value: PhysicalSize<f64>) -> Self {
-//         Self { x: value.width as f32, y: value.height as f32 }
-//     }
-// }
-
-// impl From<PhysicalPosition<f64>> for Point2d {
-//     fn from(value: PhysicalPosition<f64>) -> Self {
-//         Self { x: value.x as f32, y: value.y as f32 }
-//     }
-// }
-
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Point2d {
+    pub x: f32,
+    pub y: f32
+}
+
+impl Point2d {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn to_array(&self) -> [f32; 2] {
+        [self.x, self.y]
+    }
+
+    pub fn dot(&self, rhs: &Self) -> f32 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    /// z component of the 3d cross product, positive if `rhs` is counter-clockwise of `self`
+    pub fn cross(&self, rhs: &Self) -> f32 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn distance(&self, rhs: &Self) -> f32 {
+        (*self - *rhs).length()
+    }
+
+    /// angle from the X axis in radians, in `(-PI, PI]`
+    pub fn angle(&self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    /// counter-clockwise perpendicular of the same length
+    pub fn perp(&self) -> Self {
+        Self { x: -self.y, y: self.x }
+    }
+}
+
+impl Add for Point2d {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl Sub for Point2d {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+
+impl Neg for Point2d {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self { x: -self.x, y: -self.y }
+    }
+}
+
+impl Mul<f32> for Point2d {
+    type Output = Self;
+    fn mul(self, m: f32) -> Self::Output {
+        Self { x: self.x * m, y: self.y * m }
+    }
+}
+
+impl Div<f32> for Point2d {
+    type Output = Self;
+    fn div(self, d: f32) -> Self::Output {
+        Self { x: self.x / d, y: self.y / d }
+    }
+}
+
+impl From<(f32, f32)> for Point2d {
+    fn from(value: (f32, f32)) -> Self {
+        Self { x: value.0, y: value.1 }
+    }
+}
+
+impl From<winit::dpi::PhysicalPosition<f64>> for Point2d {
+    fn from(value: winit::dpi::PhysicalPosition<f64>) -> Self {
+        Self { x: value.x as f32, y: value.y as f32 }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Point3d {
     pub x: f32,
     pub y: f32,
@@ -36,9 +101,37 @@ pub struct Point3d {
 }
 
 impl Point3d {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
     pub fn to_array(&self) -> [f32; 3] {
         [self.x, self.y, self.z]
     }
+
+    pub fn dot(&self, rhs: &Self) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn cross(&self, rhs: &Self) -> Self {
+        Self {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// unit vector in the same direction, or zero if the length is zero
+    pub fn normalize(&self) -> Self {
+        match self.length() {
+            len if len > f32::EPSILON => *self / len,
+            _ => Self::default()
+        }
+    }
 }
 
 impl Add for Point3d {
@@ -48,6 +141,27 @@ impl Add for Point3d {
     }
 }
 
+impl Sub for Point3d {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y, z: self.z - rhs.z }
+    }
+}
+
+impl Neg for Point3d {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self { x: -self.x, y: -self.y, z: -self.z }
+    }
+}
+
+impl Mul<f32> for Point3d {
+    type Output = Self;
+    fn mul(self, m: f32) -> Self::Output {
+        Self { x: self.x * m, y: self.y * m, z: self.z * m }
+    }
+}
+
 impl Div<f32> for Point3d {
     type Output = Self;
     fn div(self, d: f32) -> Self::Output {
@@ -55,9 +169,9 @@ impl Div<f32> for Point3d {
     }
 }
 
-impl Into<Vertex> for Point3d {
-    fn into(self) -> Vertex {
-        Vertex { position: self.to_array(), ..Default::default() }
+impl From<Point3d> for Vertex {
+    fn from(value: Point3d) -> Self {
+        Vertex { position: value.to_array(), ..Default::default() }
     }
 }
 
@@ -66,3 +180,9 @@ impl From<&[f32; 3]> for Point3d {
         Point3d { x: value[0], y: value[1], z: value[2] }
     }
 }
+
+impl From<[f32; 3]> for Point3d {
+    fn from(value: [f32; 3]) -> Self {
+        Point3d { x: value[0], y: value[1], z: value[2] }
+    }
+}
@@ -0,0 +1,348 @@
+
+//! polygon triangulation
+
+use super::{Point3d, Vertex};
+
+const EPSILON: f32 = 1e-7;
+
+/// unit normal of a planar polygon, using Newell's method so that any winding order works
+///
+/// Returns zero for degenerate polygons.
+pub fn normal(points: &[Point3d]) -> Point3d {
+    let mut normal = Point3d::default();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+
+    normal.normalize()
+}
+
+/// add a triangle to a triangle list, facing the side of `toward` and dropping it if it has no area
+pub fn push_triangle(data: &mut Vec<Vertex>, [a, b, c]: [Point3d; 3], toward: Point3d) {
+    let mut normal = (b - a).cross(&(c - a));
+    if normal.length() <= f32::EPSILON {
+        return;
+    }
+
+    let corners = match normal.dot(&toward) < 0.0 {
+        true => {
+            normal = -normal;
+            [a, c, b]
+        },
+        false => [a, b, c],
+    };
+
+    let normal = normal.normalize().to_array();
+    data.extend(corners.iter().map(|p| Vertex { position: p.to_array(), normal, ..Default::default() }));
+}
+
+/// split a simple planar polygon into triangles, keeping the winding of the polygon
+///
+/// Convex polygons are fanned from the first point, concave ones are ear clipped.
+pub fn triangulate(points: &[Point3d]) -> Vec<[usize; 3]> {
+    if points.len() < 4 {
+        return fan(points.len());
+    }
+
+    let n = normal(points);
+    if n.length() == 0.0 {
+        return fan(points.len());
+    }
+
+    // any pair of axes perpendicular to the normal works, as long as (u, v, n) is right handed
+    let helper = if n.x.abs() < 0.9 { Point3d::new(1.0, 0.0, 0.0) } else { Point3d::new(0.0, 1.0, 0.0) };
+    let u = helper.cross(&n).normalize();
+    let v = n.cross(&u);
+
+    let projected: Vec<[f32; 2]> = points.iter()
+        .map(|p| [p.dot(&u), p.dot(&v)])
+        .collect();
+
+    triangulate_2d(&projected)
+}
+
+/// split a simple polygon into triangles, keeping the winding of the polygon
+pub fn triangulate_2d(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    if points.len() < 4 || is_convex(points) {
+        fan(points.len())
+    } else {
+        ear_clip(points)
+    }
+}
+
+/// split a polygon with holes into triangles, keeping the winding of the outer boundary
+///
+/// Each hole is joined to the boundary by a bridge, which gives one polygon that is
+/// ear clipped. Holes must wind against the boundary. Returns the joined points,
+/// which the triangles index.
+pub fn triangulate_with_holes(outer: &[[f32; 2]], holes: &[Vec<[f32; 2]>]) -> (Vec<[f32; 2]>, Vec<[usize; 3]>) {
+    let holes: Vec<&Vec<[f32; 2]>> = holes.iter().filter(|h| h.len() >= 3).collect();
+    if holes.is_empty() {
+        return (outer.to_vec(), triangulate_2d(outer));
+    }
+
+    let mut polygon = outer.to_vec();
+    for (i, hole) in holes.iter().enumerate() {
+        // bridges may not cross the holes that are still to be joined
+        let others: Vec<&[[f32; 2]]> = holes[i + 1..].iter().map(|h| h.as_slice()).collect();
+        polygon = bridge(&polygon, hole, &others);
+    }
+
+    let triangles = ear_clip(&polygon);
+    (polygon, triangles)
+}
+
+/// whether `p` is inside a polygon of either winding
+pub fn contains(points: &[[f32; 2]], p: [f32; 2]) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+
+        if (a[1] > p[1]) != (b[1] > p[1]) && p[0] < a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+/// join a hole to a polygon through the closest pair of points that see each other
+fn bridge(polygon: &[[f32; 2]], hole: &[[f32; 2]], others: &[&[[f32; 2]]]) -> Vec<[f32; 2]> {
+    let crosses = |a: [f32; 2], b: [f32; 2]| {
+        std::iter::once(polygon).chain(std::iter::once(hole)).chain(others.iter().copied())
+            .any(|ring| (0..ring.len()).any(|i| crosses(a, b, ring[i], ring[(i + 1) % ring.len()])))
+    };
+
+    let distance = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+    let mut pairs: Vec<(usize, usize, f32)> = (0..hole.len())
+        .flat_map(|h| (0..polygon.len()).map(move |p| (h, p)))
+        .map(|(h, p)| (h, p, distance(hole[h], polygon[p])))
+        .collect();
+    pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let (h, p) = match pairs.iter().find(|(h, p, _)| !crosses(hole[*h], polygon[*p])) {
+        Some((h, p, _)) => (*h, *p),
+        None => {
+            log::warn!("no bridge to a hole without crossing an edge, using the closest points");
+            (pairs[0].0, pairs[0].1)
+        }
+    };
+
+    let mut joined = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    joined.extend_from_slice(&polygon[..=p]);
+    joined.extend((0..=hole.len()).map(|i| hole[(h + i) % hole.len()]));
+    joined.extend_from_slice(&polygon[p..]);
+
+    joined
+}
+
+/// whether two segments cross at a point that is not an end of either
+fn crosses(a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) -> bool {
+    if [c, d].contains(&a) || [c, d].contains(&b) {
+        return false;
+    }
+
+    let (d1, d2) = (cross(a, b, c), cross(a, b, d));
+    let (d3, d4) = (cross(c, d, a), cross(c, d, b));
+
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// twice the signed area, positive for counter-clockwise polygons
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    points.iter()
+        .enumerate()
+        .map(|(i, a)| {
+            let b = points[(i + 1) % points.len()];
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum()
+}
+
+fn fan(len: usize) -> Vec<[usize; 3]> {
+    (1..len.saturating_sub(1))
+        .map(|i| [0, i, i + 1])
+        .collect()
+}
+
+fn cross(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn is_convex(points: &[[f32; 2]]) -> bool {
+    let len = points.len();
+    let mut sign = 0.0;
+
+    for i in 0..len {
+        let turn = cross(points[i], points[(i + 1) % len], points[(i + 2) % len]);
+        if turn.abs() <= EPSILON {
+            continue;
+        }
+
+        if sign == 0.0 {
+            sign = turn.signum();
+        } else if turn.signum() != sign {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn ear_clip(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    // orientation of the polygon, so clockwise polygons work without reversing them
+    let orientation = signed_area(points).signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let mut clipped = false;
+
+        for i in 0..len {
+            let (prev, cur, next) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+            let (a, b, c) = (points[prev], points[cur], points[next]);
+            let turn = cross(a, b, c) * orientation;
+
+            // collinear points add nothing, so they are dropped without a triangle
+            if turn.abs() <= EPSILON {
+                remaining.remove(i);
+                clipped = true;
+                break;
+            }
+
+            // reflex corners cannot be ears
+            if turn < 0.0 {
+                continue;
+            }
+
+            // bridged holes repeat points, which do not block the ears they are a corner of
+            let contains_other = remaining.iter()
+                .filter(|&&j| j != prev && j != cur && j != next)
+                .filter(|&&j| ![a, b, c].contains(&points[j]))
+                .any(|&j| in_triangle(points[j], a, b, c, orientation));
+
+            if !contains_other {
+                triangles.push([prev, cur, next]);
+                remaining.remove(i);
+                clipped = true;
+                break;
+            }
+        }
+
+        // self-intersecting or numerically degenerate input, so fan whatever is left
+        if !clipped {
+            log::warn!("ear clipping failed with {} points left, using a fan", remaining.len());
+            triangles.extend((1..remaining.len() - 1).map(|i| [remaining[0], remaining[i], remaining[i + 1]]));
+            return triangles;
+        }
+    }
+
+    if let [a, b, c] = remaining[..] {
+        if cross(points[a], points[b], points[c]).abs() > EPSILON {
+            triangles.push([a, b, c]);
+        }
+    }
+
+    triangles
+}
+
+/// whether `p` is inside or on the edge of the triangle `abc`
+fn in_triangle(p: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2], orientation: f32) -> bool {
+    cross(a, b, p) * orientation >= 0.0
+        && cross(b, c, p) * orientation >= 0.0
+        && cross(c, a, p) * orientation >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(points: &[[f32; 2]], triangles: &[[usize; 3]]) -> f32 {
+        triangles.iter()
+            .map(|t| cross(points[t[0]], points[t[1]], points[t[2]]))
+            .sum()
+    }
+
+    #[test]
+    fn convex_is_fanned() {
+        let square = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+        assert_eq!(triangulate_2d(&square), [[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn concave_is_clipped() {
+        // L shape, where fanning from the first point would cover the notch
+        let shape = [[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]];
+        let triangles = triangulate_2d(&shape);
+
+        assert_eq!(triangles.len(), 4);
+        assert!((area(&shape, &triangles) - signed_area(&shape)).abs() < 1e-5);
+
+        // every triangle keeps the winding of the polygon
+        for t in triangles.iter() {
+            assert!(cross(shape[t[0]], shape[t[1]], shape[t[2]]) > 0.0);
+        }
+    }
+
+    #[test]
+    fn clockwise_concave_keeps_winding() {
+        let mut shape = vec![[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [2.0, 1.0], [0.0, 4.0]];
+        shape.reverse();
+        let triangles = triangulate_2d(&shape);
+
+        assert_eq!(triangles.len(), 3);
+        assert!((area(&shape, &triangles) - signed_area(&shape)).abs() < 1e-5);
+        for t in triangles.iter() {
+            assert!(cross(shape[t[0]], shape[t[1]], shape[t[2]]) < 0.0);
+        }
+    }
+
+    #[test]
+    fn hole_is_left_open() {
+        let outer = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let hole = vec![[1.0, 1.0], [1.0, 3.0], [3.0, 3.0], [3.0, 1.0]];
+        let (points, triangles) = triangulate_with_holes(&outer, &[hole]);
+
+        assert!((area(&points, &triangles) - 2.0 * 12.0).abs() < 1e-4);
+        for t in triangles.iter() {
+            assert!(cross(points[t[0]], points[t[1]], points[t[2]]) >= 0.0);
+
+            let centroid = [0, 1].map(|k| (points[t[0]][k] + points[t[1]][k] + points[t[2]][k]) / 3.0);
+            assert!(!(1.0 < centroid[0] && centroid[0] < 3.0 && 1.0 < centroid[1] && centroid[1] < 3.0));
+        }
+    }
+
+    #[test]
+    fn point_in_polygon() {
+        let shape = [[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]];
+
+        assert!(contains(&shape, [0.5, 1.5]));
+        assert!(!contains(&shape, [1.5, 1.5]));
+        assert!(!contains(&shape, [3.0, 0.5]));
+    }
+
+    #[test]
+    fn planar_3d_polygon() {
+        // concave polygon on the YZ plane
+        let points: Vec<Point3d> = [[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]]
+            .iter()
+            .map(|p| Point3d::new(3.0, p[0], p[1]))
+            .collect();
+
+        assert_eq!(normal(&points), Point3d::new(1.0, 0.0, 0.0));
+
+        let triangles = triangulate(&points);
+        assert_eq!(triangles.len(), 4);
+        for t in triangles.iter() {
+            let [a, b, c] = t.map(|i| points[i]);
+            assert!((b - a).cross(&(c - a)).x > 0.0);
+        }
+    }
+}
@@ -1,9 +1,60 @@
 
-pub type VertexBuffer = glium::vertex::VertexBuffer<Vertex>;
-
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
     pub texture: [f32; 2],
 }
+
+pub type VertexBuffer = glium::vertex::VertexBuffer<Vertex>;
+
+/// placement of one drawn copy of a vertex buffer
+///
+/// `transform` is a column-major rigid transform, so normals only need its rotation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Instance {
+    pub transform: [[f32; 4]; 4],
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            transform: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+}
+
+impl Instance {
+    /// copy of `vertex` where this instance places it
+    pub fn vertex(&self, vertex: &Vertex) -> Vertex {
+        let m = &self.transform;
+        let apply = |v: [f32; 3], w: f32| [0, 1, 2].map(|r| m[0][r] * v[0] + m[1][r] * v[1] + m[2][r] * v[2] + m[3][r] * w);
+
+        Vertex {
+            position: apply(vertex.position, 1.0),
+            normal: apply(vertex.normal, 0.0),
+            ..*vertex
+        }
+    }
+}
+
+/// volume enclosed by a triangle list, by the divergence theorem
+///
+/// Only adds up for closed meshes with outward facing triangles, which is what the tests check.
+#[cfg(test)]
+pub fn volume(vertices: &[Vertex]) -> f32 {
+    vertices.chunks_exact(3)
+        .map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|v| super::Point3d::from(v.position));
+            a.dot(&b.cross(&c)) / 6.0
+        })
+        .sum()
+}
+
+/// per-instance attributes, drawn with `per_instance()`
+pub type InstanceBuffer = glium::vertex::VertexBuffer<Instance>;
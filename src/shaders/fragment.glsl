@@ -1,12 +1,30 @@
 #version 140
 
+uniform vec3 ambient;
+uniform vec3 diffuse;
+uniform vec3 specular;
+uniform float shininess;
+uniform float opacity;
+
 in vec3 v_normal;
 out vec4 f_color;
 
 const vec3 LIGHT_DIRECTION = vec3(-0.2, 0.4, 0.1);
+const vec3 VIEW_DIRECTION = vec3(0.0, 0.0, 1.0);
 
 void main() {
-    float lum = max(dot(normalize(v_normal), normalize(LIGHT_DIRECTION)), 0.0);
-    vec3 color = (0.3 + 0.7 * lum) * vec3(0.94, 0.68, 0.28);
-    f_color = vec4(color, 1.0);
-}
\ No newline at end of file
+    // lines have no normal, so they are drawn unlit
+    if (length(v_normal) == 0.0) {
+        f_color = vec4((0.3 * ambient + 0.7) * diffuse, opacity);
+        return;
+    }
+
+    vec3 normal = normalize(v_normal);
+    vec3 light = normalize(LIGHT_DIRECTION);
+
+    float lum = max(dot(normal, light), 0.0);
+    float spec = pow(max(dot(normal, normalize(light + VIEW_DIRECTION)), 0.0), shininess);
+
+    vec3 color = (0.3 * ambient + 0.7 * lum) * diffuse + spec * specular;
+    f_color = vec4(color, opacity);
+}
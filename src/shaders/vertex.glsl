@@ -8,11 +8,13 @@ uniform mat4 rotz_matrix;
 
 in vec3 position;
 in vec3 normal;
+// placement of this copy of the buffer, the identity for most entities
+in mat4 transform;
 out vec3 v_position;
 out vec3 v_normal;
 
 void main() {
-    v_position = position;
-    v_normal = normal;
+    v_position = (transform * vec4(position, 1.0)).xyz;
+    v_normal = mat3(transform) * normal;
     gl_Position = persp_matrix * view_matrix * rotx_matrix * roty_matrix * rotz_matrix * vec4(v_position * 0.005, 1.0);
 }
@@ -5,14 +5,15 @@ use egui_glium::EguiGlium;
 use glium::{uniform, Surface};
 
 use winit::{
+    event::{ElementState, KeyboardInput, ModifiersState, VirtualKeyCode, WindowEvent},
     event_loop::ControlFlow,
     window::Window,
 };
 
-use crate::env::ApplicationEnvironmentType;
-use crate::model::Block;
+use crate::env::{ApplicationEnvironmentType, Sketcher};
+use crate::model::Material;
 use crate::prelude::*;
-use crate::ui::UiDrawResult;
+use crate::ui::{UiDrawResult, block::BlockDialog, combine::CombineDialog, edit::EditDialog, extrude::ExtrudeDialog, fillet::FilletDialog, hole::HoleDialog, pattern::PatternDialog, revolve::RevolveDialog, sketch::SketchPlaneDialog};
 use crate::ui::menu::MenuResult;
 use crate::{
     env,
@@ -24,6 +25,8 @@ use crate::{
 // cannot #[derive(Debug)] because EguiGlium does not implement Debug
 pub struct State {
     program: glium::Program,
+    /// single instance, for geometry that is drawn where its vertices are
+    single: Option<InstanceBuffer>,
     ui: EguiGlium,
     
     env: env::ApplicationEnvironment,
@@ -31,7 +34,16 @@ pub struct State {
 
     show_settings: bool,
     show_dialog: bool,
-    dialog_vals: Vec<f32>,
+    block_dialog: BlockDialog,
+    edit_dialog: Option<EditDialog>,
+    extrude_dialog: Option<ExtrudeDialog>,
+    revolve_dialog: Option<RevolveDialog>,
+    combine_dialog: Option<CombineDialog>,
+    fillet_dialog: Option<FilletDialog>,
+    pattern_dialog: Option<PatternDialog>,
+    hole_dialog: Option<HoleDialog>,
+    sketch_dialog: Option<SketchPlaneDialog>,
+    modifiers: ModifiersState,
     status: String,
 }
 
@@ -46,16 +58,30 @@ impl ApplicationState for State {
             crate::shaders::FRAGMENT_SRC,
             None    // geometry shader
         ).unwrap();
+
+        let single = crate::prelude::buffer::identity_instances(display)
+            .map_err(|e| log::error!("Failed to produce InstanceBuffer <{}>", e))
+            .ok();
         
         Self {
             program,
-            ui: EguiGlium::new(&display, &window, &event_loop),
+            single,
+            ui: EguiGlium::new(display, window, event_loop),
             env: env::ApplicationEnvironment::new(),
             model: Model::new(),
 
             show_settings: false,
             show_dialog: false,
-            dialog_vals: vec![3.0, 4.0, 5.0],
+            block_dialog: BlockDialog::new(),
+            edit_dialog: None,
+            extrude_dialog: None,
+            revolve_dialog: None,
+            combine_dialog: None,
+            fillet_dialog: None,
+            pattern_dialog: None,
+            hole_dialog: None,
+            sketch_dialog: None,
+            modifiers: ModifiersState::empty(),
             status: String::from("no model loaded"),
         }
     }
@@ -64,15 +90,22 @@ impl ApplicationState for State {
         self.env.update();
     }
 
-    fn handle_window_event(&mut self, event: &winit::event::WindowEvent, _window: &winit::window::Window) {
-        if !self.ui.on_event(&event).consumed {
-            self.env.process_input(&event);
+    fn handle_window_event(&mut self, event: &WindowEvent, _window: &winit::window::Window) {
+        if let WindowEvent::ModifiersChanged(modifiers) = event {
+            self.modifiers = *modifiers;
+        }
+
+        if !self.ui.on_event(event).consumed && !self.handle_shortcut(event) {
+            self.env.process_input(event);
         }
         
     }
 
     fn draw_ui(&mut self, control_flow: &mut ControlFlow, window: &Window) {
-        self.ui.run(&window, |ctx| {
+        // whether the history changed in a way that moves entities to other indices
+        let mut reindexed = false;
+
+        self.ui.run(window, |ctx| {
 
             if self.show_settings {
                 egui::Window::new("settings")
@@ -80,17 +113,20 @@ impl ApplicationState for State {
                     .show(ctx, |ui| ctx.settings_ui(ui));
             }
 
-            egui::TopBottomPanel::top("menu").show(&ctx, |ui| {
+            egui::TopBottomPanel::top("menu").show(ctx, |ui| {
                 ui.horizontal(|ui| {
-                    // TODO: fix Obj save (saves faces with textures, not vertex normals)
-                    if let Some(res) = crate::ui::menu::ui(ui, control_flow) {
+                    if let Some(res) = crate::ui::menu::ui(ui, control_flow, &self.model) {
                         // handle result
                         match res {
-                            MenuResult::New => self.model = Model::new(),
+                            MenuResult::New => {
+                                self.model.clear();
+                                reindexed = true;
+                            },
                             MenuResult::Open(path) => {
                                 if let Err(e) = self.model.load(path) {
                                     log::error!("Failed to open part <{}>", e)
                                 }
+                                reindexed = true;
                             },
                             MenuResult::Save(path) => {
                                 if let Err(e) = self.model.save(&path) {
@@ -102,6 +138,30 @@ impl ApplicationState for State {
                                     log::error!("Failed to load Obj file part <{}>", e)
                                 }
                             },
+                            MenuResult::ImportStl(path) => {
+                                if let Err(e) = self.model.load_stl(&path) {
+                                    log::error!("Failed to load Stl file part <{}>", e)
+                                }
+                            },
+                            MenuResult::ExportObj(path) => {
+                                if let Err(e) = self.model.export_obj(&path) {
+                                    log::error!("Failed to export Obj file <{}>", e)
+                                }
+                            },
+                            MenuResult::ExportStl(path) => {
+                                if let Err(e) = self.model.export_stl(&path) {
+                                    log::error!("Failed to export Stl file <{}>", e)
+                                }
+                            },
+                            // `self.undo()` would borrow all of `self` inside the ui closure
+                            MenuResult::Undo => {
+                                self.model.undo();
+                                reindexed = true;
+                            },
+                            MenuResult::Redo => {
+                                self.model.redo();
+                                reindexed = true;
+                            },
                             MenuResult::Settings => {
                                 self.show_settings = true;
                             }
@@ -112,7 +172,11 @@ impl ApplicationState for State {
                     ui.horizontal(|ui| {
                         if let Some(switch) = self.env.draw_toolbar(ui) {
                             let res = match switch {
-                                UiDrawResult::EnterSketcher => Some(ApplicationEnvironmentType::Sketching(self.env.deref().into())),
+                                UiDrawResult::EnterSketcher => {
+                                    self.sketch_dialog = Some(SketchPlaneDialog::new());
+
+                                    None
+                                },
                                 UiDrawResult::ExitSketcher(sketch) => {
                                     if let Some(sketch) = sketch {
                                         self.model.push(sketch);
@@ -123,6 +187,46 @@ impl ApplicationState for State {
                                 UiDrawResult::ShowBlockDialog => {
                                     self.show_dialog = true;
 
+                                    None
+                                },
+                                UiDrawResult::ShowExtrudeDialog => {
+                                    self.extrude_dialog = Some(ExtrudeDialog::new(&self.model, false));
+
+                                    None
+                                },
+                                UiDrawResult::ShowRevolveDialog => {
+                                    self.revolve_dialog = Some(RevolveDialog::new(&self.model));
+
+                                    None
+                                },
+                                UiDrawResult::ShowCutDialog => {
+                                    self.extrude_dialog = Some(ExtrudeDialog::new(&self.model, true));
+
+                                    None
+                                },
+                                UiDrawResult::ShowHoleDialog => {
+                                    self.hole_dialog = Some(HoleDialog::new(&self.model));
+
+                                    None
+                                },
+                                UiDrawResult::ShowCombineDialog => {
+                                    self.combine_dialog = Some(CombineDialog::new(&self.model));
+
+                                    None
+                                },
+                                UiDrawResult::ShowFilletDialog => {
+                                    self.fillet_dialog = Some(FilletDialog::new(&self.model, false));
+
+                                    None
+                                },
+                                UiDrawResult::ShowChamferDialog => {
+                                    self.fillet_dialog = Some(FilletDialog::new(&self.model, true));
+
+                                    None
+                                },
+                                UiDrawResult::ShowPatternDialog => {
+                                    self.pattern_dialog = Some(PatternDialog::new(&self.model));
+
                                     None
                                 }
                             };
@@ -148,46 +252,277 @@ impl ApplicationState for State {
                     .collapsible(false)
                     .open(&mut self.show_dialog)
                     .show(ctx, |ui| {
-                        egui::Grid::new("dialog_table")
-                            .num_columns(2)
-                            .striped(true)
-                            .show(ui, |ui| {
-                                ui.label("Length");
-                                ui.add(egui::DragValue::new(&mut self.dialog_vals[0]).speed(1.0));
-                                ui.end_row();
-
-                                ui.label("Width");
-                                ui.add(egui::DragValue::new(&mut self.dialog_vals[1]).speed(1.0));
-                                ui.end_row();
-
-                                ui.label("Height");
-                                ui.add(egui::DragValue::new(&mut self.dialog_vals[2]).speed(1.0));
-                                ui.end_row();
-                            });
+                        self.block_dialog.ui(ui);
 
                         ui.separator();
                         
                         ui.horizontal(|ui| {
                             if ui.button("Create Block").clicked() {
-                                let point = Point3d { x: self.dialog_vals[0], y: self.dialog_vals[1], z: self.dialog_vals[2] };
-                                self.model.push(crate::model::ModelEntity::Block(Block::origin_and_max(point)));
+                                self.model.push(crate::model::ModelEntity::Block(self.block_dialog.block()));
 
                                 show = false;
                             }
                         });
                     });
 
-                self.show_dialog = show;
+                // window may also have been closed from its title bar
+                self.show_dialog &= show;
+            }
+    
+            if let Some(dialog) = &mut self.sketch_dialog {
+                let mut open = true;
+                let mut plane = None;
+                egui::Window::new("Sketch plane")
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let selected = dialog.plane(&self.model);
+                        if ui.add_enabled(selected.is_some(), egui::Button::new("Start Sketch")).clicked() {
+                            plane = selected;
+                        }
+                    });
+
+                let started = plane.is_some();
+                if let Some(plane) = plane {
+                    let sketcher = Sketcher::from(self.env.deref())
+                        .on_plane(plane)
+                        .with_edges(&self.model.edges());
+                    *self.env.deref_mut() = ApplicationEnvironmentType::Sketching(Box::new(sketcher));
+                }
+
+                if !open || started {
+                    self.sketch_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.extrude_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new(dialog.title())
+                    .id(egui::Id::new("extrude_dialog"))
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let entity = dialog.entity();
+                        if ui.add_enabled(entity.is_some(), egui::Button::new(format!("Create {}", dialog.title()))).clicked() {
+                            if let Some(entity) = entity {
+                                self.model.push(entity);
+                            }
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.extrude_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.revolve_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new("Revolve")
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let revolve = dialog.revolve(&self.model);
+                        if ui.add_enabled(revolve.is_some(), egui::Button::new("Create Revolve")).clicked() {
+                            if let Some(revolve) = revolve {
+                                self.model.push(crate::model::ModelEntity::Revolve(revolve));
+                            }
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.revolve_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.hole_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new("Hole")
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        if ui.add_enabled(dialog.is_valid(&self.model), egui::Button::new("Create Hole")).clicked() {
+                            self.model.push(crate::model::ModelEntity::Hole(dialog.hole()));
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.hole_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.combine_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new("Combine")
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let combine = dialog.combine();
+                        if ui.add_enabled(combine.is_some(), egui::Button::new("Create Combine")).clicked() {
+                            if let Some(combine) = combine {
+                                self.model.push(crate::model::ModelEntity::Combine(combine));
+                            }
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.combine_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.fillet_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new(dialog.title())
+                    .id(egui::Id::new("fillet_dialog"))
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let entity = dialog.entity().filter(|_| dialog.error(&self.model).is_none());
+                        if ui.add_enabled(entity.is_some(), egui::Button::new(format!("Create {}", dialog.title()))).clicked() {
+                            if let Some(entity) = entity {
+                                self.model.push(entity);
+                            }
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.fillet_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.pattern_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new(dialog.title())
+                    .id(egui::Id::new("pattern_dialog"))
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        let pattern = dialog.pattern();
+                        if ui.add_enabled(pattern.is_some(), egui::Button::new(format!("Create {}", dialog.title()))).clicked() {
+                            if let Some(pattern) = pattern {
+                                self.model.push(crate::model::ModelEntity::Pattern(pattern));
+                            }
+
+                            done = true;
+                        }
+                    });
+
+                if !open || done {
+                    self.pattern_dialog = None;
+                }
+            }
+
+            if let Some(dialog) = &mut self.edit_dialog {
+                let mut open = true;
+                let mut done = false;
+                egui::Window::new(dialog.title())
+                    .id(egui::Id::new("edit_dialog"))
+                    .collapsible(false)
+                    .open(&mut open)
+                    .show(ctx, |ui| {
+                        dialog.ui(ui, &self.model);
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            let apply = ui.button("Apply").clicked();
+                            done = ui.button("OK").clicked();
+
+                            if apply || done {
+                                let index = dialog.index();
+                                if let Some(edited) = dialog.apply(&self.model) {
+                                    self.model.replace(index, edited);
+                                }
+
+                                // stay open to show what could not be applied
+                                done &= !dialog.rejected(&self.model);
+                            }
+                        });
+                    });
+
+                if !open || done {
+                    self.edit_dialog = None;
+                }
             }
     
             // model history panel
-            egui::SidePanel::left("toolbar").show(&ctx, |ui| {
-                for geo in self.model.entities() {
-                    ui.label(geo);
+            egui::SidePanel::left("toolbar").show(ctx, |ui| {
+                let mut remove = None;
+                for (i, geo) in self.model.entities().into_iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let mut visible = self.model.is_visible(i);
+                        if ui.checkbox(&mut visible, "").changed() {
+                            self.model.set_visible(i, visible);
+                        }
+
+                        let editing = self.edit_dialog.as_ref().is_some_and(|d| d.index() == i);
+                        if ui.selectable_label(editing, geo).on_hover_text("edit").clicked() {
+                            self.edit_dialog = Some(EditDialog::new(i, self.model.entity(i)));
+                        }
+
+                        if ui.small_button("⏶").on_hover_text("move up").clicked() {
+                            self.model.move_up(i);
+
+                            // history indices changed under the dialogs
+                            reindexed = true;
+                        }
+
+                        if ui.small_button("🗑").on_hover_text("delete").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+
+                if let Some(i) = remove {
+                    self.model.remove(i);
+                    reindexed = true;
                 }
             });
     
-            egui::TopBottomPanel::bottom("statusbar").show(&ctx, |ui| {
+            egui::TopBottomPanel::bottom("statusbar").show(ctx, |ui| {
                 ui.horizontal_centered(|ui| {
                     let rotpos = match self.env.camera.lock() {
                         Ok(camera) => (
@@ -201,7 +536,7 @@ impl ApplicationState for State {
                         Err(_) => ( 0f32, 0f32, 0f32, 0f32, 0f32, 0f32, ),
                     };
 
-                    ui.label(&format!(
+                    ui.label(format!(
                         "🔄 <{:.2}, {:.2}, {:.2}> | ↔ <{:.2}, {:.2}, {:.2}>",
                         rotpos.0, rotpos.1, rotpos.2, rotpos.3, rotpos.4, rotpos.5,
                     ));
@@ -212,22 +547,26 @@ impl ApplicationState for State {
                 });
             });
         });
+
+        if reindexed {
+            self.close_dialogs();
+        }
     }
 
     fn draw_frame(&mut self, display: &Display) {
         let mut frame = display.draw();
 
-        // building the uniforms
-        let uniforms = {
+        // camera matrices are shared by every entity
+        let (persp_matrix, view_matrix, rotx_matrix, roty_matrix, rotz_matrix) = {
             let camera = self.env.camera.lock().unwrap();
 
-            uniform! {
-                persp_matrix: camera.get_perspective(),
-                view_matrix:  camera.get_view(),
-                rotx_matrix:  camera.get_x_rotation(),
-                roty_matrix:  camera.get_y_rotation(),
-                rotz_matrix:  camera.get_z_rotation(),
-            }
+            (
+                camera.get_perspective(),
+                camera.get_view(),
+                camera.get_x_rotation(),
+                camera.get_y_rotation(),
+                camera.get_z_rotation(),
+            )
         };
 
         // draw parameters
@@ -239,24 +578,120 @@ impl ApplicationState for State {
             },
 
             line_width: Some(5f32),
+            blend: glium::Blend::alpha_blending(),
 
             ..Default::default()
         };
 
         frame.clear_color_and_depth((0.18, 0.25, 0.4, 1.0), 1.0);
-        frame
-            .draw(
-                self.model.vertex_buffer(display),
-                &glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-                &self.program,
-                &uniforms,
-                &params,
-            )
-            .unwrap();
+        let program = &self.program;
+        let mut draw = |vertices: &VertexBuffer, instances: &InstanceBuffer, primitive, material: &Material| {
+            if vertices.len() == 0 || instances.len() == 0 {
+                return;
+            }
+
+            // copies of an entity share its vertices, placed by a transform per instance
+            let instances = match instances.per_instance() {
+                Ok(instances) => instances,
+                Err(e) => {
+                    log::error!("Failed to draw instances <{:?}>", e);
+                    return;
+                }
+            };
+
+            let uniforms = uniform! {
+                persp_matrix: persp_matrix,
+                view_matrix:  view_matrix,
+                rotx_matrix:  rotx_matrix,
+                roty_matrix:  roty_matrix,
+                rotz_matrix:  rotz_matrix,
+
+                ambient:   material.ambient,
+                diffuse:   material.diffuse,
+                specular:  material.specular,
+                shininess: material.shininess,
+                opacity:   material.opacity,
+            };
+
+            frame
+                .draw(
+                    (vertices, instances),
+                    glium::index::NoIndices(primitive),
+                    program,
+                    &uniforms,
+                    &params,
+                )
+                .unwrap();
+        };
+
+        let outline = Material::outline();
+        for buffer in self.model.buffers(display) {
+            draw(&buffer.vertices, &buffer.instances, glium::index::PrimitiveType::TrianglesList, &buffer.material);
+            draw(&buffer.edges, &buffer.instances, glium::index::PrimitiveType::LinesList, &outline);
+        }
+
+        // geometry being edited, such as the sketch in progress
+        let highlight = Material::highlight();
+        for (data, material) in [(self.env.preview(), &outline), (self.env.highlight(), &highlight)] {
+            let Some(single) = self.single.as_ref().filter(|_| !data.is_empty()) else {
+                continue;
+            };
+
+            match VertexBuffer::new(display, &data) {
+                Ok(buffer) => draw(&buffer, single, glium::index::PrimitiveType::LinesList, material),
+                Err(e) => log::error!("Failed to produce preview VertexBuffer <{}>", e)
+            }
+        }
 
         // draw egui ui last so that render space does not overlap
         self.ui.paint(display, &mut frame);
 
         frame.finish().unwrap();
     }
-}
\ No newline at end of file
+}
+
+impl State {
+    fn undo(&mut self) {
+        self.model.undo();
+
+        // history indices may have changed under the dialogs
+        self.close_dialogs();
+    }
+
+    fn redo(&mut self) {
+        self.model.redo();
+        self.close_dialogs();
+    }
+
+    /// close every dialog that holds history indices, which would otherwise point at other entities
+    fn close_dialogs(&mut self) {
+        self.edit_dialog = None;
+        self.extrude_dialog = None;
+        self.revolve_dialog = None;
+        self.combine_dialog = None;
+        self.fillet_dialog = None;
+        self.pattern_dialog = None;
+        self.hole_dialog = None;
+        self.sketch_dialog = None;
+    }
+
+    /// application wide key bindings, returns whether the event was handled
+    fn handle_shortcut(&mut self, event: &WindowEvent) -> bool {
+        let keycode = match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(keycode), .. },
+                ..
+            } if self.modifiers.ctrl() => keycode,
+            _ => return false
+        };
+
+        match keycode {
+            VirtualKeyCode::Z if self.modifiers.shift() => self.redo(),
+            VirtualKeyCode::Z => self.undo(),
+            VirtualKeyCode::Y => self.redo(),
+            _ => return false
+        }
+
+        true
+    }
+}
@@ -0,0 +1,155 @@
+
+use crate::model::{Anchor, Block, Orientation};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Placement {
+    Anchored,
+    TwoCorners,
+}
+
+/// fields of the block dialog
+#[derive(Debug)]
+pub struct BlockDialog {
+    placement: Placement,
+
+    origin: [f32; 3],
+    dim: [f32; 3],
+    anchor: Anchor,
+    orientation: Orientation,
+
+    corners: [[f32; 3]; 2],
+}
+
+impl Default for BlockDialog {
+    fn default() -> Self {
+        Self {
+            placement: Placement::Anchored,
+
+            origin: [0.0, 0.0, 0.0],
+            dim: [3.0, 4.0, 5.0],
+            anchor: Anchor::Corner,
+            orientation: Orientation::XY,
+
+            corners: [[0.0, 0.0, 0.0], [3.0, 4.0, 5.0]],
+        }
+    }
+}
+
+impl BlockDialog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// fields of an existing block
+    ///
+    /// The corners are where the block is, whatever its anchor and orientation.
+    pub fn from_block(block: &Block) -> Self {
+        let (min, max) = block.bounds();
+
+        Self {
+            placement: Placement::Anchored,
+
+            origin: block.origin().to_array(),
+            dim: block.dim().to_array(),
+            anchor: block.anchor(),
+            orientation: block.orientation(),
+
+            corners: [min.to_array(), max.to_array()],
+        }
+    }
+
+    /// block described by the current fields
+    pub fn block(&self) -> Block {
+        match self.placement {
+            Placement::Anchored => Block::new(
+                self.origin.into(),
+                self.dim.into(),
+                self.anchor,
+                self.orientation
+            ),
+            Placement::TwoCorners => Block::two_points(self.corners[0].into(), self.corners[1].into()),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.placement, Placement::Anchored, "Anchor point");
+            ui.radio_value(&mut self.placement, Placement::TwoCorners, "Two corners");
+        });
+
+        egui::Grid::new("dialog_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                match self.placement {
+                    Placement::Anchored => {
+                        point_row(ui, "Origin", &mut self.origin);
+
+                        ui.label("Length");
+                        ui.add(egui::DragValue::new(&mut self.dim[0]).speed(1.0));
+                        ui.end_row();
+
+                        ui.label("Width");
+                        ui.add(egui::DragValue::new(&mut self.dim[1]).speed(1.0));
+                        ui.end_row();
+
+                        ui.label("Height");
+                        ui.add(egui::DragValue::new(&mut self.dim[2]).speed(1.0));
+                        ui.end_row();
+
+                        ui.label("Anchor");
+                        egui::ComboBox::from_id_source("block_anchor")
+                            .selected_text(self.anchor.to_string())
+                            .show_ui(ui, |ui| {
+                                for anchor in [Anchor::Corner, Anchor::FaceCenter, Anchor::Centroid] {
+                                    ui.selectable_value(&mut self.anchor, anchor, anchor.to_string());
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Plane");
+                        egui::ComboBox::from_id_source("block_orientation")
+                            .selected_text(self.orientation.to_string())
+                            .show_ui(ui, |ui| {
+                                for orientation in [Orientation::XY, Orientation::YZ, Orientation::ZX] {
+                                    ui.selectable_value(&mut self.orientation, orientation, orientation.to_string());
+                                }
+                            });
+                        ui.end_row();
+                    },
+                    Placement::TwoCorners => {
+                        point_row(ui, "Corner 1", &mut self.corners[0]);
+                        point_row(ui, "Corner 2", &mut self.corners[1]);
+                    },
+                }
+            });
+    }
+}
+
+/// label and three drag values for a point, as one grid row
+pub(super) fn point_row(ui: &mut egui::Ui, label: &str, point: &mut [f32; 3]) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        for value in point.iter_mut() {
+            ui.add(egui::DragValue::new(value).speed(1.0));
+        }
+    });
+    ui.end_row();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::Point3d;
+
+    #[test]
+    fn edited_block_stays_in_place() {
+        let block = Block::new(Point3d::new(1.0, 2.0, 3.0), Point3d::new(2.0, 4.0, 6.0), Anchor::FaceCenter, Orientation::YZ);
+        let mut dialog = BlockDialog::from_block(&block);
+
+        assert_eq!(dialog.block(), block);
+
+        dialog.placement = Placement::TwoCorners;
+        assert_eq!(dialog.block().bounds(), block.bounds());
+    }
+}
@@ -0,0 +1,67 @@
+
+use crate::model::{Combine, Model, Operation};
+
+/// fields of the combine dialog
+///
+/// Only solids before `index` in the history can be combined.
+#[derive(Debug)]
+pub struct CombineDialog {
+    index: usize,
+    target: Option<usize>,
+    tool: Option<usize>,
+    operation: Operation,
+}
+
+impl CombineDialog {
+    /// new combine at the end of the history, of the last two solids in it
+    pub fn new(model: &Model) -> Self {
+        let index = model.entities().len();
+        let mut solids = (0..index).rev().filter(|&i| model.entity(i).is_solid());
+        let (tool, target) = (solids.next(), solids.next());
+
+        Self { index, target, tool, operation: Operation::Union }
+    }
+
+    /// fields of an existing combine at history `index`
+    pub fn from_combine(index: usize, combine: &Combine) -> Self {
+        Self { index, target: Some(combine.target()), tool: Some(combine.tool()), operation: combine.operation() }
+    }
+
+    /// combine described by the current fields, or `None` without two different solids
+    pub fn combine(&self) -> Option<Combine> {
+        match (self.target, self.tool) {
+            (Some(target), Some(tool)) if target != tool => Some(Combine::new(target, tool, self.operation)),
+            _ => None,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        ui.horizontal(|ui| {
+            for operation in [Operation::Union, Operation::Subtract, Operation::Intersect] {
+                ui.radio_value(&mut self.operation, operation, operation.to_string());
+            }
+        });
+
+        egui::Grid::new("combine_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                for (name, id, value) in [("Target", "combine_target", &mut self.target), ("Tool", "combine_tool", &mut self.tool)] {
+                    ui.label(name);
+                    egui::ComboBox::from_id_source(id)
+                        .selected_text(value.map(label).unwrap_or_default())
+                        .show_ui(ui, |ui| {
+                            for i in (0..earlier).filter(|&i| model.entity(i).is_solid()) {
+                                ui.selectable_value(value, Some(i), label(i));
+                            }
+                        });
+                    ui.end_row();
+                }
+            });
+    }
+}
@@ -0,0 +1,262 @@
+
+use crate::model::{Constraint, Model, ModelEntity, Plane, Status, Transform};
+use crate::prelude::*;
+
+use super::block::{BlockDialog, point_row};
+use super::combine::CombineDialog;
+use super::extrude::ExtrudeDialog;
+use super::fillet::FilletDialog;
+use super::hole::HoleDialog;
+use super::pattern::PatternDialog;
+use super::revolve::RevolveDialog;
+
+/// parameters of a history entity being edited
+#[derive(Debug)]
+pub struct EditDialog {
+    index: usize,
+    name: String,
+    kind: EditKind,
+}
+
+#[derive(Debug)]
+enum EditKind {
+    Block(BlockDialog),
+    /// dimensions are the constraint index with the edited constraint, and the status is of the last solve
+    Sketch { origin: [f32; 3], normal: [f32; 3], dimensions: Vec<(usize, Constraint)>, status: Option<Status> },
+    Imported(Transform),
+    Extrude(ExtrudeDialog),
+    Revolve(RevolveDialog),
+    Hole(HoleDialog),
+    Combine(CombineDialog),
+    /// fillets and chamfers
+    Fillet(FilletDialog),
+    Pattern(PatternDialog),
+}
+
+impl EditDialog {
+    pub fn new(index: usize, entity: &ModelEntity) -> Self {
+        let kind = match entity {
+            ModelEntity::Block(block) => EditKind::Block(BlockDialog::from_block(block)),
+            ModelEntity::Sketch(sketch) => EditKind::Sketch {
+                origin: sketch.plane().origin().to_array(),
+                normal: sketch.plane().normal().to_array(),
+                dimensions: sketch.constraints().iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.value().is_some())
+                    .map(|(i, c)| (i, *c))
+                    .collect(),
+                status: None,
+            },
+            ModelEntity::ImportedModel(model) => EditKind::Imported(model.transform()),
+            ModelEntity::Extrude(extrude) => EditKind::Extrude(ExtrudeDialog::from_extrude(index, extrude, false)),
+            ModelEntity::Cut(cut) => EditKind::Extrude(ExtrudeDialog::from_extrude(index, cut, true)),
+            ModelEntity::Hole(hole) => EditKind::Hole(HoleDialog::from_hole(index, hole)),
+            ModelEntity::Revolve(revolve) => EditKind::Revolve(RevolveDialog::from_revolve(index, revolve)),
+            ModelEntity::Combine(combine) => EditKind::Combine(CombineDialog::from_combine(index, combine)),
+            ModelEntity::Fillet(_) | ModelEntity::Chamfer(_) => EditKind::Fillet(FilletDialog::from_entity(index, entity)),
+            ModelEntity::Pattern(pattern) => EditKind::Pattern(PatternDialog::from_pattern(index, pattern)),
+        };
+
+        Self { index, name: entity.to_string(), kind }
+    }
+
+    /// history index of the entity
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn title(&self) -> String {
+        format!("Edit {}", self.name)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        match &mut self.kind {
+            EditKind::Block(dialog) => dialog.ui(ui),
+            EditKind::Extrude(dialog) => dialog.ui(ui, model),
+            EditKind::Revolve(dialog) => dialog.ui(ui, model),
+            EditKind::Hole(dialog) => dialog.ui(ui, model),
+            EditKind::Combine(dialog) => dialog.ui(ui, model),
+            EditKind::Fillet(dialog) => dialog.ui(ui, model),
+            EditKind::Pattern(dialog) => dialog.ui(ui, model),
+            EditKind::Sketch { origin, normal, dimensions, status } => {
+                egui::Grid::new("edit_table")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        point_row(ui, "Plane origin", origin);
+                        point_row(ui, "Plane normal", normal);
+
+                        for (i, constraint) in dimensions.iter_mut() {
+                            let Some(mut value) = constraint.value() else { continue };
+
+                            ui.label(format!("{} {}", constraint, *i + 1));
+                            if ui.add(egui::DragValue::new(&mut value).speed(0.1).clamp_range(constraint.range())).changed() {
+                                constraint.set_value(value);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if *status == Some(Status::OverConstrained) {
+                    ui.colored_label(ui.visuals().error_fg_color, "Dimensions cannot be met, the sketch was not changed");
+                }
+            },
+            EditKind::Imported(transform) => {
+                egui::Grid::new("edit_table")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut translation = transform.translation.to_array();
+                        point_row(ui, "Translation", &mut translation);
+                        transform.translation = translation.into();
+
+                        let mut rotation = transform.rotation.to_array();
+                        point_row(ui, "Rotation (°)", &mut rotation);
+                        transform.rotation = rotation.into();
+
+                        ui.label("Scale");
+                        ui.add(egui::DragValue::new(&mut transform.scale).speed(0.1).clamp_range(0.001..=f32::MAX));
+                        ui.end_row();
+                    });
+            },
+        }
+    }
+
+    /// whether the last [`apply`](Self::apply) left something unchanged because it could not be met
+    pub fn rejected(&self, model: &Model) -> bool {
+        match &self.kind {
+            EditKind::Sketch { status, .. } => *status == Some(Status::OverConstrained),
+            EditKind::Hole(dialog) => dialog.error(model).is_some(),
+            EditKind::Fillet(dialog) => dialog.error(model).is_some(),
+            _ => false,
+        }
+    }
+
+    /// entity being edited, with the edited parameters, or `None` if none of them changed
+    pub fn apply(&mut self, model: &Model) -> Option<ModelEntity> {
+        // a dialog opened on the entity as it is makes the entity as it is, unresolved like the edit
+        // and a rejected edit keeps the entity as it is
+        let edited = self.edited(model);
+        let current = Self::new(self.index, model.entity(self.index)).edited(model);
+
+        (!same(&edited, &current) && !same(&edited, model.entity(self.index))).then_some(edited)
+    }
+
+    /// entity being edited, with the edited parameters
+    fn edited(&mut self, model: &Model) -> ModelEntity {
+        let entity = model.entity(self.index);
+        match (&mut self.kind, entity) {
+            (EditKind::Block(dialog), ModelEntity::Block(_)) => ModelEntity::Block(dialog.block()),
+            (EditKind::Sketch { origin, normal, dimensions, status }, ModelEntity::Sketch(sketch)) => {
+                let mut sketch = sketch.clone();
+
+                // dimensions that the sketch no longer has were removed under the dialog
+                *status = None;
+                for (index, constraint) in dimensions.iter() {
+                    let (Some(current), Some(value)) = (sketch.constraints().get(*index).and_then(Constraint::value), constraint.value()) else {
+                        continue;
+                    };
+
+                    if current != value {
+                        let solved = sketch.set_dimension(*index, value);
+                        log::debug!("Sketch dimension {} set to {}: {}", index + 1, value, solved);
+
+                        if *status != Some(Status::OverConstrained) {
+                            *status = Some(solved);
+                        }
+                    }
+                }
+
+                // keep the sketch axes unless the plane was turned, and a zero normal turns nothing
+                let normal = Point3d::from(*normal).normalize();
+                let plane = match normal == sketch.plane().normal() || normal.length() == 0.0 {
+                    true => sketch.plane().at((*origin).into()),
+                    false => Plane::new((*origin).into(), normal),
+                };
+                sketch.set_plane(plane);
+
+                ModelEntity::Sketch(sketch)
+            },
+            (EditKind::Imported(transform), ModelEntity::ImportedModel(model)) => {
+                let mut model = model.clone();
+                model.set_transform(*transform);
+
+                ModelEntity::ImportedModel(model)
+            },
+            (EditKind::Extrude(dialog), ModelEntity::Extrude(_) | ModelEntity::Cut(_)) => dialog.entity().unwrap_or_else(|| entity.clone()),
+            (EditKind::Revolve(dialog), ModelEntity::Revolve(_)) => match dialog.revolve(model) {
+                Some(revolve) => ModelEntity::Revolve(revolve),
+                None => entity.clone(),
+            },
+            (EditKind::Hole(dialog), ModelEntity::Hole(_)) => match dialog.error(model) {
+                None => ModelEntity::Hole(dialog.hole()),
+                Some(_) => entity.clone(),
+            },
+            (EditKind::Fillet(dialog), ModelEntity::Fillet(_) | ModelEntity::Chamfer(_)) => match dialog.entity() {
+                Some(edited) if dialog.error(model).is_none() => edited,
+                _ => entity.clone(),
+            },
+            (EditKind::Combine(dialog), ModelEntity::Combine(_)) => match dialog.combine() {
+                Some(combine) => ModelEntity::Combine(combine),
+                None => entity.clone(),
+            },
+            (EditKind::Pattern(dialog), ModelEntity::Pattern(_)) => match dialog.pattern() {
+                Some(pattern) => ModelEntity::Pattern(pattern),
+                None => entity.clone(),
+            },
+            // history changed under the dialog, so leave the entity alone
+            _ => entity.clone()
+        }
+    }
+}
+
+/// whether two entities that edit dialogs made have the same parameters
+fn same(a: &ModelEntity, b: &ModelEntity) -> bool {
+    match (a, b) {
+        (ModelEntity::ImportedModel(a), ModelEntity::ImportedModel(b)) => a.transform() == b.transform(),
+        (ModelEntity::Sketch(a), ModelEntity::Sketch(b)) => a == b,
+        (ModelEntity::Block(a), ModelEntity::Block(b)) => a == b,
+        (ModelEntity::Extrude(a), ModelEntity::Extrude(b)) | (ModelEntity::Cut(a), ModelEntity::Cut(b)) => a == b,
+        (ModelEntity::Revolve(a), ModelEntity::Revolve(b)) => a == b,
+        (ModelEntity::Hole(a), ModelEntity::Hole(b)) => a == b,
+        (ModelEntity::Combine(a), ModelEntity::Combine(b)) => a == b,
+        (ModelEntity::Fillet(a), ModelEntity::Fillet(b)) => a == b,
+        (ModelEntity::Chamfer(a), ModelEntity::Chamfer(b)) => a == b,
+        (ModelEntity::Pattern(a), ModelEntity::Pattern(b)) => (a.entity(), a.kind(), a.skip()) == (b.entity(), b.kind(), b.skip()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Direction, Extent, Extrude, Pattern, PatternKind, Sketch};
+    use crate::prelude::fixture::{drill_block, drilled_hole};
+
+    #[test]
+    fn untouched_dialogs_change_nothing() {
+        let mut model = Model::new();
+        let square = vec![Point2d::new(0.0, 0.0), Point2d::new(1.0, 0.0), Point2d::new(1.0, 1.0), Point2d::new(0.0, 0.0)];
+        let row = Direction { direction: Point3d::new(1.0, 0.0, 0.0), count: 2, spacing: 3.0 };
+        for entity in [
+            drill_block(),
+            drilled_hole(0),
+            ModelEntity::Sketch(Sketch::polyline(Plane::xy(), square)),
+            ModelEntity::Extrude(Extrude::new(2, Extent::Blind(2.0))),
+            ModelEntity::Pattern(Pattern::new(1, PatternKind::Linear { first: row, second: None }, vec![])),
+        ] {
+            model.push(entity);
+        }
+
+        for i in 0..5 {
+            assert!(EditDialog::new(i, model.entity(i)).apply(&model).is_none(), "{}", model.entity(i));
+        }
+
+        // moving the sketch plane is an edit
+        let mut dialog = EditDialog::new(2, model.entity(2));
+        if let EditKind::Sketch { origin, .. } = &mut dialog.kind {
+            *origin = [0.0, 0.0, 1.0];
+        }
+        assert!(dialog.apply(&model).is_some());
+    }
+}
@@ -0,0 +1,153 @@
+
+use crate::model::{Extent, Extrude, Model, ModelEntity};
+
+use super::sketch::face_label;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Blind,
+    Symmetric,
+    UpToFace,
+}
+
+/// fields of the extrude dialog, which also describes cuts
+///
+/// Only entities before `index` in the history can be extruded or extruded up to.
+#[derive(Debug)]
+pub struct ExtrudeDialog {
+    index: usize,
+    sketch: Option<usize>,
+    cut: bool,
+
+    kind: Kind,
+    distance: f32,
+
+    /// history index and face index, for [`Kind::UpToFace`]
+    entity: usize,
+    face: usize,
+}
+
+impl ExtrudeDialog {
+    /// new extrusion or cut at the end of the history, of the last sketch in it
+    pub fn new(model: &Model, cut: bool) -> Self {
+        let index = model.entities().len();
+        let sketch = (0..index).rev().find(|&i| matches!(model.entity(i), ModelEntity::Sketch(_)));
+
+        // cuts go into the face a sketch was placed on
+        let distance = match cut {
+            true => -10.0,
+            false => 10.0,
+        };
+
+        Self { index, sketch, cut, kind: Kind::Blind, distance, entity: 0, face: 0 }
+    }
+
+    /// fields of an existing extrusion or cut at history `index`
+    pub fn from_extrude(index: usize, extrude: &Extrude, cut: bool) -> Self {
+        let mut dialog = Self { index, sketch: Some(extrude.sketch()), cut, kind: Kind::Blind, distance: 10.0, entity: 0, face: 0 };
+        match extrude.extent() {
+            Extent::Blind(distance) => dialog.distance = distance,
+            Extent::Symmetric(distance) => {
+                dialog.kind = Kind::Symmetric;
+                dialog.distance = distance;
+            },
+            Extent::UpToFace { entity, face } => {
+                dialog.kind = Kind::UpToFace;
+                dialog.entity = entity;
+                dialog.face = face;
+            },
+        }
+
+        dialog
+    }
+
+    /// extrusion described by the current fields, or `None` without a sketch to extrude
+    pub fn extrude(&self) -> Option<Extrude> {
+        let extent = match self.kind {
+            Kind::Blind => Extent::Blind(self.distance),
+            Kind::Symmetric => Extent::Symmetric(self.distance),
+            Kind::UpToFace => Extent::UpToFace { entity: self.entity, face: self.face },
+        };
+
+        self.sketch.map(|sketch| Extrude::new(sketch, extent))
+    }
+
+    /// the extrusion, or the cut, described by the current fields
+    pub fn entity(&self) -> Option<ModelEntity> {
+        self.extrude().map(|extrude| match self.cut {
+            true => ModelEntity::Cut(extrude),
+            false => ModelEntity::Extrude(extrude),
+        })
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.cut {
+            true => "Cut",
+            false => "Extrude",
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.kind, Kind::Blind, "Blind");
+            ui.radio_value(&mut self.kind, Kind::Symmetric, "Symmetric");
+            ui.radio_value(&mut self.kind, Kind::UpToFace, "Up to face");
+        });
+
+        egui::Grid::new("extrude_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                ui.label("Sketch");
+                egui::ComboBox::from_id_source("extrude_sketch")
+                    .selected_text(self.sketch.map(label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for i in (0..earlier).filter(|&i| matches!(model.entity(i), ModelEntity::Sketch(_))) {
+                            ui.selectable_value(&mut self.sketch, Some(i), label(i));
+                        }
+                    });
+                ui.end_row();
+
+                match self.kind {
+                    Kind::Blind | Kind::Symmetric => {
+                        ui.label("Distance");
+                        ui.add(egui::DragValue::new(&mut self.distance).speed(0.1));
+                        ui.end_row();
+                    },
+                    Kind::UpToFace => {
+                        ui.label("Entity");
+                        egui::ComboBox::from_id_source("extrude_entity")
+                            .selected_text(label(self.entity))
+                            .show_ui(ui, |ui| {
+                                for i in 0..earlier {
+                                    if ui.selectable_value(&mut self.entity, i, label(i)).changed() {
+                                        self.face = 0;
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        let faces = match self.entity < earlier {
+                            true => model.faces(self.entity),
+                            false => &[],
+                        };
+
+                        ui.label("Face");
+                        egui::ComboBox::from_id_source("extrude_face")
+                            .selected_text(faces.get(self.face).map(|f| face_label(self.face, f)).unwrap_or_default())
+                            .show_ui(ui, |ui| {
+                                for (i, face) in faces.iter().enumerate() {
+                                    ui.selectable_value(&mut self.face, i, face_label(i, face));
+                                }
+                            });
+                        ui.end_row();
+                    },
+                }
+            });
+    }
+}
@@ -0,0 +1,180 @@
+
+use std::cell::RefCell;
+
+use crate::model::{Chamfer, ChamferSize, Fillet, Model, ModelEntity};
+
+/// fields that decide whether a fillet or chamfer can be made
+type Fields = (Option<usize>, Option<usize>, bool, f32, f32, Option<f32>);
+
+/// fields of the fillet dialog, which also describes chamfers
+///
+/// Only edges of entities before `index` in the history can be rounded or bevelled.
+#[derive(Debug)]
+pub struct FilletDialog {
+    index: usize,
+    entity: Option<usize>,
+    edge: Option<usize>,
+    chamfer: bool,
+
+    radius: f32,
+    distance: f32,
+    /// angle from the first face, or `None` for equal distances
+    angle: Option<f32>,
+
+    /// fields last checked against the model, and why they cannot be made
+    checked: RefCell<Option<(Fields, Option<&'static str>)>>,
+}
+
+impl FilletDialog {
+    /// new fillet or chamfer at the end of the history, on the last entity with straight edges
+    pub fn new(model: &Model, chamfer: bool) -> Self {
+        let index = model.entities().len();
+        let entity = (0..index).rev().find(|&i| !model.blend_edges(i).is_empty());
+
+        Self { index, entity, edge: None, chamfer, radius: 1.0, distance: 1.0, angle: None, checked: RefCell::new(None) }
+    }
+
+    /// fields of an existing fillet or chamfer at history `index`
+    pub fn from_entity(index: usize, entity: &ModelEntity) -> Self {
+        let mut dialog = Self { index, entity: None, edge: None, chamfer: false, radius: 1.0, distance: 1.0, angle: None, checked: RefCell::new(None) };
+        match entity {
+            ModelEntity::Fillet(fillet) => {
+                dialog.entity = Some(fillet.entity());
+                dialog.edge = Some(fillet.edge());
+                dialog.radius = fillet.radius();
+            },
+            ModelEntity::Chamfer(chamfer) => {
+                dialog.entity = Some(chamfer.entity());
+                dialog.edge = Some(chamfer.edge());
+                dialog.chamfer = true;
+                match chamfer.size() {
+                    ChamferSize::Distance(distance) => dialog.distance = distance,
+                    ChamferSize::DistanceAngle { distance, angle } => {
+                        dialog.distance = distance;
+                        dialog.angle = Some(angle);
+                    },
+                }
+            },
+            _ => (),
+        }
+
+        dialog
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.chamfer {
+            true => "Chamfer",
+            false => "Fillet",
+        }
+    }
+
+    /// the fillet, or the chamfer, described by the current fields, or `None` without an edge
+    pub fn entity(&self) -> Option<ModelEntity> {
+        let (entity, edge) = (self.entity?, self.edge?);
+
+        Some(match self.chamfer {
+            true => {
+                let size = match self.angle {
+                    Some(angle) => ChamferSize::DistanceAngle { distance: self.distance, angle },
+                    None => ChamferSize::Distance(self.distance),
+                };
+
+                ModelEntity::Chamfer(Chamfer::new(entity, edge, size))
+            },
+            false => ModelEntity::Fillet(Fillet::new(entity, edge, self.radius)),
+        })
+    }
+
+    /// why the fillet or chamfer cannot be made on the chosen edge, or `None` if it can
+    ///
+    /// The edge is looked up in the entity's boundary, so the answer is kept until the fields change.
+    pub fn error(&self, model: &Model) -> Option<&'static str> {
+        let fields = (self.entity, self.edge, self.chamfer, self.radius, self.distance, self.angle);
+        if let Some((checked, error)) = *self.checked.borrow() {
+            if checked == fields {
+                return error;
+            }
+        }
+
+        let entity = self.entity.filter(|&i| i < self.index.min(model.entities().len()))?;
+        let error = match self.entity()? {
+            ModelEntity::Fillet(fillet) => fillet.error(model.entity(entity)),
+            ModelEntity::Chamfer(chamfer) => chamfer.error(model.entity(entity)),
+            _ => None,
+        };
+        *self.checked.borrow_mut() = Some((fields, error));
+
+        error
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        egui::Grid::new("fillet_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                ui.label("Entity");
+                egui::ComboBox::from_id_source("fillet_entity")
+                    .selected_text(self.entity.map(label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for i in (0..earlier).filter(|&i| !model.blend_edges(i).is_empty()) {
+                            if ui.selectable_value(&mut self.entity, Some(i), label(i)).changed() {
+                                self.edge = None;
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                // edges are told apart by where their middle is
+                let edges = match self.entity.filter(|&i| i < earlier) {
+                    Some(i) => model.blend_edges(i),
+                    None => &[],
+                };
+                let edge_label = |edge: usize| edges.iter()
+                    .find(|(i, _)| *i == edge)
+                    .map(|(i, mid)| format!("Edge {} at ({:.2}, {:.2}, {:.2})", i + 1, mid.x, mid.y, mid.z))
+                    .unwrap_or_default();
+
+                ui.label("Edge");
+                egui::ComboBox::from_id_source("fillet_edge")
+                    .selected_text(self.edge.map(edge_label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for (i, _) in edges.iter() {
+                            ui.selectable_value(&mut self.edge, Some(*i), edge_label(*i));
+                        }
+                    });
+                ui.end_row();
+
+                match self.chamfer {
+                    true => {
+                        ui.label("Distance");
+                        ui.add(egui::DragValue::new(&mut self.distance).speed(0.1).clamp_range(0.001..=f32::MAX));
+                        ui.end_row();
+
+                        let mut angled = self.angle.is_some();
+                        ui.label("Angle (°)");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut angled, "");
+                            let mut angle = self.angle.unwrap_or(45.0);
+                            ui.add_enabled(angled, egui::DragValue::new(&mut angle).speed(1.0).clamp_range(1.0..=89.0));
+                            self.angle = angled.then_some(angle);
+                        });
+                        ui.end_row();
+                    },
+                    false => {
+                        ui.label("Radius");
+                        ui.add(egui::DragValue::new(&mut self.radius).speed(0.1).clamp_range(0.001..=f32::MAX));
+                        ui.end_row();
+                    },
+                }
+            });
+
+        if let Some(error) = self.error(model) {
+            ui.colored_label(ui.visuals().error_fg_color, format!("The {}", error));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+
+use std::cell::RefCell;
+
+use crate::model::{Hole, HoleKind, Model};
+use crate::prelude::*;
+
+use super::sketch::face_label;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Simple,
+    Counterbore,
+    Countersink,
+    Tapped,
+}
+
+/// fields of the hole dialog
+///
+/// Only faces of entities before `index` in the history can be drilled.
+#[derive(Debug)]
+pub struct HoleDialog {
+    index: usize,
+    entity: usize,
+    face: usize,
+    position: [f32; 2],
+    diameter: f32,
+    through: bool,
+    depth: f32,
+
+    kind: Kind,
+    /// counterbore or countersink diameter
+    top_diameter: f32,
+    counterbore_depth: f32,
+    countersink_angle: f32,
+    callout: String,
+    thread_depth: f32,
+
+    /// last hole checked against the model, and why it cannot be made
+    checked: RefCell<Option<(Hole, Option<&'static str>)>>,
+}
+
+impl Default for HoleDialog {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            entity: 0,
+            face: 0,
+            position: [0.0, 0.0],
+            diameter: 5.0,
+            through: true,
+            depth: 10.0,
+
+            kind: Kind::Simple,
+            top_diameter: 10.0,
+            counterbore_depth: 5.0,
+            countersink_angle: 90.0,
+            callout: String::from("M6x1.0"),
+            thread_depth: 8.0,
+
+            checked: RefCell::new(None),
+        }
+    }
+}
+
+impl HoleDialog {
+    /// new hole at the end of the history, on the last solid in it
+    pub fn new(model: &Model) -> Self {
+        let index = model.entities().len();
+        let entity = (0..index).rev().find(|&i| model.entity(i).is_solid()).unwrap_or_default();
+
+        Self { index, entity, ..Self::default() }
+    }
+
+    /// fields of an existing hole at history `index`
+    pub fn from_hole(index: usize, hole: &Hole) -> Self {
+        let mut dialog = Self {
+            index,
+            entity: hole.entity(),
+            face: hole.face(),
+            position: hole.position().to_array(),
+            diameter: hole.diameter(),
+            through: hole.depth().is_none(),
+            depth: hole.depth().unwrap_or(10.0),
+            ..Self::default()
+        };
+
+        match hole.kind() {
+            HoleKind::Simple => dialog.kind = Kind::Simple,
+            HoleKind::Counterbore { diameter, depth } => {
+                dialog.kind = Kind::Counterbore;
+                dialog.top_diameter = *diameter;
+                dialog.counterbore_depth = *depth;
+            },
+            HoleKind::Countersink { diameter, angle } => {
+                dialog.kind = Kind::Countersink;
+                dialog.top_diameter = *diameter;
+                dialog.countersink_angle = *angle;
+            },
+            HoleKind::Tapped { callout, thread_depth } => {
+                dialog.kind = Kind::Tapped;
+                dialog.callout = callout.clone();
+                dialog.thread_depth = *thread_depth;
+            },
+        }
+
+        dialog
+    }
+
+    /// hole described by the current fields
+    pub fn hole(&self) -> Hole {
+        let kind = match self.kind {
+            Kind::Simple => HoleKind::Simple,
+            Kind::Counterbore => HoleKind::Counterbore { diameter: self.top_diameter, depth: self.counterbore_depth },
+            Kind::Countersink => HoleKind::Countersink { diameter: self.top_diameter, angle: self.countersink_angle },
+            Kind::Tapped => HoleKind::Tapped { callout: self.callout.clone(), thread_depth: self.thread_depth },
+        };
+        let depth = (!self.through).then_some(self.depth);
+
+        Hole::new(self.entity, self.face, Point2d::new(self.position[0], self.position[1]), self.diameter, depth, kind)
+    }
+
+    /// why the hole cannot be made, or `None` if it can
+    ///
+    /// Through holes are measured against every solid before them, so the answer is kept
+    /// until the fields change.
+    pub fn error(&self, model: &Model) -> Option<&'static str> {
+        let hole = self.hole();
+        let mut checked = self.checked.borrow_mut();
+        match &*checked {
+            Some((last, error)) if *last == hole => *error,
+            _ => {
+                let error = model.hole_error(self.index, &hole);
+                *checked = Some((hole, error));
+
+                error
+            },
+        }
+    }
+
+    /// whether the chosen face exists and the hole can be made
+    pub fn is_valid(&self, model: &Model) -> bool {
+        self.entity < self.index.min(model.entities().len()) && self.face < model.faces(self.entity).len() && self.error(model).is_none()
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.kind, Kind::Simple, "Simple");
+            ui.radio_value(&mut self.kind, Kind::Counterbore, "Counterbore");
+            ui.radio_value(&mut self.kind, Kind::Countersink, "Countersink");
+            ui.radio_value(&mut self.kind, Kind::Tapped, "Tapped");
+        });
+
+        egui::Grid::new("hole_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                ui.label("Entity");
+                egui::ComboBox::from_id_source("hole_entity")
+                    .selected_text(label(self.entity))
+                    .show_ui(ui, |ui| {
+                        for i in (0..earlier).filter(|&i| model.entity(i).is_solid()) {
+                            if ui.selectable_value(&mut self.entity, i, label(i)).changed() {
+                                self.face = 0;
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                let faces = match self.entity < earlier {
+                    true => model.faces(self.entity),
+                    false => &[],
+                };
+
+                ui.label("Face");
+                egui::ComboBox::from_id_source("hole_face")
+                    .selected_text(faces.get(self.face).map(|f| face_label(self.face, f)).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for (i, face) in faces.iter().enumerate() {
+                            ui.selectable_value(&mut self.face, i, face_label(i, face));
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Position on face");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.position[0]).speed(0.1).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut self.position[1]).speed(0.1).prefix("y: "));
+                });
+                ui.end_row();
+
+                let diameter = match self.kind {
+                    Kind::Tapped => "Tap drill diameter",
+                    _ => "Diameter",
+                };
+                ui.label(diameter);
+                ui.add(egui::DragValue::new(&mut self.diameter).speed(0.1).clamp_range(0.001..=f32::MAX));
+                ui.end_row();
+
+                ui.label("Depth");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.through, "Through all");
+                    ui.add_enabled(!self.through, egui::DragValue::new(&mut self.depth).speed(0.1).clamp_range(0.001..=f32::MAX));
+                });
+                ui.end_row();
+
+                match self.kind {
+                    Kind::Simple => (),
+                    Kind::Counterbore => {
+                        ui.label("Counterbore diameter");
+                        ui.add(egui::DragValue::new(&mut self.top_diameter).speed(0.1).clamp_range(self.diameter..=f32::MAX));
+                        ui.end_row();
+
+                        ui.label("Counterbore depth");
+                        ui.add(egui::DragValue::new(&mut self.counterbore_depth).speed(0.1).clamp_range(0.001..=f32::MAX));
+                        ui.end_row();
+                    },
+                    Kind::Countersink => {
+                        ui.label("Countersink diameter");
+                        ui.add(egui::DragValue::new(&mut self.top_diameter).speed(0.1).clamp_range(self.diameter..=f32::MAX));
+                        ui.end_row();
+
+                        ui.label("Countersink angle (°)");
+                        ui.add(egui::DragValue::new(&mut self.countersink_angle).speed(1.0).clamp_range(1.0..=179.0));
+                        ui.end_row();
+                    },
+                    Kind::Tapped => {
+                        ui.label("Thread");
+                        ui.text_edit_singleline(&mut self.callout);
+                        ui.end_row();
+
+                        ui.label("Thread depth");
+                        ui.add(egui::DragValue::new(&mut self.thread_depth).speed(0.1).clamp_range(0.001..=f32::MAX));
+                        ui.end_row();
+                    },
+                }
+            });
+
+        if let Some(error) = self.error(model) {
+            ui.colored_label(ui.visuals().error_fg_color, format!("The {}", error));
+        }
+    }
+}
@@ -1,15 +1,22 @@
 
 use std::path::PathBuf;
 
+use crate::model::Model;
+
 pub enum MenuResult {
     New,
     Open(PathBuf),
     Save(PathBuf),
     ImportObj(PathBuf),
+    ImportStl(PathBuf),
+    ExportObj(PathBuf),
+    ExportStl(PathBuf),
+    Undo,
+    Redo,
     Settings,
 }
 
-pub fn ui(ui: &mut egui::Ui, control_flow: &mut winit::event_loop::ControlFlow) -> Option<MenuResult> {
+pub fn ui(ui: &mut egui::Ui, control_flow: &mut winit::event_loop::ControlFlow, model: &Model) -> Option<MenuResult> {
     let mut result = None;
     
     ui.menu_button("Menu", |ui| {
@@ -20,24 +27,34 @@ pub fn ui(ui: &mut egui::Ui, control_flow: &mut winit::event_loop::ControlFlow)
 
         if ui.button("Open").clicked() {
             log::debug!("Menu > Open");
-            result = open().map(|path| MenuResult::Open(path));
+            result = open().map(MenuResult::Open);
         }
 
         if ui.button("Save").clicked() {
             log::debug!("Menu > Save");
-            result = save().map(|path| MenuResult::Save(path));
+            result = save().map(MenuResult::Save);
         }
 
         ui.menu_button("Import", |ui| {
             if ui.button("Waveform (.obj)").clicked() {
                 log::debug!("Menu > Import > Waveform");
-                result = load().map(|path| MenuResult::ImportObj(path));
+                result = load().map(MenuResult::ImportObj);
+            }
+
+            if ui.button("Stereolithography (.stl)").clicked() {
+                log::debug!("Menu > Import > Stereolithography");
+                result = load_stl().map(MenuResult::ImportStl);
             }
         });
         ui.menu_button("Export", |ui| {
+            if ui.button("Waveform (.obj)").clicked() {
+                log::debug!("Menu > Export > Waveform");
+                result = export_obj().map(MenuResult::ExportObj);
+            }
+
             if ui.button("Stereolithography (.stl)").clicked() {
-                eprintln!("impl stl export menu button");
-                ui.close_menu();
+                log::debug!("Menu > Export > Stereolithography");
+                result = export_stl().map(MenuResult::ExportStl);
             }
         });
 
@@ -49,7 +66,23 @@ pub fn ui(ui: &mut egui::Ui, control_flow: &mut winit::event_loop::ControlFlow)
             control_flow.set_exit();
         }
 
-        if let Some(_) = result {
+        if result.is_some() {
+            ui.close_menu();
+        }
+    });
+
+    ui.menu_button("Edit", |ui| {
+        if ui.add_enabled(model.can_undo(), egui::Button::new("Undo").shortcut_text("Ctrl+Z")).clicked() {
+            log::debug!("Edit > Undo");
+            result = Some(MenuResult::Undo);
+        }
+
+        if ui.add_enabled(model.can_redo(), egui::Button::new("Redo").shortcut_text("Ctrl+Y")).clicked() {
+            log::debug!("Edit > Redo");
+            result = Some(MenuResult::Redo);
+        }
+
+        if result.is_some() {
             ui.close_menu();
         }
     });
@@ -85,4 +118,40 @@ fn load() -> Option<PathBuf> {
         .add_filter("Wavefront", &["obj"])
         .show_open_single_file()
         .unwrap_or_default()
-}
\ No newline at end of file
+}
+
+fn export_obj() -> Option<PathBuf> {
+    native_dialog::FileDialog::new()
+        .set_location(&std::env::current_dir().unwrap())
+        .add_filter("Wavefront", &["obj"])
+        .show_save_single_file()
+        .unwrap_or_default()
+
+        // set extension
+        .map(|mut path| {
+            path.set_extension("obj");
+            path
+        })
+}
+
+fn load_stl() -> Option<PathBuf> {
+    native_dialog::FileDialog::new()
+        .set_location(&std::env::current_dir().unwrap())
+        .add_filter("Stereolithography", &["stl"])
+        .show_open_single_file()
+        .unwrap_or_default()
+}
+
+fn export_stl() -> Option<PathBuf> {
+    native_dialog::FileDialog::new()
+        .set_location(&std::env::current_dir().unwrap())
+        .add_filter("Stereolithography", &["stl"])
+        .show_save_single_file()
+        .unwrap_or_default()
+
+        // set extension
+        .map(|mut path| {
+            path.set_extension("stl");
+            path
+        })
+}
@@ -1,8 +1,27 @@
 
+pub mod block;
+pub mod combine;
+pub mod edit;
+pub mod extrude;
+pub mod fillet;
+pub mod hole;
 pub mod menu;
+pub mod pattern;
+pub mod revolve;
+pub mod sketch;
 
+// only returned once per frame, so boxing the entity buys nothing
+#[allow(clippy::large_enum_variant)]
 pub enum UiDrawResult {
     EnterSketcher,
     ExitSketcher(Option<crate::model::ModelEntity>),
     ShowBlockDialog,
+    ShowExtrudeDialog,
+    ShowRevolveDialog,
+    ShowCutDialog,
+    ShowHoleDialog,
+    ShowCombineDialog,
+    ShowFilletDialog,
+    ShowChamferDialog,
+    ShowPatternDialog,
 }
@@ -0,0 +1,190 @@
+
+use crate::model::{Direction, Model, Pattern, PatternKind};
+use crate::prelude::*;
+
+use super::block::point_row;
+
+/// fields of the pattern dialog, for linear and circular patterns
+///
+/// Only entities before `index` in the history can be copied.
+#[derive(Debug)]
+pub struct PatternDialog {
+    index: usize,
+    entity: Option<usize>,
+    circular: bool,
+
+    /// direction, count and spacing
+    first: ([f32; 3], usize, f32),
+    second: Option<([f32; 3], usize, f32)>,
+
+    origin: [f32; 3],
+    axis: [f32; 3],
+    count: usize,
+    angle: f32,
+
+    /// instance numbers to leave out, as typed, counted from 1 for the original
+    skip: String,
+}
+
+impl PatternDialog {
+    /// new pattern at the end of the history, of the last entity in it
+    pub fn new(model: &Model) -> Self {
+        let index = model.entities().len();
+
+        Self { entity: index.checked_sub(1), ..Self::defaults(index) }
+    }
+
+    fn defaults(index: usize) -> Self {
+        Self {
+            index,
+            entity: None,
+            circular: false,
+            first: ([1.0, 0.0, 0.0], 2, 10.0),
+            second: None,
+            origin: [0.0; 3],
+            axis: [0.0, 0.0, 1.0],
+            count: 4,
+            angle: 360.0,
+            skip: String::new(),
+        }
+    }
+
+    /// fields of an existing pattern at history `index`
+    pub fn from_pattern(index: usize, pattern: &Pattern) -> Self {
+        let mut dialog = Self { entity: Some(pattern.entity()), ..Self::defaults(index) };
+        match pattern.kind() {
+            PatternKind::Linear { first, second } => {
+                dialog.first = fields(first);
+                dialog.second = second.map(fields);
+            },
+            PatternKind::Circular { origin, axis, count, angle } => {
+                dialog.circular = true;
+                dialog.origin = origin.to_array();
+                dialog.axis = axis.to_array();
+                dialog.count = count;
+                dialog.angle = angle;
+            },
+        }
+        dialog.skip = pattern.skip().iter().map(|n| (n + 1).to_string()).collect::<Vec<_>>().join(", ");
+
+        dialog
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.circular {
+            true => "Circular Pattern",
+            false => "Linear Pattern",
+        }
+    }
+
+    /// pattern described by the current fields, or `None` without an entity or a direction
+    pub fn pattern(&self) -> Option<Pattern> {
+        let entity = self.entity?;
+        let direction = |(direction, count, spacing): ([f32; 3], usize, f32)| {
+            let direction = Point3d::from(direction);
+            (direction.length() > 0.0).then_some(Direction { direction, count, spacing })
+        };
+
+        let kind = match self.circular {
+            true => {
+                let axis = Point3d::from(self.axis);
+                if axis.length() == 0.0 {
+                    return None;
+                }
+
+                PatternKind::Circular { origin: self.origin.into(), axis, count: self.count, angle: self.angle }
+            },
+            false => PatternKind::Linear {
+                first: direction(self.first)?,
+                second: match self.second {
+                    Some(second) => Some(direction(second)?),
+                    None => None,
+                },
+            },
+        };
+
+        // the original cannot be skipped, and neither can numbers that are not instances
+        let skip = self.skip.split([',', ' '])
+            .filter_map(|n| n.trim().parse::<usize>().ok())
+            .filter(|&n| n > 1)
+            .map(|n| n - 1)
+            .collect();
+
+        Some(Pattern::new(entity, kind, skip))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.circular, false, "Linear");
+            ui.radio_value(&mut self.circular, true, "Circular");
+        });
+
+        egui::Grid::new("pattern_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                ui.label("Entity");
+                egui::ComboBox::from_id_source("pattern_entity")
+                    .selected_text(self.entity.map(label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for i in 0..earlier {
+                            ui.selectable_value(&mut self.entity, Some(i), label(i));
+                        }
+                    });
+                ui.end_row();
+
+                let count = |ui: &mut egui::Ui, value: &mut usize| {
+                    ui.label("Count");
+                    ui.add(egui::DragValue::new(value).speed(0.1).clamp_range(1..=1000));
+                    ui.end_row();
+                };
+
+                match self.circular {
+                    true => {
+                        point_row(ui, "Axis origin", &mut self.origin);
+                        point_row(ui, "Axis direction", &mut self.axis);
+                        count(ui, &mut self.count);
+
+                        ui.label("Angle (°)");
+                        ui.add(egui::DragValue::new(&mut self.angle).speed(1.0).clamp_range(-360.0..=360.0));
+                        ui.end_row();
+                    },
+                    false => {
+                        let mut two = self.second.is_some();
+                        for (name, fields) in [("First direction", Some(&mut self.first)), ("Second direction", self.second.as_mut())] {
+                            let Some((direction, n, spacing)) = fields else {
+                                continue;
+                            };
+
+                            point_row(ui, name, direction);
+                            count(ui, n);
+
+                            ui.label("Spacing");
+                            ui.add(egui::DragValue::new(spacing).speed(0.1));
+                            ui.end_row();
+                        }
+
+                        ui.label("");
+                        if ui.checkbox(&mut two, "Second direction").changed() {
+                            self.second = two.then_some(([0.0, 1.0, 0.0], 2, self.first.2));
+                        }
+                        ui.end_row();
+                    },
+                }
+
+                ui.label("Skip").on_hover_text("instance numbers, where 1 is the original");
+                ui.text_edit_singleline(&mut self.skip);
+                ui.end_row();
+            });
+    }
+}
+
+/// direction, count and spacing of `direction`, as edited
+fn fields(direction: Direction) -> ([f32; 3], usize, f32) {
+    (direction.direction.to_array(), direction.count, direction.spacing)
+}
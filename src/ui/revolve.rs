@@ -0,0 +1,111 @@
+
+use crate::model::{Axis, Model, ModelEntity, Revolve, Shape};
+
+/// fields of the revolve dialog
+///
+/// Only sketches before `index` in the history can be revolved.
+#[derive(Debug)]
+pub struct RevolveDialog {
+    index: usize,
+    sketch: Option<usize>,
+
+    axis: Axis,
+    angle: f32,
+    segments: usize,
+}
+
+impl RevolveDialog {
+    /// new revolve at the end of the history, of the last sketch in it
+    pub fn new(model: &Model) -> Self {
+        let index = model.entities().len();
+        let sketch = (0..index).rev().find(|&i| matches!(model.entity(i), ModelEntity::Sketch(_)));
+
+        Self { index, sketch, axis: Axis::Y, angle: 360.0, segments: 48 }
+    }
+
+    /// fields of an existing revolve at history `index`
+    pub fn from_revolve(index: usize, revolve: &Revolve) -> Self {
+        Self {
+            index,
+            sketch: Some(revolve.sketch()),
+            axis: revolve.axis(),
+            angle: revolve.angle(),
+            segments: revolve.segments(),
+        }
+    }
+
+    /// revolve described by the current fields, or `None` without a sketch to revolve
+    /// or with a profile across the axis
+    pub fn revolve(&self, model: &Model) -> Option<Revolve> {
+        let sketch = self.sketch?;
+        if self.crosses_axis(model) {
+            return None;
+        }
+
+        Some(Revolve::new(sketch, self.axis, self.angle, self.segments))
+    }
+
+    /// whether the chosen sketch has a profile on both sides of the axis
+    fn crosses_axis(&self, model: &Model) -> bool {
+        match self.sketch.filter(|&i| i < self.index.min(model.entities().len())).map(|i| model.entity(i)) {
+            Some(ModelEntity::Sketch(sketch)) => Revolve::crosses_axis(sketch, self.axis),
+            _ => false,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        let entities = model.entities();
+        let earlier = self.index.min(entities.len());
+
+        egui::Grid::new("revolve_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let label = |i: usize| entities.get(i).map(|name| format!("{} {}", i + 1, name)).unwrap_or_default();
+
+                ui.label("Sketch");
+                egui::ComboBox::from_id_source("revolve_sketch")
+                    .selected_text(self.sketch.map(label).unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for i in (0..earlier).filter(|&i| matches!(model.entity(i), ModelEntity::Sketch(_))) {
+                            if ui.selectable_value(&mut self.sketch, Some(i), label(i)).changed() {
+                                self.axis = Axis::Y;
+                            }
+                        }
+                    });
+                ui.end_row();
+
+                // lines of the chosen sketch, besides the model axes
+                let lines: Vec<Axis> = match self.sketch.filter(|&i| i < earlier).map(|i| model.entity(i)) {
+                    Some(ModelEntity::Sketch(sketch)) => sketch.elements().iter()
+                        .enumerate()
+                        .filter(|(_, e)| matches!(e.shape, Shape::Line { .. }))
+                        .map(|(i, _)| Axis::Line(i))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                ui.label("Axis");
+                egui::ComboBox::from_id_source("revolve_axis")
+                    .selected_text(self.axis.to_string())
+                    .show_ui(ui, |ui| {
+                        for axis in [Axis::X, Axis::Y, Axis::Z].into_iter().chain(lines) {
+                            ui.selectable_value(&mut self.axis, axis, axis.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Angle (°)");
+                ui.add(egui::DragValue::new(&mut self.angle).speed(1.0).clamp_range(-360.0..=360.0));
+                ui.end_row();
+
+                ui.label("Segments per turn");
+                ui.add(egui::DragValue::new(&mut self.segments).speed(1.0).clamp_range(3..=360));
+                ui.end_row();
+            });
+
+        if self.crosses_axis(model) {
+            ui.colored_label(ui.visuals().error_fg_color, "The profile crosses the axis");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+
+use crate::model::{Model, Plane};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Choice {
+    XY,
+    YZ,
+    ZX,
+    Offset,
+    Face,
+}
+
+/// plane prompt shown before entering the sketcher
+#[derive(Debug)]
+pub struct SketchPlaneDialog {
+    choice: Choice,
+
+    /// standard plane and distance along its normal, for [`Choice::Offset`]
+    base: Choice,
+    offset: f32,
+
+    /// history index and face index, for [`Choice::Face`]
+    entity: usize,
+    face: usize,
+}
+
+impl Default for SketchPlaneDialog {
+    fn default() -> Self {
+        Self {
+            choice: Choice::XY,
+
+            base: Choice::XY,
+            offset: 0.0,
+
+            entity: 0,
+            face: 0,
+        }
+    }
+}
+
+impl SketchPlaneDialog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// selected plane, or `None` if the selected face does not exist
+    pub fn plane(&self, model: &Model) -> Option<Plane> {
+        match self.choice {
+            Choice::XY | Choice::YZ | Choice::ZX => Some(standard(self.choice)),
+            Choice::Offset => {
+                let base = standard(self.base);
+
+                Some(base.at(base.normal() * self.offset))
+            },
+            Choice::Face => match self.entity < model.entities().len() {
+                true => model.faces(self.entity).get(self.face).cloned(),
+                false => None,
+            },
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, model: &Model) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.choice, Choice::XY, "XY");
+            ui.radio_value(&mut self.choice, Choice::YZ, "YZ");
+            ui.radio_value(&mut self.choice, Choice::ZX, "ZX");
+            ui.radio_value(&mut self.choice, Choice::Offset, "Offset");
+            ui.radio_value(&mut self.choice, Choice::Face, "Face");
+        });
+
+        egui::Grid::new("sketch_plane_table")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                match self.choice {
+                    Choice::Offset => {
+                        ui.label("Base plane");
+                        egui::ComboBox::from_id_source("sketch_plane_base")
+                            .selected_text(name(self.base))
+                            .show_ui(ui, |ui| {
+                                for base in [Choice::XY, Choice::YZ, Choice::ZX] {
+                                    ui.selectable_value(&mut self.base, base, name(base));
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Offset");
+                        ui.add(egui::DragValue::new(&mut self.offset).speed(1.0));
+                        ui.end_row();
+                    },
+                    Choice::Face => {
+                        let entities = model.entities();
+
+                        ui.label("Entity");
+                        egui::ComboBox::from_id_source("sketch_plane_entity")
+                            .selected_text(entities.get(self.entity).cloned().unwrap_or_default())
+                            .show_ui(ui, |ui| {
+                                for (i, entity) in entities.iter().enumerate() {
+                                    if ui.selectable_value(&mut self.entity, i, entity).changed() {
+                                        self.face = 0;
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
+                        let faces = match self.entity < entities.len() {
+                            true => model.faces(self.entity),
+                            false => &[],
+                        };
+
+                        ui.label("Face");
+                        egui::ComboBox::from_id_source("sketch_plane_face")
+                            .selected_text(faces.get(self.face).map(|f| face_label(self.face, f)).unwrap_or_default())
+                            .show_ui(ui, |ui| {
+                                for (i, face) in faces.iter().enumerate() {
+                                    ui.selectable_value(&mut self.face, i, face_label(i, face));
+                                }
+                            });
+                        ui.end_row();
+                    },
+                    _ => (),
+                }
+            });
+    }
+}
+
+fn standard(choice: Choice) -> Plane {
+    match choice {
+        Choice::YZ => Plane::yz(),
+        Choice::ZX => Plane::zx(),
+        _ => Plane::xy(),
+    }
+}
+
+fn name(choice: Choice) -> &'static str {
+    match choice {
+        Choice::XY => "XY",
+        Choice::YZ => "YZ",
+        Choice::ZX => "ZX",
+        Choice::Offset => "Offset",
+        Choice::Face => "Face",
+    }
+}
+
+pub(super) fn face_label(index: usize, face: &Plane) -> String {
+    let n = face.normal();
+
+    format!("{} <{:.2}, {:.2}, {:.2}>", index + 1, n.x, n.y, n.z)
+}